@@ -0,0 +1,256 @@
+use crate::error::{Result, SteganographyError};
+use image::error::{ImageError, UnsupportedErrorKind};
+use image::{DynamicImage, ImageFormat};
+use std::fmt;
+use std::io::Cursor;
+
+/// Palettes with fewer colors than this leave little texture for coefficients to hide in
+const FEW_PALETTE_COLORS: usize = 16;
+
+/// Source color model that loses fidelity when the carrier is converted to RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CarrierColorWarning {
+    /// CMYK JPEG, whose conversion to RGB can shift colors noticeably
+    Cmyk,
+    /// Indexed-palette PNG with the given number of palette entries
+    Palette { color_count: usize },
+}
+
+impl fmt::Display for CarrierColorWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CarrierColorWarning::Cmyk => write!(
+                f,
+                "input is a CMYK JPEG; converting it to RGB may shift colors and reduce carrier fidelity, which weakens robustness"
+            ),
+            CarrierColorWarning::Palette { color_count } if *color_count < FEW_PALETTE_COLORS => {
+                write!(
+                    f,
+                    "input is a palette image with only {} colors; large flat areas make per-block embedding fragile and visible",
+                    color_count
+                )
+            }
+            CarrierColorWarning::Palette { color_count } => write!(
+                f,
+                "input is a palette image with {} colors; the output will be a full-color JPEG and may differ visibly from the original",
+                color_count
+            ),
+        }
+    }
+}
+
+/// Encoder used to write the steganographic image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Lossy JPEG at the requested quality, which the embedding strength is tuned for
+    #[default]
+    Jpeg,
+    /// Lossless PNG
+    Png,
+    /// Lossless WebP
+    WebP,
+}
+
+impl OutputFormat {
+    /// Every output format, in command-line order
+    pub const ALL: [OutputFormat; 3] = [Self::Jpeg, Self::Png, Self::WebP];
+
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::WebP => "webp",
+        }
+    }
+
+    /// Looks up an output format by its name or one of its file extensions
+    pub fn from_name(format_name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|output_format| {
+            output_format.name() == format_name || output_format.extensions().contains(&format_name)
+        })
+    }
+
+    /// Infers the output format from a path's file extension
+    pub fn from_path(output_path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(output_path)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|output_format| output_format.extensions().contains(&extension.as_str()))
+    }
+
+    /// File extensions that mark this format, the preferred one first
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Jpeg => &["jpg", "jpeg"],
+            Self::Png => &["png"],
+            Self::WebP => &["webp"],
+        }
+    }
+
+    /// Whether encoding discards detail, so `--quality` applies
+    pub fn is_lossy(self) -> bool {
+        self == Self::Jpeg
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Inspects encoded image bytes for color models that `to_rgb8` converts lossily
+///
+/// Only the header is decoded. Formats other than JPEG and PNG are never flagged.
+pub fn inspect_carrier_color_model(encoded_image: &[u8]) -> Result<Option<CarrierColorWarning>> {
+    match image::guess_format(encoded_image) {
+        Ok(ImageFormat::Jpeg) => {
+            let mut jpeg_decoder = jpeg_decoder::Decoder::new(Cursor::new(encoded_image));
+            jpeg_decoder
+                .read_info()
+                .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
+
+            let is_cmyk = jpeg_decoder
+                .info()
+                .is_some_and(|info| info.pixel_format == jpeg_decoder::PixelFormat::CMYK32);
+            Ok(is_cmyk.then_some(CarrierColorWarning::Cmyk))
+        }
+        Ok(ImageFormat::Png) => {
+            let png_reader = png::Decoder::new(Cursor::new(encoded_image))
+                .read_info()
+                .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
+            let png_info = png_reader.info();
+
+            if png_info.color_type != png::ColorType::Indexed {
+                return Ok(None);
+            }
+            let color_count = png_info
+                .palette
+                .as_ref()
+                .map_or(0, |palette| palette.len() / 3);
+            Ok(Some(CarrierColorWarning::Palette { color_count }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Lists the image formats this build can decode
+///
+/// Support depends on the `image` crate features compiled in, so each format is probed
+/// with an empty buffer; only formats without a decoder report themselves unsupported.
+pub fn decodable_image_formats() -> Vec<ImageFormat> {
+    ImageFormat::all()
+        .filter(|&image_format| {
+            !is_unsupported_format(image::load_from_memory_with_format(&[], image_format).err())
+        })
+        .collect()
+}
+
+/// Lists the image formats this build can encode through the `image` crate
+pub fn encodable_image_formats() -> Vec<ImageFormat> {
+    let probe_image = DynamicImage::new_rgb8(1, 1);
+    ImageFormat::all()
+        .filter(|&image_format| {
+            !is_unsupported_format(
+                probe_image
+                    .write_to(&mut Cursor::new(Vec::new()), image_format)
+                    .err(),
+            )
+        })
+        .collect()
+}
+
+/// Returns whether an image operation failed because the format isn't compiled in
+fn is_unsupported_format(image_error: Option<ImageError>) -> bool {
+    matches!(
+        image_error,
+        Some(ImageError::Unsupported(ref unsupported_error))
+            if matches!(unsupported_error.kind(), UnsupportedErrorKind::Format(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jpeg_encoder::{ColorType, Encoder};
+
+    #[test]
+    fn test_cmyk_jpeg_is_flagged() {
+        let cmyk_pixels = [40u8, 80, 120, 10].repeat(16 * 16);
+        let mut jpeg_buffer = Vec::new();
+        Encoder::new(&mut jpeg_buffer, 90)
+            .encode(&cmyk_pixels, 16, 16, ColorType::Cmyk)
+            .unwrap();
+
+        assert_eq!(
+            inspect_carrier_color_model(&jpeg_buffer).unwrap(),
+            Some(CarrierColorWarning::Cmyk)
+        );
+
+        let mut rgb_buffer = Vec::new();
+        Encoder::new(&mut rgb_buffer, 90)
+            .encode(&[128u8; 16 * 16 * 3], 16, 16, ColorType::Rgb)
+            .unwrap();
+        assert_eq!(inspect_carrier_color_model(&rgb_buffer).unwrap(), None);
+    }
+
+    #[test]
+    fn test_palette_png_reports_color_count() {
+        let mut png_buffer = Vec::new();
+        {
+            let mut png_encoder = png::Encoder::new(&mut png_buffer, 4, 4);
+            png_encoder.set_color(png::ColorType::Indexed);
+            png_encoder.set_depth(png::BitDepth::Eight);
+            png_encoder.set_palette(vec![0, 0, 0, 255, 255, 255, 255, 0, 0]);
+            let mut png_writer = png_encoder.write_header().unwrap();
+            png_writer
+                .write_image_data(&[0, 1, 2, 0].repeat(4))
+                .unwrap();
+        }
+
+        let carrier_warning = inspect_carrier_color_model(&png_buffer).unwrap();
+        assert_eq!(
+            carrier_warning,
+            Some(CarrierColorWarning::Palette { color_count: 3 })
+        );
+        assert!(carrier_warning
+            .unwrap()
+            .to_string()
+            .contains("only 3 colors"));
+    }
+
+    #[test]
+    fn test_output_format_is_inferred_from_extension() {
+        assert_eq!(
+            OutputFormat::from_path("hidden.JPG"),
+            Some(OutputFormat::Jpeg)
+        );
+        assert_eq!(
+            OutputFormat::from_path("out/hidden.png"),
+            Some(OutputFormat::Png)
+        );
+        assert_eq!(
+            OutputFormat::from_path("hidden.webp"),
+            Some(OutputFormat::WebP)
+        );
+        assert_eq!(OutputFormat::from_path("hidden"), None);
+        assert_eq!(OutputFormat::from_path("hidden.gif"), None);
+
+        assert_eq!(OutputFormat::from_name("jpg"), Some(OutputFormat::Jpeg));
+        assert!(OutputFormat::Jpeg.is_lossy());
+        assert!(!OutputFormat::Png.is_lossy());
+    }
+
+    #[test]
+    fn test_format_probing() {
+        let decodable_formats = decodable_image_formats();
+        assert!(decodable_formats.contains(&ImageFormat::Jpeg));
+        assert!(decodable_formats.contains(&ImageFormat::Png));
+
+        assert!(encodable_image_formats().contains(&ImageFormat::Png));
+    }
+}
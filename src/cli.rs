@@ -1,10 +1,69 @@
-use crate::crypto::CryptographicEngine;
+use crate::carrier::{
+    decodable_image_formats, encodable_image_formats, inspect_carrier_color_model, OutputFormat,
+};
+use crate::compression::{decompress_gzip, is_gzip_stream};
+use crate::crypto::{
+    CryptographicEngine, FrameOptions, KeyEncoding, DEFAULT_REPETITION_FACTOR, PASSPHRASE_SALT_SIZE,
+};
+use crate::embedding::{
+    AdaptiveStrength, DctEmbeddingAlgorithm, EmbeddingChannel, EmbeddingConfiguration,
+    EmbeddingPositions,
+};
 use crate::error::{Result, SteganographyError};
-use crate::steganography::SteganographyEngine;
-use clap::{Parser, Subcommand};
-use image::{io::Reader as ImageReader, ImageBuffer, Rgb, RgbImage};
+use crate::frame::{BlockVisitation, CodingScheme, ContentType, FrameHeader, Framing};
+use crate::output::{
+    diagnostic, json_object, progress_bars_supported, route_diagnostics_to_stderr,
+    show_progress_bars, RawEncoding,
+};
+use crate::reed_solomon;
+use crate::steganography::{
+    bit_error_rate, byte_error_count, ExtractionResult, SteganographyEngine,
+    DEFAULT_MACRO_BLOCK_SCALE, DEFAULT_PROTECTED_HEADER_COPIES,
+};
+use base64::{engine::general_purpose, Engine as _};
+use clap::{Args, Parser, Subcommand};
+use image::{
+    io::Reader as ImageReader, DynamicImage, ImageBuffer, ImageFormat, Rgb, RgbImage, RgbaImage,
+};
+use std::fs::{self, File, OpenOptions};
+use std::io::BufReader;
+use std::ops::RangeInclusive;
 use std::path::Path;
 
+/// Extraction confidence below which the user is warned about fragile recovery
+const LOW_EXTRACTION_CONFIDENCE: f32 = 0.6;
+
+/// Payload likelihood from which `detect` reports that an image likely carries hidden data
+const LIKELY_PAYLOAD_PRESENCE: f64 = 0.5;
+
+/// Name, without extension, of the key file the batch command writes to its output directory
+const BATCH_KEY_FILE_STEM: &str = "batch";
+
+/// JPEG quality used by hide when `--quality` isn't given
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Quality the embedding strength is tuned for when the output is lossless
+///
+/// Nothing is quantized away, so the gentlest embedding keeps the changes least visible.
+const LOSSLESS_EMBEDDING_QUALITY: u8 = 100;
+
+/// Qualities `hide --auto-quality` searches, lowest first
+///
+/// Below 50 the embedding strength needed to survive quantization makes the changes visible.
+const AUTO_QUALITY_RANGE: RangeInclusive<u8> = 50..=100;
+
+/// Sides of the DCT blocks `--block-size` accepts
+const SUPPORTED_BLOCK_SIZES: [usize; 3] = [8, 16, 32];
+
+/// JPEG qualities listed in the capacity planning table
+const CAPACITY_TABLE_QUALITIES: [u8; 5] = [50, 65, 75, 85, 95];
+
+/// PSNR in decibels below which `hide --report-quality` warns that embedding may be visible
+const DEFAULT_MIN_PSNR: f64 = 35.0;
+
+/// Bytes shown per line when hex-dumping a binary payload
+const HEX_DUMP_LINE_LENGTH: usize = 16;
+
 /// Command-line interface for the steganography tool
 #[derive(Parser)]
 #[command(name = "steg")]
@@ -13,62 +72,734 @@ use std::path::Path;
 pub struct CommandLineInterface {
     #[command(subcommand)]
     pub command: SteganographyCommand,
+
+    /// Print the result as one JSON object, sending progress messages to stderr
+    #[arg(
+        long,
+        global = true,
+        help = "Print the result of hide or extract as a single JSON object on stdout; progress messages go to stderr"
+    )]
+    pub json: bool,
+
+    /// Draw a progress bar on stderr while blocks are embedded or read
+    #[arg(
+        long,
+        global = true,
+        help = "Show a progress bar on stderr while embedding or extracting on large images"
+    )]
+    pub progress: bool,
 }
 
 /// Available steganography commands
 #[derive(Subcommand)]
 pub enum SteganographyCommand {
     /// Hide encrypted data in an image
-    Hide {
-        /// Input image file path
-        #[arg(short, long, help = "Path to the input image file")]
+    Hide(HideArguments),
+
+    /// Extract and decrypt data from a steganographic image
+    Extract(ExtractArguments),
+
+    /// Report how much data an image can hold
+    Capacity {
+        /// Carrier image file path
+        #[arg(short, long, help = "Path to the image to inspect")]
         input: String,
-        
-        /// Output image file path (without extension)
-        #[arg(short, long, help = "Output path for the steganographic image")]
-        output: String,
-        
-        /// Secret data to hide (will be encrypted)
-        #[arg(short, long, help = "Secret message to hide in the image")]
+
+        /// Print capacity and robustness across common JPEG qualities
+        #[arg(
+            long,
+            help = "Show quantization step and recommended payload for JPEG qualities 50-95"
+        )]
+        table: bool,
+
+        /// Error correction to size the largest message for, matching `hide --ecc`
+        #[arg(
+            long,
+            default_value = "repetition",
+            value_parser = parse_coding_scheme,
+            help = "Size the largest message for repetition or rs (Reed-Solomon) error correction"
+        )]
+        ecc: CodingScheme,
+
+        /// Channels to count, matching `hide --channels`
+        #[arg(
+            long,
+            default_value = "luma",
+            value_parser = parse_embedding_channel,
+            help = "Count capacity in the luma, chroma or all YCbCr channels"
+        )]
+        channels: EmbeddingChannel,
+    },
+
+    /// Measure how many embedded bits flip when an image is recompressed
+    RobustnessTest {
+        /// Steganographic image file path
+        #[arg(short, long, help = "Path to the steganographic image")]
+        input: String,
+
+        /// JPEG quality to recompress with
+        #[arg(
+            short,
+            long,
+            default_value = "75",
+            help = "JPEG quality (1-100) used to recompress the image"
+        )]
+        quality: u8,
+    },
+
+    /// Check in memory that a message survives hiding and JPEG compression
+    Verify {
+        /// Carrier image file path
+        #[arg(short, long, help = "Path to the carrier image")]
+        input: String,
+
+        /// Message to round-trip
+        #[arg(short, long, help = "Secret message to test with")]
         data: String,
-        
-        /// Optional encryption key file path
-        #[arg(short, long, help = "Path to encryption key file (will generate if not provided)")]
-        key_file: Option<String>,
-        
-        /// JPEG quality for output image
+
+        /// JPEG quality to encode with
         #[arg(
-            short, 
-            long, 
+            short,
+            long,
             default_value = "85",
-            help = "JPEG quality (1-100, higher = better quality but larger file)"
+            help = "JPEG quality (1-100) the hidden image is encoded with"
         )]
         quality: u8,
     },
-    
-    /// Extract and decrypt data from a steganographic image
-    Extract {
+
+    /// Hide the same message in every image of a directory with one shared key
+    Batch {
+        /// Directory of carrier images
+        #[arg(long, help = "Directory whose images are used as carriers")]
+        input_dir: String,
+
+        /// Directory receiving the steganographic images and the key
+        #[arg(
+            long,
+            help = "Directory to write the hidden images and batch.key to (created if missing)"
+        )]
+        output_dir: String,
+
+        /// Message to hide in every image
+        #[arg(short, long, help = "Secret message to hide in every image")]
+        data: String,
+
+        /// JPEG quality for the output images
+        #[arg(
+            short,
+            long,
+            default_value_t = DEFAULT_JPEG_QUALITY,
+            help = "JPEG quality (1-100) of the output images"
+        )]
+        quality: u8,
+    },
+
+    /// Re-encrypt the payload of a steganographic image under a new key
+    RotateKey {
+        /// Steganographic image file path
+        #[arg(short, long, help = "Path to the steganographic image")]
+        input: String,
+
+        /// Key the payload is currently encrypted with
+        #[arg(long, help = "Key file, or the key itself, the image was hidden with")]
+        old_key: String,
+
+        /// Key file for the new key, loaded if it exists and generated otherwise
+        #[arg(
+            long,
+            help = "Key file to encrypt with; generated if missing (default: <output>.key)"
+        )]
+        new_key_file: Option<String>,
+
+        /// Output file path for the re-encrypted image
+        #[arg(short, long, help = "Output path for the re-encrypted image")]
+        output: String,
+
+        /// JPEG quality for the output image
+        #[arg(
+            short,
+            long,
+            default_value_t = DEFAULT_JPEG_QUALITY,
+            help = "JPEG quality (1-100) of the output image"
+        )]
+        quality: u8,
+    },
+
+    /// Check a key against a steganographic image without revealing the payload
+    VerifyKey {
         /// Steganographic image file path
         #[arg(short, long, help = "Path to the steganographic image")]
         input: String,
-        
-        /// Encryption key (file path or base64 string)
-        #[arg(short, long, help = "Encryption key file path or base64 key string")]
+
+        /// Key to check
+        #[arg(short, long, help = "Key file, or the key itself, to check")]
         key: String,
-        
-        /// Expected data length in bytes (optional)
-        #[arg(short, long, help = "Expected data length in bytes (optional optimization)")]
-        length: Option<usize>,
     },
-    
+
+    /// Estimate without a key whether an image carries a hidden payload
+    Detect {
+        /// Image file path
+        #[arg(short, long, help = "Path to the image to check")]
+        input: String,
+    },
+
+    /// Print an image's dimensions, format, block count, capacity and payload likelihood
+    Info {
+        /// Image file path
+        #[arg(short, long, help = "Path to the image to inspect")]
+        input: String,
+    },
+
+    /// List the image formats this build can read and write
+    Formats,
+
     /// Generate a demonstration with test images
     Demo,
 }
 
+/// Arguments for the hide command
+#[derive(Args)]
+pub struct HideArguments {
+    /// Input image file path
+    #[arg(
+        short,
+        long,
+        required_unless_present = "split",
+        help = "Path to the input image file"
+    )]
+    pub input: Option<String>,
+
+    /// Output image file path (without extension)
+    #[arg(short, long, help = "Output path for the steganographic image")]
+    pub output: String,
+
+    /// Encoder for the output image, otherwise taken from the output extension
+    #[arg(
+        long,
+        value_parser = parse_output_format,
+        help = "Write the output as jpeg, png or webp (lossless); defaults to the output path's extension, else jpeg"
+    )]
+    pub format: Option<OutputFormat>,
+
+    /// Secret data to hide (will be encrypted)
+    #[arg(
+        short,
+        long,
+        required_unless_present = "data_file",
+        conflicts_with = "data_file",
+        help = "Secret message to hide in the image"
+    )]
+    pub data: Option<String>,
+
+    /// File whose contents are hidden instead of a message
+    #[arg(
+        long,
+        visible_alias = "file",
+        help = "Hide the contents of a file; its content type is detected unless --content-type is given"
+    )]
+    pub data_file: Option<String>,
+
+    /// Content type recorded in the frame instead of the detected one
+    #[arg(
+        long,
+        value_parser = parse_content_type,
+        help = "Record the payload as text, binary, gzip or json instead of detecting it"
+    )]
+    pub content_type: Option<ContentType>,
+
+    /// Optional encryption key file path
+    #[arg(
+        short,
+        long,
+        help = "Path to encryption key file (will generate if not provided)"
+    )]
+    pub key_file: Option<String>,
+
+    /// Passphrase to derive the key from instead of using a key file
+    #[arg(
+        long,
+        conflicts_with_all = ["key_file", "seed_positions_from_key"],
+        help = "Derive the key from a passphrase with Argon2id instead of using a key file"
+    )]
+    pub passphrase: Option<String>,
+
+    /// Encoding of the key line in a newly generated key file
+    #[arg(
+        long,
+        default_value = "base64",
+        value_parser = parse_key_encoding,
+        help = "Write a generated key as base64 or hex (extract reads either)"
+    )]
+    pub output_key_format: KeyEncoding,
+
+    /// JPEG quality for output image
+    #[arg(
+        short,
+        long,
+        help = "JPEG quality (1-100, higher = better quality but larger file; default 85, ignored for lossless formats)"
+    )]
+    pub quality: Option<u8>,
+
+    /// Pick the lowest JPEG quality at which the payload survives a round trip
+    #[arg(
+        long,
+        conflicts_with = "quality",
+        help = "Search for the lowest JPEG quality (50-100) at which the payload still extracts, to minimize file size"
+    )]
+    pub auto_quality: bool,
+
+    /// Compress the message before encryption
+    #[arg(long, help = "Deflate the message before encryption to fit more text")]
+    pub compress: bool,
+
+    /// Error-correcting code for the encrypted payload, recorded in the frame header
+    #[arg(
+        long,
+        default_value = "repetition",
+        value_parser = parse_coding_scheme,
+        help = "Protect the payload with repetition (5x, soft-decision) or rs (Reed-Solomon, far less overhead); extract reads it from the header"
+    )]
+    pub ecc: CodingScheme,
+
+    /// Copies of each encrypted byte when repetition coding
+    #[arg(
+        long,
+        default_value_t = DEFAULT_REPETITION_FACTOR,
+        help = "Repeat each encrypted byte N times (odd; lower fits more data in lossless channels); extract needs the same N"
+    )]
+    pub repetition: usize,
+
+    /// Seal the payload with ChaCha20-Poly1305
+    #[arg(
+        long,
+        help = "Add a Poly1305 tag so a wrong key or damaged image fails clearly (16 more bytes)"
+    )]
+    pub authenticated: bool,
+
+    /// Mark the payload with start and end sentinels instead of a length header
+    #[arg(
+        long,
+        default_value = "header",
+        value_parser = parse_framing,
+        conflicts_with_all = ["data_file", "content_type", "passphrase", "compress", "ecc", "authenticated", "pad_to_block", "block_visitation", "alpha_channel"],
+        help = "Frame the payload with a length header, or with start and end sentinels that survive header damage (text only; extract needs the same --framing)"
+    )]
+    pub framing: Framing,
+
+    /// Pad the carrier to a multiple of the block size before embedding
+    #[arg(
+        long,
+        help = "Pad the image to a multiple of 8 pixels so edge blocks embed reliably (changes output dimensions)"
+    )]
+    pub pad_to_block: bool,
+
+    /// Derive per-block embedding positions from the encryption key
+    #[arg(
+        long,
+        help = "Choose embedding positions per block from the key so they can't be located without it"
+    )]
+    pub seed_positions_from_key: bool,
+
+    /// DCT coefficients carrying each bit instead of the built-in mid-frequency set
+    #[arg(
+        long,
+        value_parser = parse_embedding_positions,
+        conflicts_with = "seed_positions_from_key",
+        help = "Carry each bit in these DCT coefficients, e.g. \"4,1;1,4;3,2\" (row,column below the block size, not 0,0); extract needs the same list"
+    )]
+    pub positions: Option<EmbeddingPositions>,
+
+    /// Embed extra copies of the frame header
+    #[arg(
+        long,
+        help = "Embed the header three times so a damaged header doesn't lose the whole message"
+    )]
+    pub protect_header: bool,
+
+    /// Embed each bit in a 32x32 region instead of an 8x8 block
+    #[arg(
+        long,
+        help = "Embed each bit across a 32x32 region so it survives downscaling (1/16 of the capacity)"
+    )]
+    pub macro_block: bool,
+
+    /// Side of the DCT block carrying each bit
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        value_parser = parse_block_size,
+        conflicts_with = "macro_block",
+        help = "Embed each bit in an 8x8, 16x16 or 32x32 DCT block; larger survives more downscaling"
+    )]
+    pub block_size: Option<usize>,
+
+    /// Order of the blocks after the header, recorded in the frame header
+    #[arg(
+        long,
+        default_value = "row-major",
+        value_parser = parse_block_visitation,
+        help = "Visit blocks after the header in row-major, column-major, zigzag or key-shuffled (keyed) order (extract reads it from the header)"
+    )]
+    pub block_visitation: BlockVisitation,
+
+    /// YCbCr channels whose blocks carry the payload
+    #[arg(
+        long,
+        default_value = "luma",
+        value_parser = parse_embedding_channel,
+        help = "Embed in the luma, chroma or all YCbCr channels; chroma adds capacity but survives JPEG less well (extract needs the same value)"
+    )]
+    pub channels: EmbeddingChannel,
+
+    /// Scale the embedding strength by each block's texture
+    #[arg(
+        long,
+        help = "Embed more gently in flat blocks and more strongly in textured ones, so smooth areas like sky show fewer artifacts (extract needs nothing extra)"
+    )]
+    pub adaptive_strength: bool,
+
+    /// Hide even if the carrier already seems to hold an embedded payload
+    #[arg(
+        long,
+        help = "Hide even when the carrier looks like it already contains hidden data"
+    )]
+    pub force: bool,
+
+    /// Print the PSNR between the carrier and the steganographic image
+    #[arg(
+        long,
+        help = "Report the PSNR of the embedding and warn when it falls below --min-psnr"
+    )]
+    pub report_quality: bool,
+
+    /// PSNR below which --report-quality warns that the embedding may be visible
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MIN_PSNR,
+        requires = "report_quality",
+        help = "PSNR in dB below which --report-quality warns"
+    )]
+    pub min_psnr: f64,
+
+    /// Run every check and the embedding in memory, but write neither the image nor a key
+    #[arg(
+        long,
+        help = "Check that hiding would succeed and print what would be created, without writing any files"
+    )]
+    pub dry_run: bool,
+
+    /// Decoder to use instead of guessing from the file
+    #[arg(
+        long,
+        value_parser = parse_image_format,
+        help = "Force the input image format (e.g. png, jpeg) instead of guessing it"
+    )]
+    pub input_format: Option<ImageFormat>,
+
+    /// Embed in the alpha channel of an opaque RGBA image instead of the luminance DCT
+    #[arg(
+        long,
+        conflicts_with_all = ["format", "pad_to_block", "seed_positions_from_key", "positions", "protect_header", "macro_block", "block_size", "block_visitation", "channels", "adaptive_strength", "force", "report_quality", "auto_quality"],
+        help = "Hide in the alpha channel of an RGBA image (lossless, saved as PNG)"
+    )]
+    pub alpha_channel: bool,
+
+    /// Covers to spread the payload across, one part per image
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["input", "format", "passphrase", "auto_quality", "framing", "pad_to_block", "seed_positions_from_key", "positions", "protect_header", "macro_block", "block_size", "block_visitation", "channels", "adaptive_strength", "report_quality", "input_format", "dry_run", "alpha_channel"],
+        help = "Comma-separated cover images to split the payload across, saved as <output>-1.jpg, <output>-2.jpg, ..."
+    )]
+    pub split: Option<Vec<String>>,
+}
+
+/// Arguments for the extract command
+#[derive(Args)]
+pub struct ExtractArguments {
+    /// Steganographic image file path
+    #[arg(
+        short,
+        long,
+        required_unless_present = "split",
+        help = "Path to the steganographic image"
+    )]
+    pub input: Option<String>,
+
+    /// Encryption key (file path or base64 string)
+    #[arg(
+        short,
+        long,
+        required_unless_present = "passphrase",
+        help = "Encryption key file path or base64 key string"
+    )]
+    pub key: Option<String>,
+
+    /// Passphrase the key was derived from when hiding
+    #[arg(
+        long,
+        conflicts_with_all = ["key", "seed_positions_from_key"],
+        help = "Passphrase given to hide --passphrase; the salt is read from the image"
+    )]
+    pub passphrase: Option<String>,
+
+    /// Expected data length in bytes (optional)
+    #[arg(
+        short,
+        long,
+        help = "Expected data length in bytes (optional optimization)"
+    )]
+    pub length: Option<usize>,
+
+    /// Where to save the carrier cropped back to its pre-padding dimensions
+    #[arg(
+        long,
+        help = "Save the image cropped back to its original size if it was padded during hiding"
+    )]
+    pub cropped_output: Option<String>,
+
+    /// Derive embedding positions from the key, matching `hide --seed-positions-from-key`
+    #[arg(long, help = "Read embedding positions derived from the key")]
+    pub seed_positions_from_key: bool,
+
+    /// DCT coefficients to read, matching `hide --positions`
+    #[arg(
+        long,
+        value_parser = parse_embedding_positions,
+        conflicts_with = "seed_positions_from_key",
+        help = "Read the DCT coefficients given to hide --positions"
+    )]
+    pub positions: Option<EmbeddingPositions>,
+
+    /// Read a replicated header, matching `hide --protect-header`
+    #[arg(long, help = "Read the header copies embedded by --protect-header")]
+    pub protect_header: bool,
+
+    /// Refuse payloads without an authentication tag
+    #[arg(
+        long,
+        help = "Require a payload hidden with --authenticated (tagged payloads are always verified)"
+    )]
+    pub authenticated: bool,
+
+    /// Find the payload by its sentinels, matching `hide --framing`
+    #[arg(
+        long,
+        default_value = "header",
+        value_parser = parse_framing,
+        conflicts_with_all = ["passphrase", "authenticated", "cropped_output", "alpha_channel"],
+        help = "Read a payload hidden with --framing sentinel by scanning for its markers"
+    )]
+    pub framing: Framing,
+
+    /// Copies of each encrypted byte, matching `hide --repetition`
+    #[arg(
+        long,
+        default_value_t = DEFAULT_REPETITION_FACTOR,
+        help = "Repetition factor given to hide --repetition"
+    )]
+    pub repetition: usize,
+
+    /// Read 32x32 regions, matching `hide --macro-block`
+    #[arg(long, help = "Read data embedded by --macro-block")]
+    pub macro_block: bool,
+
+    /// Side of the pixel region carrying each bit, matching `hide --block-size`
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        value_parser = parse_block_size,
+        conflicts_with = "macro_block",
+        help = "Block size given to hide --block-size"
+    )]
+    pub block_size: Option<usize>,
+
+    /// Channels to read, matching `hide --channels`
+    #[arg(
+        long,
+        default_value = "luma",
+        value_parser = parse_embedding_channel,
+        help = "Read the luma, chroma or all YCbCr channels, as given to hide --channels"
+    )]
+    pub channels: EmbeddingChannel,
+
+    /// Calibrate the voting threshold from this image before decoding
+    #[arg(
+        long,
+        help = "Estimate the embedded amplitude and noise level first and adapt the voting threshold"
+    )]
+    pub noise_floor: bool,
+
+    /// Fail instead of replacing invalid UTF-8 in the recovered message
+    #[arg(
+        long,
+        help = "Fail if the recovered message is not valid UTF-8 instead of showing it lossily"
+    )]
+    pub strict_utf8: bool,
+
+    /// Print the decrypted bytes as they are, without decompressing or decoding them
+    #[arg(
+        long,
+        value_name = "hex|base64",
+        num_args = 0..=1,
+        default_missing_value = "hex",
+        value_parser = parse_raw_encoding,
+        conflicts_with_all = ["strict_utf8", "output_file"],
+        help = "Dump the decrypted bytes as hex (default) or base64 without any UTF-8 decoding"
+    )]
+    pub raw: Option<RawEncoding>,
+
+    /// Decoder to use instead of guessing from the file
+    #[arg(
+        long,
+        value_parser = parse_image_format,
+        help = "Force the input image format (e.g. png, jpeg) instead of guessing it"
+    )]
+    pub input_format: Option<ImageFormat>,
+
+    /// Read from the alpha channel, matching `hide --alpha-channel`
+    #[arg(
+        long,
+        conflicts_with_all = ["seed_positions_from_key", "positions", "protect_header", "macro_block", "block_size", "channels", "noise_floor"],
+        help = "Read data hidden in the alpha channel by --alpha-channel"
+    )]
+    pub alpha_channel: bool,
+
+    /// Stego images holding the parts of a split payload, in any order
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["input", "passphrase", "length", "cropped_output", "seed_positions_from_key", "positions", "protect_header", "framing", "macro_block", "block_size", "channels", "noise_floor", "input_format", "alpha_channel"],
+        help = "Comma-separated images from hide --split to reassemble the payload from"
+    )]
+    pub split: Option<Vec<String>>,
+
+    /// Where to write the recovered payload instead of printing it
+    #[arg(
+        long,
+        visible_alias = "output",
+        help = "Write the recovered payload to this file instead of printing or hex-dumping it"
+    )]
+    pub output_file: Option<String>,
+}
+
+/// Parses an image format name or file extension such as `png` or `jpg`
+fn parse_image_format(format_name: &str) -> std::result::Result<ImageFormat, String> {
+    ImageFormat::from_extension(format_name.to_ascii_lowercase())
+        .ok_or_else(|| format!("unknown image format '{}'", format_name))
+}
+
+/// Parses an output format name, `jpeg`, `png` or `webp`
+fn parse_output_format(format_name: &str) -> std::result::Result<OutputFormat, String> {
+    OutputFormat::from_name(&format_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown output format '{}' (expected jpeg, png or webp)",
+            format_name
+        )
+    })
+}
+
+/// Parses a content type name such as `text` or `json`
+fn parse_content_type(content_type_name: &str) -> std::result::Result<ContentType, String> {
+    ContentType::from_name(&content_type_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown content type '{}' (expected text, binary, gzip or json)",
+            content_type_name
+        )
+    })
+}
+
+/// Parses a block visitation order name such as `zigzag`
+fn parse_block_visitation(visitation_name: &str) -> std::result::Result<BlockVisitation, String> {
+    BlockVisitation::from_name(&visitation_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown block visitation '{}' (expected row-major, column-major or zigzag)",
+            visitation_name
+        )
+    })
+}
+
+/// Parses a list of DCT coefficient positions such as `4,1;1,4;3,2`
+fn parse_embedding_positions(
+    position_list: &str,
+) -> std::result::Result<EmbeddingPositions, String> {
+    let largest_block_size = SUPPORTED_BLOCK_SIZES[SUPPORTED_BLOCK_SIZES.len() - 1];
+    EmbeddingPositions::parse_for_block_size(position_list, largest_block_size)
+        .map_err(|error| error.to_string())
+}
+
+/// Parses a `--block-size` value, one of [`SUPPORTED_BLOCK_SIZES`]
+fn parse_block_size(block_size_text: &str) -> std::result::Result<usize, String> {
+    block_size_text
+        .parse::<usize>()
+        .ok()
+        .filter(|block_size| SUPPORTED_BLOCK_SIZES.contains(block_size))
+        .ok_or_else(|| {
+            format!(
+                "unsupported block size '{}' (expected 8, 16 or 32)",
+                block_size_text
+            )
+        })
+}
+
+/// Parses an embedding channel selection, `luma`, `chroma` or `all`
+fn parse_embedding_channel(channel_name: &str) -> std::result::Result<EmbeddingChannel, String> {
+    EmbeddingChannel::from_name(&channel_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown channels '{}' (expected luma, chroma or all)",
+            channel_name
+        )
+    })
+}
+
+/// Parses a framing name, `header` or `sentinel`
+fn parse_framing(framing_name: &str) -> std::result::Result<Framing, String> {
+    Framing::from_name(&framing_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown framing '{}' (expected header or sentinel)",
+            framing_name
+        )
+    })
+}
+
+/// Parses an error correction name, `repetition` or `rs`
+fn parse_coding_scheme(coding_scheme_name: &str) -> std::result::Result<CodingScheme, String> {
+    CodingScheme::from_name(&coding_scheme_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown error correction '{}' (expected repetition or rs)",
+            coding_scheme_name
+        )
+    })
+}
+
+/// Parses a key encoding name, `base64` or `hex`
+fn parse_key_encoding(encoding_name: &str) -> std::result::Result<KeyEncoding, String> {
+    KeyEncoding::from_name(&encoding_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown key format '{}' (expected base64 or hex)",
+            encoding_name
+        )
+    })
+}
+
+/// Parses a raw dump encoding name, `hex` or `base64`
+fn parse_raw_encoding(encoding_name: &str) -> std::result::Result<RawEncoding, String> {
+    RawEncoding::from_name(&encoding_name.to_ascii_lowercase()).ok_or_else(|| {
+        format!(
+            "unknown raw format '{}' (expected hex or base64)",
+            encoding_name
+        )
+    })
+}
+
 /// Command-line interface handler
-pub struct CommandLineHandler {
-    steganography_engine: SteganographyEngine,
+///
+/// The engine transforms `N`x`N` blocks, 8x8 unless `--block-size` asks for larger ones.
+pub struct CommandLineHandler<const N: usize = 8> {
+    steganography_engine: SteganographyEngine<DctEmbeddingAlgorithm<N>, N>,
     cryptographic_engine: CryptographicEngine,
+    /// Whether the command's result is printed as JSON instead of prose
+    json_output: bool,
 }
 
 impl CommandLineHandler {
@@ -77,140 +808,1799 @@ impl CommandLineHandler {
         Self {
             steganography_engine: SteganographyEngine::new(),
             cryptographic_engine: CryptographicEngine::new(),
+            json_output: false,
+        }
+    }
+
+    /// Converts a decoded image to RGBA, rejecting images that never had an alpha channel
+    fn require_alpha_channel(input_path: &str, decoded_image: &DynamicImage) -> Result<RgbaImage> {
+        if !decoded_image.color().has_alpha() {
+            return Err(SteganographyError::InvalidInput(format!(
+                "{} has no alpha channel to hide data in",
+                input_path
+            )));
+        }
+        Ok(decoded_image.to_rgba8())
+    }
+
+    /// Reads the message or file to hide and the content type to record for it
+    fn read_secret_payload(
+        secret_message: Option<String>,
+        data_file_path: Option<String>,
+        content_type: Option<ContentType>,
+    ) -> Result<(Vec<u8>, ContentType)> {
+        let (secret_data, detected_content_type) = match (secret_message, data_file_path) {
+            (_, Some(data_file_path)) => {
+                let file_data = fs::read(&data_file_path)?;
+                let detected_content_type =
+                    CommandLineHandler::detect_content_type(&data_file_path, &file_data);
+                (file_data, detected_content_type)
+            }
+            (Some(secret_message), None) => (secret_message.into_bytes(), ContentType::Text),
+            (None, None) => {
+                return Err(SteganographyError::InvalidInput(
+                    "Either --data or --data-file is required".to_string(),
+                ))
+            }
+        };
+
+        let content_type = content_type.unwrap_or(detected_content_type);
+        diagnostic!("Payload content type: {}", content_type);
+        Ok((secret_data, content_type))
+    }
+
+    /// Guesses a file's content type from its gzip magic bytes, extension and encoding
+    fn detect_content_type(file_path: &str, file_data: &[u8]) -> ContentType {
+        let is_json_file = Path::new(file_path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+        if is_gzip_stream(file_data) {
+            ContentType::Gzip
+        } else if std::str::from_utf8(file_data).is_err() {
+            ContentType::Binary
+        } else if is_json_file {
+            ContentType::Json
+        } else {
+            ContentType::Text
+        }
+    }
+
+    /// Returns whether a recovered payload should be shown as text rather than bytes
+    ///
+    /// Gzip payloads carry no type of their own, so they count as text if they decompressed
+    /// to valid UTF-8.
+    fn is_text_payload(content_type: ContentType, decrypted_data: &[u8]) -> bool {
+        match content_type {
+            ContentType::Text | ContentType::Json => true,
+            ContentType::Binary => false,
+            ContentType::Gzip => std::str::from_utf8(decrypted_data).is_ok(),
+        }
+    }
+
+    /// Formats bytes as offset, hex and printable ASCII columns, one line per 16 bytes
+    fn format_hex_dump(data: &[u8]) -> String {
+        data.chunks(HEX_DUMP_LINE_LENGTH)
+            .enumerate()
+            .map(|(line_index, line_bytes)| {
+                let hex_column: Vec<String> = line_bytes
+                    .iter()
+                    .map(|data_byte| format!("{:02x}", data_byte))
+                    .collect();
+                let ascii_column: String = line_bytes
+                    .iter()
+                    .map(|&data_byte| {
+                        if data_byte.is_ascii_graphic() || data_byte == b' ' {
+                            data_byte as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                format!(
+                    "{:08x}  {:<width$}  |{}|\n",
+                    line_index * HEX_DUMP_LINE_LENGTH,
+                    hex_column.join(" "),
+                    ascii_column,
+                    width = HEX_DUMP_LINE_LENGTH * 3 - 1
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the key file to load or create: the given path, or one named after the output
+    fn resolve_key_file_path(output_path: &str, key_file_path: &Option<String>) -> String {
+        key_file_path
+            .clone()
+            .unwrap_or_else(|| format!("{}.key", output_path))
+    }
+
+    /// Fails before any embedding work if the image or a newly generated key can't be saved
+    fn ensure_hide_outputs_writable(
+        output_file_path: &str,
+        output_path: &str,
+        key_file_path: &Option<String>,
+    ) -> Result<()> {
+        CommandLineHandler::ensure_writable(output_file_path)?;
+
+        // An existing key file is only read, so it doesn't need to be writable
+        let key_path = CommandLineHandler::resolve_key_file_path(output_path, key_file_path);
+        if !Path::new(&key_path).exists() {
+            CommandLineHandler::ensure_writable(&key_path)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a file can be written at `file_path` without changing what is there
+    ///
+    /// Existing files are opened for writing without truncation; otherwise an empty file is
+    /// created and removed again.
+    fn ensure_writable(file_path: &str) -> Result<()> {
+        let write_probe = if Path::new(file_path).exists() {
+            OpenOptions::new().write(true).open(file_path).map(drop)
+        } else {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(file_path)
+                .and_then(|_| fs::remove_file(file_path))
+        };
+
+        write_probe.map_err(|e| {
+            SteganographyError::IoError(std::io::Error::new(
+                e.kind(),
+                format!("cannot write to {}: {}", file_path, e),
+            ))
+        })
+    }
+}
+
+impl<const N: usize> CommandLineHandler<N> {
+    /// Creates a handler whose engine transforms `M`x`M` blocks, keeping the output mode
+    fn with_block_size<const M: usize>(&self) -> CommandLineHandler<M> {
+        CommandLineHandler {
+            steganography_engine: SteganographyEngine::default(),
+            cryptographic_engine: CryptographicEngine::new(),
+            json_output: self.json_output,
         }
     }
 
     /// Processes the command-line interface and executes the appropriate command
     pub fn process_command(&mut self, cli: CommandLineInterface) -> Result<()> {
+        if cli.json
+            && !matches!(
+                cli.command,
+                SteganographyCommand::Hide(_) | SteganographyCommand::Extract(_)
+            )
+        {
+            return Err(SteganographyError::InvalidInput(
+                "--json is only supported by hide and extract".to_string(),
+            ));
+        }
+        self.json_output = cli.json;
+        route_diagnostics_to_stderr(cli.json);
+        if cli.progress && !progress_bars_supported() {
+            diagnostic!("Note: this build has no progress bars (the `progress` feature is off)");
+        }
+        show_progress_bars(cli.progress);
+
         match cli.command {
-            SteganographyCommand::Hide {
+            SteganographyCommand::Hide(hide_arguments) => self.handle_hide_command(hide_arguments),
+
+            SteganographyCommand::Extract(extract_arguments) => {
+                self.handle_extract_command(extract_arguments)
+            }
+
+            SteganographyCommand::Capacity {
                 input,
-                output,
+                table,
+                ecc,
+                channels,
+            } => self.handle_capacity_command(input, table, ecc, channels),
+
+            SteganographyCommand::RobustnessTest { input, quality } => {
+                self.handle_robustness_test_command(input, quality)
+            }
+
+            SteganographyCommand::Verify {
+                input,
+                data,
+                quality,
+            } => self.handle_verify_command(input, data, quality),
+
+            SteganographyCommand::Batch {
+                input_dir,
+                output_dir,
                 data,
-                key_file,
                 quality,
-            } => self.handle_hide_command(input, output, data, key_file, quality),
+            } => self.handle_batch_command(input_dir, output_dir, data, quality),
+
+            SteganographyCommand::RotateKey {
+                input,
+                old_key,
+                new_key_file,
+                output,
+                quality,
+            } => self.handle_rotate_key_command(input, old_key, new_key_file, output, quality),
 
-            SteganographyCommand::Extract { input, key, length } => {
-                self.handle_extract_command(input, key, length)
+            SteganographyCommand::VerifyKey { input, key } => {
+                self.handle_verify_key_command(input, key)
             }
 
+            SteganographyCommand::Detect { input } => self.handle_detect_command(input),
+
+            SteganographyCommand::Info { input } => self.handle_info_command(input),
+
+            SteganographyCommand::Formats => self.handle_formats_command(),
+
             SteganographyCommand::Demo => self.handle_demo_command(),
         }
     }
 
     /// Handles the hide command to embed data in an image
-    fn handle_hide_command(
-        &mut self,
-        input_path: String,
-        output_path: String,
-        secret_data: String,
-        key_file_path: Option<String>,
-        jpeg_quality: u8,
-    ) -> Result<()> {
-        // Validate JPEG quality parameter
-        if !(1..=100).contains(&jpeg_quality) {
+    fn handle_hide_command(&mut self, hide_arguments: HideArguments) -> Result<()> {
+        if hide_arguments.alpha_channel {
+            return self.handle_alpha_channel_hide(hide_arguments);
+        }
+        if hide_arguments.split.is_some() {
+            return self.handle_split_hide(hide_arguments);
+        }
+        if let Some(block_size) = hide_arguments.block_size.filter(|&size| size != N) {
+            return match block_size {
+                16 => self
+                    .with_block_size::<16>()
+                    .handle_hide_command(hide_arguments),
+                32 => self
+                    .with_block_size::<32>()
+                    .handle_hide_command(hide_arguments),
+                _ => self
+                    .with_block_size::<8>()
+                    .handle_hide_command(hide_arguments),
+            };
+        }
+
+        let HideArguments {
+            input: input_path,
+            output: output_path,
+            format: output_format,
+            data: secret_message,
+            data_file: data_file_path,
+            content_type,
+            key_file: key_file_path,
+            passphrase,
+            output_key_format,
+            quality: requested_quality,
+            auto_quality,
+            compress: compress_payload,
+            ecc,
+            repetition,
+            authenticated,
+            framing,
+            pad_to_block,
+            seed_positions_from_key,
+            positions,
+            protect_header,
+            macro_block,
+            block_visitation,
+            channels,
+            adaptive_strength,
+            force,
+            report_quality,
+            min_psnr,
+            input_format,
+            dry_run,
+            ..
+        } = hide_arguments;
+        // clap requires --input unless --split was given, which is handled above
+        let input_path = input_path.unwrap_or_default();
+
+        let output_format = output_format
+            .or_else(|| OutputFormat::from_path(&output_path))
+            .unwrap_or_default();
+
+        // Validate JPEG quality parameter; lossless output keeps the weakest embedding
+        let jpeg_quality = if output_format.is_lossy() {
+            let jpeg_quality = requested_quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+            if !(1..=100).contains(&jpeg_quality) {
+                return Err(SteganographyError::InvalidInput(
+                    "JPEG quality must be between 1 and 100".to_string(),
+                ));
+            }
+            jpeg_quality
+        } else {
+            if auto_quality {
+                return Err(SteganographyError::InvalidInput(format!(
+                    "--auto-quality only applies to JPEG output, not {}",
+                    output_format
+                )));
+            }
+            if requested_quality.is_some() {
+                diagnostic!(
+                    "Note: --quality only applies to lossy output and is ignored for {}",
+                    output_format
+                );
+            }
+            LOSSLESS_EMBEDDING_QUALITY
+        };
+        self.use_repetition_factor(repetition)?;
+        if block_visitation == BlockVisitation::Keyed && passphrase.is_some() {
             return Err(SteganographyError::InvalidInput(
-                "JPEG quality must be between 1 and 100".to_string(),
+                "--block-visitation keyed needs a key file; a passphrase's salt would be stored in the shuffled blocks it unlocks".to_string(),
             ));
         }
 
+        // Determine output file path with proper extension
+        let output_file_path = self.get_output_file_path(&output_path, output_format);
+        CommandLineHandler::ensure_hide_outputs_writable(
+            &output_file_path,
+            &output_path,
+            &key_file_path,
+        )?;
+
+        // Warn when conversion to RGB is likely to degrade the carrier
+        if let Some(carrier_warning) = inspect_carrier_color_model(&std::fs::read(&input_path)?)? {
+            diagnostic!("Warning: {}", carrier_warning);
+        }
+
+        let (secret_data, content_type) =
+            CommandLineHandler::read_secret_payload(secret_message, data_file_path, content_type)?;
+
         // Load input image as RGB to preserve color information
-        let source_image = ImageReader::open(&input_path)?
-            .decode()
-            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
-            .to_rgb8();
+        let source_image = self.load_rgb_image(&input_path, input_format)?;
 
-        println!(
+        diagnostic!(
             "Loaded source image: {}x{} pixels",
             source_image.width(),
             source_image.height()
         );
 
-        // Generate or load encryption key
-        let encryption_key = self.get_or_generate_encryption_key(&output_path, key_file_path)?;
+        if macro_block {
+            self.use_macro_blocks();
+        }
+        self.use_embedding_channel(channels);
+        if let Some(positions) = positions {
+            self.use_embedding_positions(positions)?;
+        }
+        if adaptive_strength {
+            self.use_adaptive_strength();
+        }
+        self.use_block_visitation(block_visitation);
+        self.check_for_prior_embedding(&source_image, force)?;
 
-        // Encrypt the secret data with error correction
-        let encrypted_data = self
-            .cryptographic_engine
-            .encrypt_with_error_correction(&encryption_key, secret_data.as_bytes())?;
+        // Optionally pad the carrier so no block straddles the image edge
+        let (source_image, original_dimensions) = if pad_to_block {
+            let original_dimensions = (
+                u16::try_from(source_image.width()).map_err(|_| {
+                    SteganographyError::InvalidInput("Image too wide to pad".to_string())
+                })?,
+                u16::try_from(source_image.height()).map_err(|_| {
+                    SteganographyError::InvalidInput("Image too tall to pad".to_string())
+                })?,
+            );
+            let padded_image = self
+                .steganography_engine
+                .pad_to_block_multiple(&source_image);
+            diagnostic!(
+                "Padded carrier to {}x{} pixels",
+                padded_image.width(),
+                padded_image.height()
+            );
+            (padded_image, Some(original_dimensions))
+        } else {
+            (source_image, None)
+        };
 
-        println!(
+        // Derive, load or generate the encryption key
+        let key_path = passphrase
+            .is_none()
+            .then(|| CommandLineHandler::resolve_key_file_path(&output_path, &key_file_path));
+        let (encryption_key, passphrase_salt) = self.resolve_hide_encryption_key(
+            &output_path,
+            key_file_path,
+            output_key_format,
+            passphrase.as_deref(),
+            dry_run,
+        )?;
+        if seed_positions_from_key {
+            self.use_keyed_positions(&encryption_key);
+        }
+        self.use_block_order_seed(&encryption_key);
+        if protect_header {
+            self.use_protected_header();
+        }
+
+        // Encrypt the secret data with error correction, optionally compressing it first
+        self.use_coding_scheme(ecc);
+        let encrypted_data = match framing {
+            Framing::LengthHeader => self.cryptographic_engine.encrypt_with_frame_options(
+                &encryption_key,
+                &secret_data,
+                &FrameOptions {
+                    compress: compress_payload,
+                    original_dimensions,
+                    content_type,
+                    block_visitation,
+                    passphrase_salt,
+                    authenticated,
+                    configuration_fingerprint: Some(
+                        self.steganography_engine.configuration_fingerprint(),
+                    ),
+                },
+            )?,
+            Framing::Sentinel => self
+                .cryptographic_engine
+                .encrypt_with_sentinel_framing(&encryption_key, &secret_data)?,
+        };
+
+        diagnostic!(
             "Encrypted {} bytes of data to {} bytes",
             secret_data.len(),
             encrypted_data.len()
         );
 
-        // Hide encrypted data in the image
-        let steganographic_image = self.steganography_engine.hide_data_in_rgb_image(
-            &source_image,
-            &encrypted_data,
-            jpeg_quality,
-        )?;
+        // Search for the lowest quality now that the exact payload is known
+        let jpeg_quality = if auto_quality {
+            self.select_lowest_surviving_quality(
+                &source_image,
+                &encrypted_data,
+                &encryption_key,
+                &secret_data,
+                framing,
+            )?
+        } else {
+            jpeg_quality
+        };
+
+        // Hide encrypted data in the image
+        let steganographic_image = self
+            .steganography_engine
+            .hide_data_in_rgb_image(&source_image, &encrypted_data, jpeg_quality)
+            .inspect_err(|error| {
+                self.report_capacity_remedies(error, encrypted_data.len(), secret_data.len())
+            })?;
+
+        // Save the steganographic image, unless this is only a dry run
+        let output_description = if output_format.is_lossy() {
+            format!("quality: {}", jpeg_quality)
+        } else {
+            format!("lossless {}", output_format)
+        };
+        if dry_run {
+            diagnostic!(
+                "Dry run: would save steganographic image to: {} ({})",
+                output_file_path,
+                output_description
+            );
+        } else {
+            self.save_steganographic_image(
+                &steganographic_image,
+                &output_file_path,
+                output_format,
+                jpeg_quality,
+            )?;
+            diagnostic!(
+                "Steganographic image saved to: {} ({})",
+                output_file_path,
+                output_description
+            );
+            diagnostic!("Image is ready for sharing on social media and messaging platforms!");
+        }
+
+        let embedding_psnr = self
+            .steganography_engine
+            .compute_psnr(&source_image, &steganographic_image);
+        if report_quality {
+            diagnostic!("Embedding PSNR: {:.2} dB", embedding_psnr);
+            if embedding_psnr < min_psnr {
+                diagnostic!(
+                    "Warning: PSNR is below {:.1} dB; the embedding strength may be too aggressive for this image and the changes may be visible",
+                    min_psnr
+                );
+            }
+        }
+
+        if self.json_output {
+            self.print_hide_json(
+                &output_file_path,
+                key_path,
+                self.steganography_engine
+                    .calculate_capacity_bits(&source_image)?,
+                encrypted_data.len(),
+                Some(embedding_psnr),
+            );
+        }
+        Ok(())
+    }
+
+    /// Hides data losslessly in the alpha channel of an opaque RGBA image, saving a PNG
+    fn handle_alpha_channel_hide(&mut self, hide_arguments: HideArguments) -> Result<()> {
+        let HideArguments {
+            input: input_path,
+            output: output_path,
+            data: secret_message,
+            data_file: data_file_path,
+            content_type,
+            key_file: key_file_path,
+            passphrase,
+            output_key_format,
+            compress: compress_payload,
+            ecc,
+            repetition,
+            authenticated,
+            input_format,
+            dry_run,
+            ..
+        } = hide_arguments;
+        // clap rules out --split with --alpha-channel, so --input was given
+        let input_path = input_path.unwrap_or_default();
+        self.use_repetition_factor(repetition)?;
+
+        // Alpha bits only survive lossless storage
+        let output_file_path = if output_path.ends_with(".png") {
+            output_path.clone()
+        } else {
+            format!("{}.png", output_path)
+        };
+        CommandLineHandler::ensure_hide_outputs_writable(
+            &output_file_path,
+            &output_path,
+            &key_file_path,
+        )?;
+
+        let (secret_data, content_type) =
+            CommandLineHandler::read_secret_payload(secret_message, data_file_path, content_type)?;
+
+        let source_image = self.load_rgba_image(&input_path, input_format)?;
+        let capacity_bits = self
+            .steganography_engine
+            .calculate_alpha_capacity_bits(&source_image);
+        diagnostic!(
+            "Loaded RGBA source image: {}x{} pixels, alpha channel capacity {} bits",
+            source_image.width(),
+            source_image.height(),
+            capacity_bits
+        );
+
+        let key_path = passphrase
+            .is_none()
+            .then(|| CommandLineHandler::resolve_key_file_path(&output_path, &key_file_path));
+        let (encryption_key, passphrase_salt) = self.resolve_hide_encryption_key(
+            &output_path,
+            key_file_path,
+            output_key_format,
+            passphrase.as_deref(),
+            dry_run,
+        )?;
+        self.use_coding_scheme(ecc);
+        let encrypted_data = self.cryptographic_engine.encrypt_with_frame_options(
+            &encryption_key,
+            &secret_data,
+            &FrameOptions {
+                compress: compress_payload,
+                content_type,
+                passphrase_salt,
+                authenticated,
+                ..FrameOptions::default()
+            },
+        )?;
+
+        diagnostic!(
+            "Encrypted {} bytes of data to {} bytes",
+            secret_data.len(),
+            encrypted_data.len()
+        );
+
+        let steganographic_image = self
+            .steganography_engine
+            .hide_data_in_rgba_alpha(&source_image, &encrypted_data)
+            .inspect_err(|error| {
+                self.report_capacity_remedies(error, encrypted_data.len(), secret_data.len())
+            })?;
+
+        if dry_run {
+            diagnostic!(
+                "Dry run: would save steganographic image to: {} (lossless PNG)",
+                output_file_path
+            );
+        } else {
+            steganographic_image
+                .save(&output_file_path)
+                .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
+
+            diagnostic!(
+                "Steganographic image saved to: {} (lossless PNG)",
+                output_file_path
+            );
+            diagnostic!("Recompressing or flattening this image will destroy the hidden data");
+        }
+
+        // The RGB channels are untouched, so there is no PSNR to report
+        if self.json_output {
+            self.print_hide_json(
+                &output_file_path,
+                key_path,
+                self.steganography_engine
+                    .calculate_alpha_capacity_bits(&source_image),
+                encrypted_data.len(),
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    /// Handles `hide --split`, spreading one encrypted payload across several covers
+    fn handle_split_hide(&mut self, hide_arguments: HideArguments) -> Result<()> {
+        let HideArguments {
+            output: output_path,
+            data: secret_message,
+            data_file: data_file_path,
+            content_type,
+            key_file: key_file_path,
+            output_key_format,
+            quality: requested_quality,
+            compress: compress_payload,
+            ecc,
+            repetition,
+            authenticated,
+            force,
+            split: cover_paths,
+            ..
+        } = hide_arguments;
+        // Only dispatched here when --split was given
+        let cover_paths = cover_paths.unwrap_or_default();
+
+        let jpeg_quality = requested_quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+        if !(1..=100).contains(&jpeg_quality) {
+            return Err(SteganographyError::InvalidInput(
+                "JPEG quality must be between 1 and 100".to_string(),
+            ));
+        }
+        self.use_repetition_factor(repetition)?;
+
+        // Each part is saved next to the others, numbered by its place in the split
+        let output_file_paths: Vec<String> = (1..=cover_paths.len())
+            .map(|part_number| format!("{}-{}.jpg", output_path, part_number))
+            .collect();
+        for output_file_path in &output_file_paths {
+            CommandLineHandler::ensure_hide_outputs_writable(
+                output_file_path,
+                &output_path,
+                &key_file_path,
+            )?;
+        }
+
+        let (secret_data, content_type) =
+            CommandLineHandler::read_secret_payload(secret_message, data_file_path, content_type)?;
+
+        let mut source_images = Vec::with_capacity(cover_paths.len());
+        for cover_path in &cover_paths {
+            let source_image = self.load_rgb_image(cover_path, None)?;
+            diagnostic!(
+                "Loaded cover {}: {}x{} pixels",
+                cover_path,
+                source_image.width(),
+                source_image.height()
+            );
+            self.check_for_prior_embedding(&source_image, force)?;
+            source_images.push(source_image);
+        }
+
+        let key_path = CommandLineHandler::resolve_key_file_path(&output_path, &key_file_path);
+        let (encryption_key, _) = self.resolve_hide_encryption_key(
+            &output_path,
+            key_file_path,
+            output_key_format,
+            None,
+            false,
+        )?;
+        self.use_coding_scheme(ecc);
+        let encrypted_data = self.cryptographic_engine.encrypt_with_frame_options(
+            &encryption_key,
+            &secret_data,
+            &FrameOptions {
+                compress: compress_payload,
+                content_type,
+                authenticated,
+                configuration_fingerprint: Some(
+                    self.steganography_engine.configuration_fingerprint(),
+                ),
+                ..FrameOptions::default()
+            },
+        )?;
+
+        diagnostic!(
+            "Encrypted {} bytes of data to {} bytes",
+            secret_data.len(),
+            encrypted_data.len()
+        );
+
+        let steganographic_images = self
+            .steganography_engine
+            .hide_data_across_rgb_images(&source_images, &encrypted_data, jpeg_quality)
+            .inspect_err(|error| {
+                self.report_capacity_remedies(error, encrypted_data.len(), secret_data.len())
+            })?;
+
+        for (part_index, (steganographic_image, output_file_path)) in steganographic_images
+            .iter()
+            .zip(&output_file_paths)
+            .enumerate()
+        {
+            self.save_steganographic_image(
+                steganographic_image,
+                output_file_path,
+                OutputFormat::Jpeg,
+                jpeg_quality,
+            )?;
+            diagnostic!(
+                "Part {} of {} saved to: {} (quality: {})",
+                part_index + 1,
+                output_file_paths.len(),
+                output_file_path,
+                jpeg_quality
+            );
+        }
+        diagnostic!(
+            "Extract with --split and every part; the key is at {}",
+            key_path
+        );
+        Ok(())
+    }
+
+    /// Prints the result of a hide as a JSON object on stdout
+    fn print_hide_json(
+        &self,
+        output_file_path: &str,
+        key_path: Option<String>,
+        capacity_bits: usize,
+        payload_bytes: usize,
+        embedding_psnr: Option<f64>,
+    ) {
+        println!(
+            "{}",
+            json_object(&[
+                ("output_path", output_file_path.into()),
+                ("key_path", key_path.into()),
+                ("capacity_bits", capacity_bits.into()),
+                ("payload_bytes", payload_bytes.into()),
+                ("psnr", embedding_psnr.into()),
+            ])
+        );
+    }
+
+    /// Handles the extract command to retrieve data from a steganographic image
+    fn handle_extract_command(&mut self, extract_arguments: ExtractArguments) -> Result<()> {
+        if let Some(block_size) = extract_arguments.block_size.filter(|&size| size != N) {
+            return match block_size {
+                16 => self
+                    .with_block_size::<16>()
+                    .handle_extract_command(extract_arguments),
+                32 => self
+                    .with_block_size::<32>()
+                    .handle_extract_command(extract_arguments),
+                _ => self
+                    .with_block_size::<8>()
+                    .handle_extract_command(extract_arguments),
+            };
+        }
+
+        let ExtractArguments {
+            input: input_path,
+            key: key_input,
+            passphrase,
+            length: expected_length,
+            cropped_output: cropped_output_path,
+            seed_positions_from_key,
+            positions,
+            protect_header,
+            authenticated,
+            framing,
+            repetition,
+            macro_block,
+            block_size: _,
+            channels,
+            noise_floor,
+            strict_utf8,
+            raw: raw_encoding,
+            input_format,
+            alpha_channel,
+            split: split_paths,
+            output_file: output_file_path,
+        } = extract_arguments;
+        self.use_repetition_factor(repetition)?;
+        if raw_encoding.is_some() && self.json_output {
+            return Err(SteganographyError::InvalidInput(
+                "--raw prints the payload bytes themselves, so it can't be combined with --json"
+                    .to_string(),
+            ));
+        }
+
+        if let Some(output_file_path) = &output_file_path {
+            CommandLineHandler::ensure_writable(output_file_path)?;
+        }
+
+        if let Some(split_paths) = split_paths {
+            // clap requires a key whenever no passphrase was given, and --split rules one out
+            return self.extract_split_payload(
+                &split_paths,
+                &key_input.unwrap_or_default(),
+                authenticated,
+                strict_utf8,
+                raw_encoding,
+                output_file_path,
+            );
+        }
+        // clap requires --input unless --split was given
+        let input_path = input_path.unwrap_or_default();
+
+        // Keep the alpha channel when reading from it; otherwise flatten to RGB
+        let carrier_image = self.load_image(&input_path, input_format)?;
+
+        diagnostic!(
+            "Loaded steganographic image: {}x{} pixels",
+            carrier_image.width(),
+            carrier_image.height()
+        );
+
+        // Load encryption key; a passphrase key needs the salt stored in the image instead
+        let loaded_key = key_input
+            .map(|key_input| self.cryptographic_engine.load_key_from_input(&key_input))
+            .transpose()?;
+        self.use_embedding_channel(channels);
+        if let Some(positions) = positions {
+            self.use_embedding_positions(positions)?;
+        }
+        if seed_positions_from_key {
+            if let Some(encryption_key) = &loaded_key {
+                self.use_keyed_positions(encryption_key);
+            }
+        }
+        if protect_header {
+            self.use_protected_header();
+        }
+        if macro_block {
+            self.use_macro_blocks();
+        }
+
+        // Extract encrypted data from the image
+        let extraction_result = if alpha_channel {
+            let steganographic_image =
+                CommandLineHandler::require_alpha_channel(&input_path, &carrier_image)?;
+            self.steganography_engine
+                .extract_data_from_rgba_alpha(&steganographic_image, expected_length)?
+        } else {
+            let steganographic_image = carrier_image.to_rgb8();
+            if noise_floor {
+                self.calibrate_noise_floor(&steganographic_image)?;
+            }
+            if framing == Framing::LengthHeader {
+                self.use_recorded_block_visitation(&steganographic_image);
+            }
+            if self.steganography_engine.block_visitation() == BlockVisitation::Keyed {
+                let encryption_key = loaded_key.as_ref().ok_or_else(|| {
+                    SteganographyError::InvalidInput(
+                        "this image was hidden with --block-visitation keyed; extract it with --key".to_string(),
+                    )
+                })?;
+                self.use_block_order_seed(encryption_key);
+            }
+            match (framing, expected_length) {
+                // The frame header says how long the frame is, so stop reading there
+                (Framing::LengthHeader, None) => {
+                    self.steganography_engine.extract_frame_from_rgb_image(
+                        &steganographic_image,
+                        self.cryptographic_engine.repetition_factor(),
+                    )?
+                }
+                _ => self
+                    .steganography_engine
+                    .extract_data_from_rgb_image(&steganographic_image, expected_length)?,
+            }
+        };
+        let extracted_encrypted_data = &extraction_result.data;
+
+        diagnostic!(
+            "Extracted {} bytes of encrypted data",
+            extracted_encrypted_data.len()
+        );
+
+        if framing == Framing::Sentinel {
+            // clap rules out a passphrase with sentinel framing, so a key was given
+            return self.extract_sentinel_payload(
+                &loaded_key.unwrap_or_default(),
+                &extraction_result,
+                strict_utf8,
+                raw_encoding,
+                output_file_path,
+            );
+        }
+
+        // Parameters that differ from the embedding's read garbage; say so before decoding it
+        self.cryptographic_engine
+            .require_configuration_fingerprint(
+                extracted_encrypted_data,
+                self.steganography_engine.configuration_fingerprint(),
+            )?;
+
+        // Score only the framed payload since unused cover blocks carry no signal
+        let frame_length = self
+            .cryptographic_engine
+            .calculate_encoded_frame_length(extracted_encrypted_data)
+            .unwrap_or(extracted_encrypted_data.len());
+        self.report_extraction_confidence(
+            extraction_result.confidence_for_leading_bytes(frame_length),
+        );
+
+        // clap requires a passphrase whenever no key was given
+        let encryption_key = match loaded_key {
+            Some(encryption_key) => encryption_key,
+            None => self.derive_extraction_key(
+                passphrase.as_deref().unwrap_or_default(),
+                extracted_encrypted_data,
+            )?,
+        };
+
+        if authenticated {
+            self.cryptographic_engine
+                .require_authenticated_frame(extracted_encrypted_data)?;
+        }
+
+        // Decrypt the extracted data
+        let decrypted_data = self.cryptographic_engine.decrypt_with_bit_reliability(
+            &encryption_key,
+            extracted_encrypted_data,
+            &extraction_result.bit_confidences,
+        )?;
+
+        // Padded carriers record their original size so they can be cropped back
+        let frame_header = self
+            .cryptographic_engine
+            .read_frame_header(extracted_encrypted_data)?;
+        match (frame_header.original_dimensions, cropped_output_path) {
+            (Some((original_width, original_height)), Some(cropped_output_path)) => {
+                let cropped_image = self.steganography_engine.crop_to_dimensions(
+                    &carrier_image.to_rgb8(),
+                    original_width as u32,
+                    original_height as u32,
+                );
+                cropped_image
+                    .save(&cropped_output_path)
+                    .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
+                diagnostic!(
+                    "Cropped carrier back to {}x{} pixels: {}",
+                    original_width,
+                    original_height,
+                    cropped_output_path
+                );
+            }
+            (Some((original_width, original_height)), None) => {
+                diagnostic!(
+                    "Carrier was padded from {}x{} pixels (use --cropped-output to restore)",
+                    original_width,
+                    original_height
+                );
+            }
+            (None, Some(_)) => {
+                diagnostic!("Carrier was not padded; no cropped output written");
+            }
+            (None, None) => {}
+        }
+
+        self.present_extracted_payload(
+            decrypted_data,
+            frame_header.content_type,
+            strict_utf8,
+            raw_encoding,
+            output_file_path,
+        )
+    }
+
+    /// Reassembles the parts of a payload hidden by `hide --split`, then decrypts it
+    fn extract_split_payload(
+        &mut self,
+        split_paths: &[String],
+        key_input: &str,
+        authenticated: bool,
+        strict_utf8: bool,
+        raw_encoding: Option<RawEncoding>,
+        output_file_path: Option<String>,
+    ) -> Result<()> {
+        let encryption_key = self.cryptographic_engine.load_key_from_input(key_input)?;
+        let mut steganographic_images = Vec::with_capacity(split_paths.len());
+        for split_path in split_paths {
+            steganographic_images.push(self.load_rgb_image(split_path, None)?);
+        }
+
+        let extraction_result = self
+            .steganography_engine
+            .extract_data_from_rgb_images(&steganographic_images)?;
+        let extracted_encrypted_data = &extraction_result.data;
+        diagnostic!(
+            "Reassembled {} bytes of encrypted data from {} parts",
+            extracted_encrypted_data.len(),
+            steganographic_images.len()
+        );
+
+        self.cryptographic_engine
+            .require_configuration_fingerprint(
+                extracted_encrypted_data,
+                self.steganography_engine.configuration_fingerprint(),
+            )?;
+        let frame_length = self
+            .cryptographic_engine
+            .calculate_encoded_frame_length(extracted_encrypted_data)
+            .unwrap_or(extracted_encrypted_data.len());
+        self.report_extraction_confidence(
+            extraction_result.confidence_for_leading_bytes(frame_length),
+        );
+
+        if authenticated {
+            self.cryptographic_engine
+                .require_authenticated_frame(extracted_encrypted_data)?;
+        }
+        let decrypted_data = self.cryptographic_engine.decrypt_with_bit_reliability(
+            &encryption_key,
+            extracted_encrypted_data,
+            &extraction_result.bit_confidences,
+        )?;
+        let frame_header = self
+            .cryptographic_engine
+            .read_frame_header(extracted_encrypted_data)?;
+
+        self.present_extracted_payload(
+            decrypted_data,
+            frame_header.content_type,
+            strict_utf8,
+            raw_encoding,
+            output_file_path,
+        )
+    }
+
+    /// Handles the capacity command to report how much data an image can hold
+    fn handle_capacity_command(
+        &mut self,
+        input_path: String,
+        show_table: bool,
+        coding_scheme: CodingScheme,
+        embedding_channel: EmbeddingChannel,
+    ) -> Result<()> {
+        let decoded_image = ImageReader::open(&input_path)?
+            .decode()
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
+        let carrier_image = decoded_image.to_rgb8();
+        self.use_embedding_channel(embedding_channel);
+        self.use_coding_scheme(coding_scheme);
+
+        let capacity_bits = self
+            .steganography_engine
+            .calculate_capacity_bits(&carrier_image)?;
+        let capacity_bytes = capacity_bits / 8;
+
+        diagnostic!(
+            "Image: {}x{} pixels",
+            carrier_image.width(),
+            carrier_image.height()
+        );
+        let channel_components = embedding_channel.components();
+        if embedding_channel == EmbeddingChannel::Luma {
+            diagnostic!(
+                "Raw capacity: {} bits ({} bytes) in the luminance channel",
+                capacity_bits,
+                capacity_bytes
+            );
+        } else {
+            let component_names: Vec<&str> = channel_components
+                .iter()
+                .map(|component| component.name())
+                .collect();
+            diagnostic!(
+                "Raw capacity: {} bits ({} bytes) across the {} channels, {} bits each",
+                capacity_bits,
+                capacity_bytes,
+                component_names.join(", "),
+                capacity_bits / channel_components.len()
+            );
+        }
+        let coding_description = match coding_scheme {
+            CodingScheme::Repetition => format!(
+                "{}x repetition coding",
+                self.cryptographic_engine.repetition_factor()
+            ),
+            CodingScheme::ReedSolomon => format!(
+                "Reed-Solomon coding ({} parity bytes per 255-byte block)",
+                reed_solomon::PARITY_BYTES
+            ),
+        };
+        diagnostic!(
+            "Overhead: {}-byte frame header, nonce and {}",
+            FrameHeader::default().encoded_length(),
+            coding_description
+        );
+        diagnostic!(
+            "Maximum message size: {} bytes",
+            self.cryptographic_engine
+                .calculate_max_plaintext_length(capacity_bytes)
+        );
+
+        let macro_block_capacity_bits = SteganographyEngine::new()
+            .with_macro_block_scale(DEFAULT_MACRO_BLOCK_SCALE)
+            .calculate_capacity_bits(&carrier_image)?;
+        diagnostic!(
+            "Macro-block capacity (--macro-block): {} bits, maximum message size {} bytes",
+            macro_block_capacity_bits,
+            self.cryptographic_engine
+                .calculate_max_plaintext_length(macro_block_capacity_bits / 8)
+        );
+
+        if decoded_image.color().has_alpha() {
+            let alpha_capacity_bits = self
+                .steganography_engine
+                .calculate_alpha_capacity_bits(&decoded_image.to_rgba8());
+            diagnostic!(
+                "Alpha channel capacity (--alpha-channel, opaque pixels only): {} bits, maximum message size {} bytes",
+                alpha_capacity_bits,
+                self.cryptographic_engine
+                    .calculate_max_plaintext_length(alpha_capacity_bits / 8)
+            );
+        }
+
+        if show_table {
+            self.print_capacity_table(capacity_bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Prints the quantization step and a recommended payload for common JPEG qualities
+    ///
+    /// The robustness margin is how many quantization steps the embedded coefficient sits
+    /// from zero. The recommendation reserves `1 / margin` of the capacity as headroom, so
+    /// qualities that quantize coarsely relative to the embedding get more slack.
+    fn print_capacity_table(&self, capacity_bytes: usize) {
+        let embedding_algorithm = self.steganography_engine.embedding_algorithm();
+
+        diagnostic!();
+        diagnostic!("Quality  Quant step  Margin  Recommended max message");
+        for jpeg_quality in CAPACITY_TABLE_QUALITIES {
+            let quantization_step = embedding_algorithm.primary_quantization_step(jpeg_quality);
+            let robustness_margin =
+                embedding_algorithm.primary_embedding_amplitude(jpeg_quality) / quantization_step;
+            let usable_bytes =
+                (capacity_bytes as f32 * (1.0 - 1.0 / robustness_margin)).floor() as usize;
+
+            diagnostic!(
+                "{:>7}  {:>10.1}  {:>5.1}x  {} bytes",
+                jpeg_quality,
+                quantization_step,
+                robustness_margin,
+                self.cryptographic_engine
+                    .calculate_max_plaintext_length(usable_bytes)
+            );
+        }
+    }
+
+    /// Handles the robustness test command by recompressing an image and comparing raw bits
+    fn handle_robustness_test_command(&self, input_path: String, jpeg_quality: u8) -> Result<()> {
+        if !(1..=100).contains(&jpeg_quality) {
+            return Err(SteganographyError::InvalidInput(
+                "JPEG quality must be between 1 and 100".to_string(),
+            ));
+        }
+
+        let steganographic_image = ImageReader::open(&input_path)?
+            .decode()
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
+            .to_rgb8();
+
+        let pristine_bits = self
+            .steganography_engine
+            .extract_raw_bits(&steganographic_image)?;
+
+        let recompressed_jpeg = self
+            .steganography_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, jpeg_quality)?;
+        let recompressed_image = image::load_from_memory(&recompressed_jpeg)
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
+            .to_rgb8();
+        let recompressed_bits = self
+            .steganography_engine
+            .extract_raw_bits(&recompressed_image)?;
+
+        let error_rate = bit_error_rate(&pristine_bits, &recompressed_bits);
+        diagnostic!(
+            "Recompressed at quality {}: {} of {} bits flipped (bit error rate {:.2}%)",
+            jpeg_quality,
+            (error_rate * pristine_bits.len() as f64).round() as usize,
+            pristine_bits.len(),
+            error_rate * 100.0
+        );
+
+        Ok(())
+    }
+
+    /// Handles the formats command by listing the image formats compiled into this binary
+    fn handle_formats_command(&self) -> Result<()> {
+        let format_names = |image_formats: Vec<image::ImageFormat>| {
+            image_formats
+                .iter()
+                .map(|image_format| format!("{:?}", image_format))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        diagnostic!("Input formats: {}", format_names(decodable_image_formats()));
+        diagnostic!("Steganographic output: JPEG (jpeg-encoder), lossless PNG or WebP (--format)");
+        diagnostic!(
+            "Cropped output formats: {}",
+            format_names(encodable_image_formats())
+        );
+
+        Ok(())
+    }
+
+    /// Handles the verify command to round-trip a message through JPEG in memory
+    ///
+    /// Nothing is written to disk, so this shows whether a quality is high enough before the
+    /// real hide.
+    fn handle_verify_command(
+        &self,
+        input_path: String,
+        secret_message: String,
+        jpeg_quality: u8,
+    ) -> Result<()> {
+        if !(1..=100).contains(&jpeg_quality) {
+            return Err(SteganographyError::InvalidInput(
+                "JPEG quality must be between 1 and 100".to_string(),
+            ));
+        }
+
+        let source_image = ImageReader::open(&input_path)?
+            .decode()
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
+            .to_rgb8();
+
+        // A throwaway key is as good as the real one for judging survival
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let encrypted_data = self
+            .cryptographic_engine
+            .encrypt_with_error_correction(&encryption_key, secret_message.as_bytes())?;
+        let steganographic_image = self.steganography_engine.hide_data_in_rgb_image(
+            &source_image,
+            &encrypted_data,
+            jpeg_quality,
+        )?;
+
+        let steganographic_jpeg = self
+            .steganography_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, jpeg_quality)?;
+        let reloaded_image = image::load_from_memory(&steganographic_jpeg)
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
+            .to_rgb8();
+        let extraction_result = self
+            .steganography_engine
+            .extract_data_from_rgb_image(&reloaded_image, None)?;
+
+        let recovered_data = self.cryptographic_engine.decrypt_with_bit_reliability(
+            &encryption_key,
+            &extraction_result.data,
+            &extraction_result.bit_confidences,
+        );
+        if matches!(&recovered_data, Ok(recovered_data) if recovered_data == secret_message.as_bytes())
+        {
+            diagnostic!(
+                "PASS: the message survived hiding and JPEG encoding at quality {}",
+                jpeg_quality
+            );
+        } else {
+            diagnostic!(
+                "FAIL: the message did not survive JPEG encoding at quality {}; {} of {} embedded bytes were damaged",
+                jpeg_quality,
+                byte_error_count(&encrypted_data, &extraction_result.data),
+                encrypted_data.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Binary-searches `AUTO_QUALITY_RANGE` for the lowest JPEG quality the payload survives
+    ///
+    /// Each step hides the payload tuned for the quality, encodes and decodes a JPEG at it,
+    /// and extracts and decrypts in memory, like the verify command. Survival is assumed to
+    /// only improve with quality.
+    fn select_lowest_surviving_quality(
+        &self,
+        source_image: &RgbImage,
+        encrypted_data: &[u8],
+        encryption_key: &[u8; 32],
+        secret_data: &[u8],
+        framing: Framing,
+    ) -> Result<u8> {
+        let (mut lowest_quality, mut highest_quality) =
+            (*AUTO_QUALITY_RANGE.start(), *AUTO_QUALITY_RANGE.end());
+        if !self.payload_survives_jpeg(
+            source_image,
+            encrypted_data,
+            encryption_key,
+            secret_data,
+            framing,
+            highest_quality,
+        )? {
+            return Err(SteganographyError::InvalidInput(format!(
+                "the payload doesn't survive JPEG encoding even at quality {}; use a larger or more textured carrier, or lossless output",
+                highest_quality
+            )));
+        }
+
+        while lowest_quality < highest_quality {
+            let middle_quality = lowest_quality + (highest_quality - lowest_quality) / 2;
+            if self.payload_survives_jpeg(
+                source_image,
+                encrypted_data,
+                encryption_key,
+                secret_data,
+                framing,
+                middle_quality,
+            )? {
+                highest_quality = middle_quality;
+            } else {
+                lowest_quality = middle_quality + 1;
+            }
+        }
+
+        diagnostic!(
+            "Auto quality: selected JPEG quality {}, the lowest at which the payload survives",
+            highest_quality
+        );
+        Ok(highest_quality)
+    }
+
+    /// Returns whether the payload decrypts to `secret_data` after hiding and a JPEG round
+    /// trip at `jpeg_quality`, all in memory
+    fn payload_survives_jpeg(
+        &self,
+        source_image: &RgbImage,
+        encrypted_data: &[u8],
+        encryption_key: &[u8; 32],
+        secret_data: &[u8],
+        framing: Framing,
+        jpeg_quality: u8,
+    ) -> Result<bool> {
+        let steganographic_image = self.steganography_engine.hide_data_in_rgb_image(
+            source_image,
+            encrypted_data,
+            jpeg_quality,
+        )?;
+        let steganographic_jpeg = self
+            .steganography_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, jpeg_quality)?;
+        let reloaded_image = image::load_from_memory(&steganographic_jpeg)
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
+            .to_rgb8();
+        let extraction_result = self
+            .steganography_engine
+            .extract_data_from_rgb_image(&reloaded_image, None)?;
+
+        let recovered_data = match framing {
+            Framing::LengthHeader => self.cryptographic_engine.decrypt_with_bit_reliability(
+                encryption_key,
+                &extraction_result.data,
+                &extraction_result.bit_confidences,
+            ),
+            Framing::Sentinel => self.cryptographic_engine.decrypt_sentinel_frame(
+                encryption_key,
+                &extraction_result.data,
+                &extraction_result.bit_confidences,
+            ),
+        };
+        let survives =
+            matches!(&recovered_data, Ok(recovered_data) if recovered_data == secret_data);
+        diagnostic!(
+            "Auto quality: quality {} {}",
+            jpeg_quality,
+            if survives { "survives" } else { "fails" }
+        );
+        Ok(survives)
+    }
+
+    /// Handles the batch command by hiding one message in every image of a directory
+    ///
+    /// Images too small for the message are skipped with a warning and other failures are
+    /// reported, so one bad file doesn't abort the batch. Output images keep their file stem.
+    fn handle_batch_command(
+        &self,
+        input_directory: String,
+        output_directory: String,
+        secret_message: String,
+        jpeg_quality: u8,
+    ) -> Result<()> {
+        if !(1..=100).contains(&jpeg_quality) {
+            return Err(SteganographyError::InvalidInput(
+                "JPEG quality must be between 1 and 100".to_string(),
+            ));
+        }
+
+        fs::create_dir_all(&output_directory)?;
+        if fs::canonicalize(&input_directory)? == fs::canonicalize(&output_directory)? {
+            return Err(SteganographyError::InvalidInput(
+                "the output directory must differ from the input directory so carriers aren't overwritten".to_string(),
+            ));
+        }
+
+        let mut carrier_paths: Vec<_> = fs::read_dir(&input_directory)?
+            .map(|directory_entry| directory_entry.map(|directory_entry| directory_entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        carrier_paths.retain(|carrier_path| {
+            carrier_path.is_file() && ImageFormat::from_path(carrier_path).is_ok()
+        });
+        carrier_paths.sort();
+
+        // One key for the whole batch, reused if the output directory already has one
+        let key_output_path = Path::new(&output_directory).join(BATCH_KEY_FILE_STEM);
+        let encryption_key = self.get_or_generate_encryption_key(
+            &key_output_path.to_string_lossy(),
+            None,
+            KeyEncoding::default(),
+        )?;
+
+        let (mut hidden_count, mut skipped_count, mut failed_count) = (0usize, 0usize, 0usize);
+        for carrier_path in &carrier_paths {
+            let file_stem = carrier_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let output_file_path = Path::new(&output_directory)
+                .join(format!("{}.jpg", file_stem))
+                .to_string_lossy()
+                .into_owned();
+
+            match self.hide_batch_image(
+                carrier_path,
+                &output_file_path,
+                &encryption_key,
+                secret_message.as_bytes(),
+                jpeg_quality,
+            ) {
+                Ok(()) => {
+                    diagnostic!(
+                        "Hid message in {} -> {}",
+                        carrier_path.display(),
+                        output_file_path
+                    );
+                    hidden_count += 1;
+                }
+                Err(SteganographyError::CapacityError {
+                    required,
+                    available,
+                }) => {
+                    diagnostic!(
+                        "Warning: skipping {}: it needs {} bits of capacity but only has {}",
+                        carrier_path.display(),
+                        required,
+                        available
+                    );
+                    skipped_count += 1;
+                }
+                Err(error) => {
+                    diagnostic!("Failed {}: {}", carrier_path.display(), error);
+                    failed_count += 1;
+                }
+            }
+        }
+
+        diagnostic!(
+            "Batch complete: {} of {} images hidden, {} skipped as too small, {} failed",
+            hidden_count,
+            carrier_paths.len(),
+            skipped_count,
+            failed_count
+        );
+        Ok(())
+    }
+
+    /// Runs the hide pipeline with default options on one image of a batch
+    fn hide_batch_image(
+        &self,
+        carrier_path: &Path,
+        output_file_path: &str,
+        encryption_key: &[u8; 32],
+        secret_data: &[u8],
+        jpeg_quality: u8,
+    ) -> Result<()> {
+        let source_image = self.load_rgb_image(&carrier_path.to_string_lossy(), None)?;
+        self.check_for_prior_embedding(&source_image, false)?;
+
+        let encrypted_data = self.cryptographic_engine.encrypt_with_frame_options(
+            encryption_key,
+            secret_data,
+            &FrameOptions {
+                configuration_fingerprint: Some(
+                    self.steganography_engine.configuration_fingerprint(),
+                ),
+                ..FrameOptions::default()
+            },
+        )?;
+        let steganographic_image = self.steganography_engine.hide_data_in_rgb_image(
+            &source_image,
+            &encrypted_data,
+            jpeg_quality,
+        )?;
+        self.steganography_engine.save_rgb_image_as_jpeg(
+            &steganographic_image,
+            output_file_path,
+            jpeg_quality,
+        )
+    }
+
+    /// Handles the rotate-key command by re-hiding a payload under a new key
+    ///
+    /// The original cover is gone, so the decoded steganographic image serves as the cover.
+    /// The options recorded in the frame header are kept. Only length-header payloads hidden
+    /// in the RGB channels with a key file can be rotated.
+    fn handle_rotate_key_command(
+        &mut self,
+        input_path: String,
+        old_key_input: String,
+        new_key_file_path: Option<String>,
+        output_path: String,
+        jpeg_quality: u8,
+    ) -> Result<()> {
+        if !(1..=100).contains(&jpeg_quality) {
+            return Err(SteganographyError::InvalidInput(
+                "JPEG quality must be between 1 and 100".to_string(),
+            ));
+        }
+
+        let output_format = OutputFormat::from_path(&output_path).unwrap_or_default();
+        let output_file_path = self.get_output_file_path(&output_path, output_format);
+        CommandLineHandler::ensure_hide_outputs_writable(
+            &output_file_path,
+            &output_path,
+            &new_key_file_path,
+        )?;
+
+        let old_key = self
+            .cryptographic_engine
+            .load_key_from_input(&old_key_input)?;
+        let steganographic_image = self.load_rgb_image(&input_path, None)?;
+
+        // Read the payload back exactly as extract does
+        self.use_recorded_block_visitation(&steganographic_image);
+        self.use_block_order_seed(&old_key);
+        let extraction_result = self
+            .steganography_engine
+            .extract_data_from_rgb_image(&steganographic_image, None)?;
+        self.cryptographic_engine
+            .require_configuration_fingerprint(
+                &extraction_result.data,
+                self.steganography_engine.configuration_fingerprint(),
+            )?;
+        let frame_header = self
+            .cryptographic_engine
+            .read_frame_header(&extraction_result.data)?;
+        if frame_header.has_passphrase_salt {
+            return Err(SteganographyError::InvalidInput(
+                "this image was hidden with a passphrase; rotate-key only re-encrypts key file payloads".to_string(),
+            ));
+        }
+        let plaintext_data = self.cryptographic_engine.decrypt_with_bit_reliability(
+            &old_key,
+            &extraction_result.data,
+            &extraction_result.bit_confidences,
+        )?;
+        diagnostic!("Decrypted {} bytes with the old key", plaintext_data.len());
+
+        let new_key = self.get_or_generate_encryption_key(
+            &output_path,
+            new_key_file_path,
+            KeyEncoding::default(),
+        )?;
+        if new_key == old_key {
+            return Err(SteganographyError::InvalidInput(
+                "the new key is the same as the old key; pass a different --new-key-file"
+                    .to_string(),
+            ));
+        }
+
+        self.use_block_order_seed(&new_key);
+        self.use_coding_scheme(frame_header.coding_scheme);
+        let encrypted_data = self.cryptographic_engine.encrypt_with_frame_options(
+            &new_key,
+            &plaintext_data,
+            &FrameOptions {
+                compress: frame_header.is_compressed,
+                original_dimensions: frame_header.original_dimensions,
+                content_type: frame_header.content_type,
+                block_visitation: frame_header.block_visitation,
+                passphrase_salt: None,
+                authenticated: frame_header.is_authenticated,
+                configuration_fingerprint: Some(
+                    self.steganography_engine.configuration_fingerprint(),
+                ),
+            },
+        )?;
+        // Embedding sets each carrying coefficient outright, so the new bits replace the old
+        let rotated_image = self.steganography_engine.hide_data_in_rgb_image(
+            &steganographic_image,
+            &encrypted_data,
+            jpeg_quality,
+        )?;
+        self.verify_rotated_payload(
+            &rotated_image,
+            output_format,
+            jpeg_quality,
+            &new_key,
+            &plaintext_data,
+        )?;
+        self.save_steganographic_image(
+            &rotated_image,
+            &output_file_path,
+            output_format,
+            jpeg_quality,
+        )?;
+
+        diagnostic!(
+            "Re-encrypted image saved to: {} (quality: {})",
+            output_file_path,
+            jpeg_quality
+        );
+        diagnostic!(
+            "Note: the steganographic image was re-used as the cover, so the output has been compressed once more than the original"
+        );
+        Ok(())
+    }
+
+    /// Fails unless the rotated image, encoded as it will be saved, decrypts to
+    /// `plaintext_data` under the new key
+    fn verify_rotated_payload(
+        &self,
+        rotated_image: &RgbImage,
+        output_format: OutputFormat,
+        jpeg_quality: u8,
+        new_key: &[u8; 32],
+        plaintext_data: &[u8],
+    ) -> Result<()> {
+        let reloaded_jpeg;
+        let saved_image = match output_format {
+            OutputFormat::Jpeg => {
+                let rotated_jpeg = self
+                    .steganography_engine
+                    .encode_rgb_image_as_jpeg(rotated_image, jpeg_quality)?;
+                reloaded_jpeg = image::load_from_memory(&rotated_jpeg)
+                    .map_err(|e| SteganographyError::ImageError(e.to_string()))?
+                    .to_rgb8();
+                &reloaded_jpeg
+            }
+            // Lossless outputs are saved exactly as embedded
+            OutputFormat::Png | OutputFormat::WebP => rotated_image,
+        };
+        let extraction_result = self
+            .steganography_engine
+            .extract_data_from_rgb_image(saved_image, None)?;
+        let recovered_data = self.cryptographic_engine.decrypt_with_bit_reliability(
+            new_key,
+            &extraction_result.data,
+            &extraction_result.bit_confidences,
+        );
+
+        if !matches!(&recovered_data, Ok(recovered_data) if recovered_data == plaintext_data) {
+            return Err(SteganographyError::InvalidInput(
+                "the re-encrypted payload does not extract under the new key; hide the message again from the original cover".to_string(),
+            ));
+        }
+        diagnostic!("Verified that the re-encrypted image extracts under the new key");
+        Ok(())
+    }
 
-        // Determine output file path with proper extension
-        let output_file_path = self.get_output_file_path(&output_path, jpeg_quality);
+    /// Handles the verify-key command by checking a key against the payload's tag or checksum
+    ///
+    /// Nothing of the payload is printed or written.
+    fn handle_verify_key_command(&mut self, input_path: String, key_input: String) -> Result<()> {
+        let encryption_key = self.cryptographic_engine.load_key_from_input(&key_input)?;
+        let steganographic_image = self.load_rgb_image(&input_path, None)?;
 
-        // Save the steganographic image
-        self.save_steganographic_image(&steganographic_image, &output_file_path, jpeg_quality)?;
+        // Read the payload back exactly as extract does
+        self.use_recorded_block_visitation(&steganographic_image);
+        self.use_block_order_seed(&encryption_key);
+        let extraction_result = self
+            .steganography_engine
+            .extract_data_from_rgb_image(&steganographic_image, None)?;
+        self.cryptographic_engine
+            .require_configuration_fingerprint(
+                &extraction_result.data,
+                self.steganography_engine.configuration_fingerprint(),
+            )?;
+        let frame_header = self
+            .cryptographic_engine
+            .read_frame_header(&extraction_result.data)?;
+        if frame_header.has_passphrase_salt {
+            return Err(SteganographyError::InvalidInput(
+                "this image was hidden with a passphrase; verify-key only checks key file payloads"
+                    .to_string(),
+            ));
+        }
 
-        println!(
-            "Steganographic image saved to: {} (quality: {})",
-            output_file_path, jpeg_quality
-        );
-        println!("Image is ready for sharing on social media and messaging platforms!");
+        self.cryptographic_engine.verify_key_with_bit_reliability(
+            &encryption_key,
+            &extraction_result.data,
+            &extraction_result.bit_confidences,
+        )?;
 
+        let verified_check = if frame_header.is_authenticated {
+            "authentication tag"
+        } else {
+            "checksum"
+        };
+        diagnostic!("Key matches: the payload's {} verified", verified_check);
         Ok(())
     }
 
-    /// Handles the extract command to retrieve data from a steganographic image
-    fn handle_extract_command(
-        &mut self,
-        input_path: String,
-        key_input: String,
-        expected_length: Option<usize>,
-    ) -> Result<()> {
-        // Load steganographic image as RGB
-        let steganographic_image = ImageReader::open(&input_path)?
+    /// Handles the detect command by scoring how likely an image is to carry a payload
+    fn handle_detect_command(&self, input_path: String) -> Result<()> {
+        let image = self.load_rgb_image(&input_path, None)?;
+        self.report_payload_likelihood(&image)
+    }
+
+    /// Handles the info command, combining the read-only diagnostics for one image
+    fn handle_info_command(&self, input_path: String) -> Result<()> {
+        let image_reader = ImageReader::open(&input_path)?.with_guessed_format()?;
+        let image_format = image_reader.format();
+        let decoded_image = image_reader
             .decode()
-            .map_err(|e| SteganographyError::ImageError(e.to_string()))?
-            .to_rgb8();
+            .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
+        let carrier_image = decoded_image.to_rgb8();
 
-        println!(
-            "Loaded steganographic image: {}x{} pixels",
-            steganographic_image.width(),
-            steganographic_image.height()
+        diagnostic!(
+            "File: {} ({} bytes)",
+            input_path,
+            fs::metadata(&input_path)?.len()
+        );
+        diagnostic!(
+            "Format: {}, {:?}",
+            image_format.map_or_else(|| "unknown".to_string(), |format| format!("{:?}", format)),
+            decoded_image.color()
+        );
+        diagnostic!(
+            "Dimensions: {}x{} pixels",
+            carrier_image.width(),
+            carrier_image.height()
         );
 
-        // Load encryption key
-        let encryption_key = self.cryptographic_engine.load_key_from_input(&key_input)?;
-
-        // Extract encrypted data from the image
-        let extracted_encrypted_data = self
+        // The default embedding carries one bit in the luminance of each 8x8 block, edge
+        // blocks included
+        let capacity_bits = self
             .steganography_engine
-            .extract_data_from_rgb_image(&steganographic_image, expected_length)?;
-
-        println!(
-            "Extracted {} bytes of encrypted data",
-            extracted_encrypted_data.len()
+            .calculate_capacity_bits(&carrier_image)?;
+        diagnostic!("8x8 blocks: {}", capacity_bits);
+        diagnostic!(
+            "Capacity: {} bits ({} bytes), maximum message size {} bytes with the default options",
+            capacity_bits,
+            capacity_bits / 8,
+            self.cryptographic_engine
+                .calculate_max_plaintext_length(capacity_bits / 8)
         );
 
-        // Decrypt the extracted data
-        let decrypted_data = self
-            .cryptographic_engine
-            .decrypt_with_error_correction(&encryption_key, &extracted_encrypted_data)?;
+        self.report_payload_likelihood(&carrier_image)
+    }
 
-        let secret_message = String::from_utf8(decrypted_data)?;
+    /// Prints how likely it is that `image` carries a payload, with the verdict
+    fn report_payload_likelihood(&self, image: &RgbImage) -> Result<()> {
+        let payload_likelihood = self.steganography_engine.detect_payload_presence(image)?;
 
-        println!("Successfully extracted secret message:");
-        println!("\"{}\"", secret_message);
+        diagnostic!("Payload likelihood: {:.0}%", payload_likelihood * 100.0);
+        if payload_likelihood >= LIKELY_PAYLOAD_PRESENCE {
+            diagnostic!("Verdict: likely contains hidden data");
+        } else {
+            diagnostic!("Verdict: no hidden data detected");
+            diagnostic!("Note: payloads hidden with --seed-positions-from-key, --macro-block, --channels other than luma or --alpha-channel are not detected");
+        }
 
         Ok(())
     }
 
     /// Handles the demo command to create a demonstration
-    fn handle_demo_command(&mut self) -> Result<()> {
-        println!("Creating demonstration...");
+    fn handle_demo_command(&self) -> Result<()> {
+        diagnostic!("Creating demonstration...");
 
         // Create a test image
         self.create_demonstration_image()?;
@@ -244,100 +2634,634 @@ impl CommandLineHandler {
         )?;
 
         // Extract and verify the message
-        let extracted_data = self
+        let extraction_result = self
             .steganography_engine
             .extract_data_from_rgb_image(&steganographic_image, None)?;
 
-        let recovered_data = self
-            .cryptographic_engine
-            .decrypt_with_error_correction(&encryption_key, &extracted_data)?;
+        let recovered_data = self.cryptographic_engine.decrypt_with_bit_reliability(
+            &encryption_key,
+            &extraction_result.data,
+            &extraction_result.bit_confidences,
+        )?;
 
         let recovered_message = String::from_utf8(recovered_data)?;
 
         // Save encryption key for manual testing
-        self.cryptographic_engine
-            .save_key_to_file(&encryption_key, "demo_encryption.key")?;
+        self.cryptographic_engine.save_key_to_file(
+            &encryption_key,
+            "demo_encryption.key",
+            KeyEncoding::Base64,
+        )?;
 
         // Display results
-        println!("\n=== DEMONSTRATION RESULTS ===");
-        println!("Original message: \"{}\"", demo_message);
-        println!("Recovered message: \"{}\"", recovered_message);
-        println!("Success: {}", demo_message == recovered_message);
+        diagnostic!("\n=== DEMONSTRATION RESULTS ===");
+        diagnostic!("Original message: \"{}\"", demo_message);
+        diagnostic!("Recovered message: \"{}\"", recovered_message);
+        diagnostic!("Success: {}", demo_message == recovered_message);
+
+        diagnostic!("\n=== FILES CREATED ===");
+        diagnostic!("📸 demo_test_image.jpg - Original test image");
+        diagnostic!("🔒 demo_hidden_message.jpg - JPEG with hidden message (ready for sharing!)");
+        diagnostic!("🔑 demo_encryption.key - Encryption key for manual testing");
+
+        diagnostic!("\n=== MANUAL TEST COMMANDS ===");
+        diagnostic!("Extract message: ./target/release/steg extract -i demo_hidden_message.jpg -k demo_encryption.key");
+
+        Ok(())
+    }
+
+    /// Loads an image as RGB, flattening any alpha channel
+    fn load_rgb_image(
+        &self,
+        input_path: &str,
+        input_format: Option<ImageFormat>,
+    ) -> Result<RgbImage> {
+        Ok(self.load_image(input_path, input_format)?.to_rgb8())
+    }
+
+    /// Loads an RGBA image without flattening, failing if it has no alpha channel
+    fn load_rgba_image(
+        &self,
+        input_path: &str,
+        input_format: Option<ImageFormat>,
+    ) -> Result<RgbaImage> {
+        CommandLineHandler::require_alpha_channel(
+            input_path,
+            &self.load_image(input_path, input_format)?,
+        )
+    }
+
+    /// Decodes an image, using the given decoder or guessing the format from the file
+    fn load_image(
+        &self,
+        input_path: &str,
+        input_format: Option<ImageFormat>,
+    ) -> Result<DynamicImage> {
+        let image_reader = match input_format {
+            Some(image_format) => {
+                ImageReader::with_format(BufReader::new(File::open(input_path)?), image_format)
+            }
+            None => ImageReader::open(input_path)?.with_guessed_format()?,
+        };
+
+        let decoded_image = image_reader.decode().map_err(|e| match input_format {
+            Some(image_format) => SteganographyError::ImageError(format!(
+                "{} is not a valid {:?} image: {}",
+                input_path, image_format, e
+            )),
+            None => SteganographyError::ImageError(e.to_string()),
+        })?;
+
+        Ok(decoded_image)
+    }
+
+    /// Switches the engine to per-block embedding positions derived from the encryption key
+    fn use_keyed_positions(&mut self, encryption_key: &[u8; 32]) {
+        let configuration = self
+            .steganography_engine
+            .embedding_algorithm()
+            .configuration()
+            .clone();
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine = steganography_engine.with_embedding_algorithm(
+            DctEmbeddingAlgorithm::with_keyed_block_positions(configuration, *encryption_key),
+        );
+    }
+
+    /// Switches the engine to embedding the frame header several times
+    fn use_protected_header(&mut self) {
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine =
+            steganography_engine.with_header_copies(DEFAULT_PROTECTED_HEADER_COPIES);
+    }
+
+    /// Switches the engine to visiting the blocks after the header in the given order
+    fn use_block_visitation(&mut self, block_visitation: BlockVisitation) {
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine = steganography_engine.with_block_visitation(block_visitation);
+    }
+
+    /// Seeds the keyed block visitation order with the encryption key
+    fn use_block_order_seed(&mut self, encryption_key: &[u8; 32]) {
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine = steganography_engine.with_block_order_seed(*encryption_key);
+    }
+
+    /// Refuses a carrier that already seems to hold a payload unless forced, then only warns
+    ///
+    /// Hiding over an earlier payload leaves parts of it behind, which makes the new one
+    /// harder to recover than with a clean carrier.
+    fn check_for_prior_embedding(&self, source_image: &RgbImage, force: bool) -> Result<()> {
+        if !self
+            .steganography_engine
+            .detect_prior_embedding(source_image)?
+        {
+            return Ok(());
+        }
+
+        if !force {
+            return Err(SteganographyError::InvalidInput(
+                "the carrier appears to already contain hidden data; hide in a clean copy of the original image, or pass --force to embed anyway".to_string(),
+            ));
+        }
+        diagnostic!(
+            "Warning: the carrier appears to already contain hidden data; embedding anyway because of --force (a clean carrier extracts more reliably)"
+        );
+        Ok(())
+    }
+
+    /// Follows the block visitation order recorded in the frame header
+    ///
+    /// An unreadable header keeps the default order; the full extraction then reports the
+    /// damage.
+    fn use_recorded_block_visitation(&mut self, steganographic_image: &RgbImage) {
+        if let Ok(block_visitation) = self
+            .steganography_engine
+            .read_block_visitation(steganographic_image)
+        {
+            if block_visitation != BlockVisitation::default() {
+                diagnostic!("Block visitation: {}", block_visitation);
+            }
+            self.use_block_visitation(block_visitation);
+        }
+    }
+
+    /// Decrypts a payload hidden with `--framing sentinel` and presents it as text
+    fn extract_sentinel_payload(
+        &self,
+        encryption_key: &[u8; 32],
+        extraction_result: &ExtractionResult,
+        strict_utf8: bool,
+        raw_encoding: Option<RawEncoding>,
+        output_file_path: Option<String>,
+    ) -> Result<()> {
+        let decrypted_data = self.cryptographic_engine.decrypt_sentinel_frame(
+            encryption_key,
+            &extraction_result.data,
+            &extraction_result.bit_confidences,
+        )?;
+
+        // Score only the framed payload since unused cover blocks carry no signal
+        let frame_length = self
+            .cryptographic_engine
+            .sentinel_frame_length(decrypted_data.len());
+        self.report_extraction_confidence(
+            extraction_result.confidence_for_leading_bytes(frame_length),
+        );
+
+        self.present_extracted_payload(
+            decrypted_data,
+            ContentType::Text,
+            strict_utf8,
+            raw_encoding,
+            output_file_path,
+        )
+    }
+
+    /// Switches the cryptographic engine to repeating each byte `repetition_factor` times
+    ///
+    /// Even factors are rejected because majority voting can't break their ties.
+    fn use_repetition_factor(&mut self, repetition_factor: usize) -> Result<()> {
+        if repetition_factor.is_multiple_of(2) {
+            return Err(SteganographyError::InvalidInput(format!(
+                "repetition factor must be odd and at least 1, got {}",
+                repetition_factor
+            )));
+        }
+        let coding_scheme = self.cryptographic_engine.coding_scheme();
+        self.cryptographic_engine = CryptographicEngine::with_repetition_factor(repetition_factor)
+            .with_coding_scheme(coding_scheme);
+        Ok(())
+    }
+
+    /// Switches the cryptographic engine to protecting payloads with the given code
+    fn use_coding_scheme(&mut self, coding_scheme: CodingScheme) {
+        let cryptographic_engine = std::mem::take(&mut self.cryptographic_engine);
+        self.cryptographic_engine = cryptographic_engine.with_coding_scheme(coding_scheme);
+    }
+
+    /// Switches the engine to embedding in the given YCbCr channels
+    ///
+    /// Must run before `use_keyed_positions`, which keeps the configuration it finds.
+    fn use_embedding_channel(&mut self, embedding_channel: EmbeddingChannel) {
+        let configuration = EmbeddingConfiguration {
+            embedding_channel,
+            ..self
+                .steganography_engine
+                .embedding_algorithm()
+                .configuration()
+                .clone()
+        };
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine = steganography_engine.with_embedding_algorithm(
+            DctEmbeddingAlgorithm::with_block_configuration(configuration),
+        );
+    }
+
+    /// Switches the engine to carrying each bit in every one of the given DCT coefficients
+    ///
+    /// Positions are parsed against the largest block size, so those outside the engine's
+    /// blocks are rejected here. Must run before `use_keyed_positions`, which keeps the
+    /// configuration it finds.
+    fn use_embedding_positions(&mut self, embedding_positions: EmbeddingPositions) -> Result<()> {
+        let embedding_positions = embedding_positions.into_inner();
+        if let Some(&(coefficient_y, coefficient_x)) = embedding_positions
+            .iter()
+            .find(|&&(coefficient_y, coefficient_x)| coefficient_y >= N || coefficient_x >= N)
+        {
+            return Err(SteganographyError::PositionParseError(format!(
+                "'{},{}' is outside the {}x{} block; pass a larger --block-size to use it",
+                coefficient_y, coefficient_x, N, N
+            )));
+        }
+        let configuration = EmbeddingConfiguration {
+            redundant_position_count: embedding_positions.len(),
+            embedding_positions,
+            ..self
+                .steganography_engine
+                .embedding_algorithm()
+                .configuration()
+                .clone()
+        };
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine = steganography_engine.with_embedding_algorithm(
+            DctEmbeddingAlgorithm::with_block_configuration(configuration),
+        );
+        Ok(())
+    }
+
+    /// Switches the engine to scaling the embedding strength by each block's texture
+    ///
+    /// Must run before `use_keyed_positions`, which keeps the configuration it finds.
+    fn use_adaptive_strength(&mut self) {
+        let configuration = EmbeddingConfiguration {
+            adaptive_strength: AdaptiveStrength::PHOTOGRAPHIC,
+            ..self
+                .steganography_engine
+                .embedding_algorithm()
+                .configuration()
+                .clone()
+        };
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine = steganography_engine.with_embedding_algorithm(
+            DctEmbeddingAlgorithm::with_block_configuration(configuration),
+        );
+    }
+
+    /// Switches the engine to embedding each bit across a macro-block region
+    fn use_macro_blocks(&mut self) {
+        let steganography_engine = std::mem::take(&mut self.steganography_engine);
+        self.steganography_engine =
+            steganography_engine.with_macro_block_scale(DEFAULT_MACRO_BLOCK_SCALE);
+    }
+
+    /// Converts a recovered message to text, replacing invalid UTF-8 unless strict mode is on
+    fn decode_message_text(&self, decrypted_data: Vec<u8>, strict_utf8: bool) -> Result<String> {
+        match String::from_utf8(decrypted_data) {
+            Ok(secret_message) => Ok(secret_message),
+            Err(utf8_error) if strict_utf8 => Err(utf8_error.into()),
+            Err(utf8_error) => {
+                let decrypted_data = utf8_error.into_bytes();
+                let lossy_message = String::from_utf8_lossy(&decrypted_data).into_owned();
+                let replaced_count = decrypted_data
+                    .utf8_chunks()
+                    .filter(|utf8_chunk| !utf8_chunk.invalid().is_empty())
+                    .count();
+                diagnostic!(
+                    "Warning: recovered message is not valid UTF-8; {} invalid sequence(s) shown as {} (recovery may be incomplete, use --strict-utf8 to fail instead)",
+                    replaced_count,
+                    char::REPLACEMENT_CHARACTER
+                );
+                Ok(lossy_message)
+            }
+        }
+    }
+
+    /// Prints, hex-dumps or saves the recovered payload as its frame content type says
+    fn present_extracted_payload(
+        &self,
+        decrypted_data: Vec<u8>,
+        content_type: ContentType,
+        strict_utf8: bool,
+        raw_encoding: Option<RawEncoding>,
+        output_file_path: Option<String>,
+    ) -> Result<()> {
+        // A raw dump shows exactly what was decrypted, so even gzip payloads stay compressed
+        if let Some(raw_encoding) = raw_encoding {
+            diagnostic!(
+                "Decrypted {} bytes of {} payload, dumped raw:",
+                decrypted_data.len(),
+                content_type
+            );
+            match raw_encoding {
+                RawEncoding::Hex => {
+                    print!("{}", CommandLineHandler::format_hex_dump(&decrypted_data))
+                }
+                RawEncoding::Base64 => {
+                    println!("{}", general_purpose::STANDARD.encode(&decrypted_data))
+                }
+            }
+            return Ok(());
+        }
+
+        let decrypted_data = if content_type == ContentType::Gzip {
+            let decompressed_data = decompress_gzip(&decrypted_data)?;
+            diagnostic!(
+                "Decompressed gzip payload: {} bytes -> {} bytes",
+                decrypted_data.len(),
+                decompressed_data.len()
+            );
+            decompressed_data
+        } else {
+            decrypted_data
+        };
+
+        if self.json_output {
+            return self.print_extract_json(
+                decrypted_data,
+                content_type,
+                strict_utf8,
+                output_file_path,
+            );
+        }
+
+        if let Some(output_file_path) = output_file_path {
+            fs::write(&output_file_path, &decrypted_data)?;
+            diagnostic!(
+                "Saved {} bytes of {} payload to: {}",
+                decrypted_data.len(),
+                content_type,
+                output_file_path
+            );
+            return Ok(());
+        }
 
-        println!("\n=== FILES CREATED ===");
-        println!("📸 demo_test_image.jpg - Original test image");
-        println!("🔒 demo_hidden_message.jpg - JPEG with hidden message (ready for sharing!)");
-        println!("🔑 demo_encryption.key - Encryption key for manual testing");
+        let is_text = CommandLineHandler::is_text_payload(content_type, &decrypted_data);
+        if !is_text {
+            diagnostic!(
+                "Successfully extracted {} bytes of binary data (use --output-file to save it):",
+                decrypted_data.len()
+            );
+            print!("{}", CommandLineHandler::format_hex_dump(&decrypted_data));
+            return Ok(());
+        }
+
+        let secret_message = self.decode_message_text(decrypted_data, strict_utf8)?;
+        if content_type == ContentType::Json {
+            diagnostic!("Successfully extracted JSON document:");
+            diagnostic!("{}", secret_message);
+        } else {
+            diagnostic!("Successfully extracted secret message:");
+            diagnostic!("\"{}\"", secret_message);
+        }
+        Ok(())
+    }
+
+    /// Prints the result of an extract as a JSON object on stdout
+    ///
+    /// `message` holds the recovered text, or `null` for binary payloads and payloads
+    /// written to `--output-file`.
+    fn print_extract_json(
+        &self,
+        decrypted_data: Vec<u8>,
+        content_type: ContentType,
+        strict_utf8: bool,
+        output_file_path: Option<String>,
+    ) -> Result<()> {
+        let bytes_extracted = decrypted_data.len();
+        let is_text = CommandLineHandler::is_text_payload(content_type, &decrypted_data);
+        let message = if let Some(output_file_path) = &output_file_path {
+            fs::write(output_file_path, &decrypted_data)?;
+            None
+        } else if is_text {
+            Some(self.decode_message_text(decrypted_data, strict_utf8)?)
+        } else {
+            None
+        };
 
-        println!("\n=== MANUAL TEST COMMANDS ===");
-        println!("Extract message: ./target/release/steg extract -i demo_hidden_message.jpg -k demo_encryption.key");
+        println!(
+            "{}",
+            json_object(&[
+                ("message", message.into()),
+                ("bytes_extracted", bytes_extracted.into()),
+                ("content_type", content_type.name().into()),
+                ("output_file", output_file_path.into()),
+            ])
+        );
+        Ok(())
+    }
 
+    /// Runs the calibration pass and reports the threshold it chose
+    fn calibrate_noise_floor(&mut self, steganographic_image: &RgbImage) -> Result<()> {
+        match self
+            .steganography_engine
+            .calibrate_noise_floor(steganographic_image)?
+        {
+            Some(noise_floor_estimate) => diagnostic!(
+                "Noise floor: embedded amplitude {:.1}, noise level {:.1}, voting threshold {:.1}",
+                noise_floor_estimate.embedded_amplitude,
+                noise_floor_estimate.noise_level,
+                noise_floor_estimate.vote_threshold
+            ),
+            None => diagnostic!("Image too small to calibrate; keeping the fixed voting threshold"),
+        }
         Ok(())
     }
 
+    /// Explains a failed hide in message bytes and suggests how to make the message fit
+    fn report_capacity_remedies(
+        &self,
+        error: &SteganographyError,
+        encrypted_length: usize,
+        message_length: usize,
+    ) {
+        if let SteganographyError::CapacityError {
+            required,
+            available,
+        } = *error
+        {
+            for capacity_hint in
+                self.capacity_hints(required, available, encrypted_length, message_length)
+            {
+                diagnostic!("{}", capacity_hint);
+            }
+        }
+    }
+
+    /// Builds the lines explaining how large a message fits and what to change
+    ///
+    /// Anything the carrier spends beyond the encrypted frame itself, such as replicated
+    /// header copies, is subtracted before converting the capacity back to message bytes.
+    fn capacity_hints(
+        &self,
+        required_bits: usize,
+        available_bits: usize,
+        encrypted_length: usize,
+        message_length: usize,
+    ) -> Vec<String> {
+        let layout_overhead_bits = required_bits.saturating_sub(encrypted_length * 8);
+        let frame_capacity_bytes = available_bits.saturating_sub(layout_overhead_bits) / 8;
+        let max_message_length = self
+            .cryptographic_engine
+            .calculate_max_plaintext_length(frame_capacity_bytes);
+
+        let mut capacity_hints = vec![
+            format!(
+                "Your message is {} bytes but this image fits at most {} bytes ({} bytes after encryption and error correction).",
+                message_length,
+                max_message_length,
+                encrypted_length
+            ),
+            "To make it fit:".to_string(),
+            format!("  - use a larger carrier image (each {0}x{0} block holds one bit)", N),
+            "  - add --compress to deflate text before encryption".to_string(),
+        ];
+        if self.cryptographic_engine.coding_scheme() == CodingScheme::Repetition {
+            capacity_hints.push(
+                "  - add --ecc rs for Reed-Solomon error correction (far less overhead than repetition)".to_string(),
+            );
+        }
+        if self
+            .steganography_engine
+            .embedding_algorithm()
+            .configuration()
+            .embedding_channel
+            != EmbeddingChannel::All
+        {
+            capacity_hints.push(
+                "  - add --channels all to embed in the chroma channels too (up to 3x the capacity, less robust to JPEG)".to_string(),
+            );
+        }
+        capacity_hints.push(
+            "  - for an opaque RGBA PNG, use --alpha-channel (one bit per pixel, lossless only)"
+                .to_string(),
+        );
+        capacity_hints
+    }
+
+    /// Prints the extraction confidence and warns when recovery is close to failing
+    fn report_extraction_confidence(&self, confidence: f32) {
+        diagnostic!("Extraction confidence: {:.0}%", confidence * 100.0);
+
+        if confidence < LOW_EXTRACTION_CONFIDENCE {
+            diagnostic!(
+                "Warning: extraction confidence is low; further recompression of this image may make the message unrecoverable"
+            );
+        }
+    }
+
+    /// Derives the hide key from a passphrase and a fresh salt, or loads or generates a key
+    /// file when no passphrase is given
+    ///
+    /// Returns the salt alongside the key so it can be stored with the payload. A dry run
+    /// still loads an existing key file, but a key it would generate is kept in memory only.
+    fn resolve_hide_encryption_key(
+        &self,
+        output_path: &str,
+        key_file_path: Option<String>,
+        key_encoding: KeyEncoding,
+        passphrase: Option<&str>,
+        dry_run: bool,
+    ) -> Result<([u8; 32], Option<[u8; PASSPHRASE_SALT_SIZE]>)> {
+        let Some(passphrase) = passphrase else {
+            let key_path = CommandLineHandler::resolve_key_file_path(output_path, &key_file_path);
+            if dry_run && !Path::new(&key_path).exists() {
+                diagnostic!(
+                    "Dry run: would generate a new encryption key and save it to: {}",
+                    key_path
+                );
+                return Ok((CryptographicEngine::generate_encryption_key(), None));
+            }
+            let encryption_key =
+                self.get_or_generate_encryption_key(output_path, key_file_path, key_encoding)?;
+            return Ok((encryption_key, None));
+        };
+
+        let passphrase_salt = CryptographicEngine::generate_passphrase_salt();
+        let encryption_key = self
+            .cryptographic_engine
+            .derive_key_from_passphrase(passphrase, &passphrase_salt)?;
+        diagnostic!("Derived encryption key from passphrase; no key file written");
+        Ok((encryption_key, Some(passphrase_salt)))
+    }
+
+    /// Derives the extraction key from a passphrase and the salt stored in the frame
+    fn derive_extraction_key(
+        &self,
+        passphrase: &str,
+        extracted_encrypted_data: &[u8],
+    ) -> Result<[u8; 32]> {
+        let passphrase_salt = self
+            .cryptographic_engine
+            .read_passphrase_salt(extracted_encrypted_data)?
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(
+                    "This image was not hidden with a passphrase; use --key instead".to_string(),
+                )
+            })?;
+        self.cryptographic_engine
+            .derive_key_from_passphrase(passphrase, &passphrase_salt)
+    }
+
     /// Gets or generates an encryption key based on the provided parameters
     fn get_or_generate_encryption_key(
         &self,
         output_path: &str,
         key_file_path: Option<String>,
+        key_encoding: KeyEncoding,
     ) -> Result<[u8; 32]> {
-        match key_file_path {
-            Some(key_path) => {
-                if Path::new(&key_path).exists() {
-                    // Load existing key
-                    self.cryptographic_engine.load_key_from_input(&key_path)
-                } else {
-                    // Generate new key and save to specified path
-                    let new_key = CryptographicEngine::generate_encryption_key();
-                    self.cryptographic_engine
-                        .save_key_to_file(&new_key, &key_path)?;
-                    println!("Generated new encryption key and saved to: {}", key_path);
-                    Ok(new_key)
-                }
-            }
-            None => {
-                // Generate new key and save with output name
-                let new_key = CryptographicEngine::generate_encryption_key();
-                let auto_key_path = format!("{}.key", output_path);
-                self.cryptographic_engine
-                    .save_key_to_file(&new_key, &auto_key_path)?;
-                println!("Generated new encryption key and saved to: {}", auto_key_path);
-                Ok(new_key)
-            }
+        let key_path = CommandLineHandler::resolve_key_file_path(output_path, &key_file_path);
+
+        if Path::new(&key_path).exists() {
+            // Never replace an existing key file: a valid one is reused, a malformed one is an error
+            return self
+                .cryptographic_engine
+                .load_key_from_input(&key_path)
+                .map_err(|e| {
+                    SteganographyError::InvalidInput(format!(
+                        "Existing key file {} is not a valid key ({}); refusing to overwrite it",
+                        key_path, e
+                    ))
+                });
         }
+
+        let new_key = CryptographicEngine::generate_encryption_key();
+        self.cryptographic_engine
+            .save_key_to_file(&new_key, &key_path, key_encoding)?;
+        diagnostic!("Generated new encryption key and saved to: {}", key_path);
+        Ok(new_key)
     }
 
     /// Determines the output file path with appropriate extension
-    fn get_output_file_path(&self, output_path: &str, jpeg_quality: u8) -> String {
-        if output_path.ends_with(".jpg") || output_path.ends_with(".jpeg") {
+    fn get_output_file_path(&self, output_path: &str, output_format: OutputFormat) -> String {
+        if OutputFormat::from_path(output_path) == Some(output_format) {
             output_path.to_string()
-        } else if jpeg_quality == 100 {
-            // For testing: save as PNG to avoid compression
-            format!("{}.png", output_path)
         } else {
-            format!("{}.jpg", output_path)
+            format!("{}.{}", output_path, output_format.extensions()[0])
         }
     }
 
-    /// Saves the steganographic image with appropriate format
+    /// Saves the steganographic image with the selected encoder
     fn save_steganographic_image(
         &self,
         steganographic_image: &RgbImage,
         output_path: &str,
+        output_format: OutputFormat,
         jpeg_quality: u8,
     ) -> Result<()> {
-        if jpeg_quality == 100 && output_path.ends_with(".png") {
-            // Save as PNG for testing purposes
-            steganographic_image
-                .save(output_path)
-                .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
-            println!("Test mode: Saved as PNG to avoid compression");
-        } else {
-            // Save as JPEG with specified quality
-            self.steganography_engine
-                .save_rgb_image_as_jpeg(steganographic_image, output_path, jpeg_quality)?;
+        match output_format {
+            OutputFormat::Jpeg => self.steganography_engine.save_rgb_image_as_jpeg(
+                steganographic_image,
+                output_path,
+                jpeg_quality,
+            ),
+            OutputFormat::Png => self
+                .steganography_engine
+                .save_rgb_image_as_png(steganographic_image, output_path),
+            OutputFormat::WebP => self
+                .steganography_engine
+                .save_rgb_image_as_webp(steganographic_image, output_path),
         }
-        Ok(())
     }
 
     /// Creates a colorful test image for demonstration
@@ -356,7 +3280,7 @@ impl CommandLineHandler {
             .save("demo_test_image.jpg")
             .map_err(|e| SteganographyError::ImageError(e.to_string()))?;
 
-        println!("Created colorful test image: demo_test_image.jpg");
+        diagnostic!("Created colorful test image: demo_test_image.jpg");
         Ok(())
     }
 }
@@ -366,3 +3290,217 @@ impl Default for CommandLineHandler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_existing_valid_key_file_is_loaded_not_regenerated() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key_path = temp_dir.path().join("existing.key");
+        let key_path = key_path.to_str().unwrap().to_string();
+
+        let handler = CommandLineHandler::new();
+        let existing_key = CryptographicEngine::generate_encryption_key();
+        handler
+            .cryptographic_engine
+            .save_key_to_file(&existing_key, &key_path, KeyEncoding::Base64)
+            .unwrap();
+        let existing_contents = fs::read_to_string(&key_path).unwrap();
+
+        let loaded_key = handler
+            .get_or_generate_encryption_key("unused", Some(key_path.clone()), KeyEncoding::Hex)
+            .unwrap();
+
+        assert_eq!(loaded_key, existing_key);
+        assert_eq!(fs::read_to_string(&key_path).unwrap(), existing_contents);
+    }
+
+    #[test]
+    fn test_existing_malformed_key_file_is_not_overwritten() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output_path = temp_dir.path().join("hidden");
+        let output_path = output_path.to_str().unwrap();
+        let key_path = format!("{}.key", output_path);
+        fs::write(&key_path, "not a key").unwrap();
+
+        let handler = CommandLineHandler::new();
+        let result = handler.get_or_generate_encryption_key(output_path, None, KeyEncoding::Base64);
+
+        assert!(matches!(
+            result,
+            Err(SteganographyError::InvalidInput(message)) if message.contains("refusing to overwrite")
+        ));
+        assert_eq!(fs::read_to_string(&key_path).unwrap(), "not a key");
+    }
+
+    #[test]
+    fn test_missing_key_file_is_generated_and_saved() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key_path = temp_dir.path().join("new.key");
+        let key_path = key_path.to_str().unwrap().to_string();
+
+        let handler = CommandLineHandler::new();
+        let generated_key = handler
+            .get_or_generate_encryption_key("unused", Some(key_path.clone()), KeyEncoding::Hex)
+            .unwrap();
+
+        assert_eq!(
+            handler
+                .cryptographic_engine
+                .load_key_from_input(&key_path)
+                .unwrap(),
+            generated_key
+        );
+        assert!(fs::read_to_string(&key_path)
+            .unwrap()
+            .lines()
+            .any(|line| line
+                .strip_prefix("key: ")
+                .is_some_and(|key| key.len() == 64)));
+    }
+
+    #[test]
+    fn test_writability_check_leaves_targets_untouched() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let existing_path = temp_dir.path().join("existing.jpg");
+        let existing_path = existing_path.to_str().unwrap();
+        fs::write(existing_path, "keep me").unwrap();
+        let new_path = temp_dir.path().join("new.jpg");
+        let new_path = new_path.to_str().unwrap();
+
+        CommandLineHandler::ensure_writable(existing_path).unwrap();
+        CommandLineHandler::ensure_writable(new_path).unwrap();
+
+        assert_eq!(fs::read_to_string(existing_path).unwrap(), "keep me");
+        assert!(!Path::new(new_path).exists());
+    }
+
+    #[test]
+    fn test_writability_check_rejects_missing_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output_path = temp_dir.path().join("missing").join("hidden");
+        let output_path = output_path.to_str().unwrap();
+
+        assert!(matches!(
+            CommandLineHandler::ensure_hide_outputs_writable(
+                &format!("{}.jpg", output_path),
+                output_path,
+                &None
+            ),
+            Err(SteganographyError::IoError(_))
+        ));
+
+        // The image is fine but the requested key file can't be created
+        let writable_output = temp_dir.path().join("hidden.jpg");
+        assert!(matches!(
+            CommandLineHandler::ensure_hide_outputs_writable(
+                writable_output.to_str().unwrap(),
+                "hidden",
+                &Some(format!("{}.key", output_path))
+            ),
+            Err(SteganographyError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_capacity_hints_report_message_size_that_fits() {
+        let handler = CommandLineHandler::new();
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, &[b'x'; 40])
+            .unwrap();
+
+        // A 256x256 carrier has 1024 blocks, and three header copies cost another 160 bits
+        let required_bits = encrypted_data.len() * 8 + 160;
+        let capacity_hints = handler.capacity_hints(required_bits, 1024, encrypted_data.len(), 40);
+
+        let max_message_length = crypto_engine.calculate_max_plaintext_length(108);
+        assert!(max_message_length > 0 && max_message_length < 40);
+        assert!(capacity_hints[0].contains(&format!("at most {} bytes", max_message_length)));
+        assert!(capacity_hints
+            .iter()
+            .any(|hint| hint.contains("--compress")));
+    }
+
+    #[test]
+    fn test_even_repetition_factor_is_rejected() {
+        let mut handler = CommandLineHandler::new();
+        for repetition_factor in [0, 2, 4] {
+            assert!(matches!(
+                handler.use_repetition_factor(repetition_factor),
+                Err(SteganographyError::InvalidInput(message)) if message.contains("odd")
+            ));
+        }
+
+        handler.use_coding_scheme(CodingScheme::ReedSolomon);
+        handler.use_repetition_factor(3).unwrap();
+        assert_eq!(handler.cryptographic_engine.repetition_factor(), 3);
+        assert_eq!(
+            handler.cryptographic_engine.coding_scheme(),
+            CodingScheme::ReedSolomon
+        );
+    }
+
+    #[test]
+    fn test_reused_carrier_is_refused_unless_forced() {
+        let handler = CommandLineHandler::new();
+        let clean_carrier = RgbImage::from_fn(128, 128, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 2) as u8, ((x * y) % 256) as u8])
+        });
+        let reused_carrier = handler
+            .steganography_engine
+            .hide_data_in_rgb_image(&clean_carrier, &[0x5a; 32], 85)
+            .unwrap();
+
+        assert!(handler
+            .check_for_prior_embedding(&clean_carrier, false)
+            .is_ok());
+        assert!(matches!(
+            handler.check_for_prior_embedding(&reused_carrier, false),
+            Err(SteganographyError::InvalidInput(message)) if message.contains("--force")
+        ));
+        assert!(handler
+            .check_for_prior_embedding(&reused_carrier, true)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_content_type_detected_from_file_contents() {
+        let gzip_data = [0x1f, 0x8b, 0x08, 0x00];
+        let binary_data = [0xff, 0xfe, 0x00, 0x80];
+
+        assert_eq!(
+            CommandLineHandler::detect_content_type("notes.txt", b"plain text"),
+            ContentType::Text
+        );
+        assert_eq!(
+            CommandLineHandler::detect_content_type("config.JSON", b"{\"a\": 1}"),
+            ContentType::Json
+        );
+        assert_eq!(
+            CommandLineHandler::detect_content_type("archive.tar.gz", &gzip_data),
+            ContentType::Gzip
+        );
+        assert_eq!(
+            CommandLineHandler::detect_content_type("blob.json", &binary_data),
+            ContentType::Binary
+        );
+    }
+
+    #[test]
+    fn test_hex_dump_lines_show_offset_hex_and_ascii() {
+        let hex_dump = CommandLineHandler::format_hex_dump(b"0123456789abcdef\x00Hi");
+        let dump_lines: Vec<&str> = hex_dump.lines().collect();
+
+        assert_eq!(dump_lines.len(), 2);
+        assert!(dump_lines[0].starts_with("00000000  30 31 32"));
+        assert!(dump_lines[0].ends_with("|0123456789abcdef|"));
+        assert!(dump_lines[1].starts_with("00000010  00 48 69 "));
+        assert!(dump_lines[1].ends_with("|.Hi|"));
+        assert_eq!(dump_lines[0].find('|'), dump_lines[1].find('|'));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::{Result, SteganographyError};
+use flate2::{
+    read::{DeflateDecoder, GzDecoder},
+    write::DeflateEncoder,
+    Compression,
+};
+use std::io::{Read, Write};
+
+/// Upper bound on decompressed payload size to guard against decompression bombs
+const MAX_DECOMPRESSED_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Magic bytes opening every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Compresses a payload with raw DEFLATE at maximum compression
+///
+/// The zlib wrapper is left off on purpose: the frame's plaintext CRC32 already guards
+/// integrity, so its header and Adler-32 trailer would only cost six bytes of capacity.
+pub fn compress_payload(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut deflate_encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    deflate_encoder
+        .write_all(payload)
+        .map_err(|error| SteganographyError::CompressionError(error.to_string()))?;
+    deflate_encoder
+        .finish()
+        .map_err(|error| SteganographyError::CompressionError(error.to_string()))
+}
+
+/// Decompresses a raw DEFLATE payload produced by `compress_payload`
+pub fn decompress_payload(compressed_payload: &[u8]) -> Result<Vec<u8>> {
+    read_bounded(DeflateDecoder::new(compressed_payload))
+}
+
+/// Returns true if `payload` starts with the gzip magic bytes
+pub fn is_gzip_stream(payload: &[u8]) -> bool {
+    payload.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompresses a gzip stream, such as a `.gz` file hidden as-is
+pub fn decompress_gzip(gzip_payload: &[u8]) -> Result<Vec<u8>> {
+    read_bounded(GzDecoder::new(gzip_payload))
+}
+
+/// Reads a decompressing stream to the end, refusing output beyond `MAX_DECOMPRESSED_SIZE`
+fn read_bounded(decoder: impl Read) -> Result<Vec<u8>> {
+    let mut decompressed_payload = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decompressed_payload)
+        .map_err(|error| SteganographyError::CompressionError(error.to_string()))?;
+
+    if decompressed_payload.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(SteganographyError::CompressionError(format!(
+            "Decompressed payload exceeds {} bytes",
+            MAX_DECOMPRESSED_SIZE
+        )));
+    }
+
+    Ok(decompressed_payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_roundtrip() {
+        let test_data = "repeat me ".repeat(200);
+
+        let compressed_data = compress_payload(test_data.as_bytes()).unwrap();
+        let decompressed_data = decompress_payload(&compressed_data).unwrap();
+
+        assert!(compressed_data.len() < test_data.len());
+        assert_eq!(test_data.as_bytes(), decompressed_data.as_slice());
+    }
+
+    #[test]
+    fn test_gzip_stream_decompresses() {
+        let test_data = b"gzipped payload ".repeat(20);
+        let mut gzip_encoder = flate2::write::GzEncoder::new(Vec::new(), Compression::fast());
+        gzip_encoder.write_all(&test_data).unwrap();
+        let gzip_payload = gzip_encoder.finish().unwrap();
+
+        assert!(is_gzip_stream(&gzip_payload));
+        assert!(!is_gzip_stream(&test_data));
+        assert_eq!(decompress_gzip(&gzip_payload).unwrap(), test_data);
+        assert!(decompress_gzip(&test_data).is_err());
+    }
+}
@@ -1,11 +1,25 @@
+use crate::compression::{compress_payload, decompress_payload};
 use crate::error::{Result, SteganographyError};
+use crate::frame::{
+    BlockVisitation, CodingScheme, ContentType, FrameHeader, Payload, SENTINEL_END_MARKER,
+    SENTINEL_START_MARKER,
+};
+use crate::output::diagnostic;
+use crate::reed_solomon;
+use argon2::Argon2;
 use base64::{engine::general_purpose, Engine as _};
 use chacha20::{
     cipher::{KeyIvInit, StreamCipher},
-    ChaCha20,
+    ChaCha20, Nonce,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305,
 };
 use rand::RngCore;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::{fs, path::Path};
+use subtle::ConstantTimeEq;
 
 /// ChaCha20 encryption key size in bytes
 const ENCRYPTION_KEY_SIZE: usize = 32;
@@ -13,25 +27,132 @@ const ENCRYPTION_KEY_SIZE: usize = 32;
 /// ChaCha20 nonce size in bytes
 const NONCE_SIZE: usize = 12;
 
+/// Poly1305 tag size in bytes, appended to authenticated ciphertext
+const AUTHENTICATION_TAG_SIZE: usize = 16;
+
+/// CRC32 size in bytes, appended to the plaintext before encryption
+const PLAINTEXT_CHECKSUM_SIZE: usize = 4;
+
+/// Size of the random salt stored with a payload encrypted under a passphrase-derived key
+pub const PASSPHRASE_SALT_SIZE: usize = 16;
+
+/// First line of a framed key file
+const KEY_FILE_MAGIC: &str = "STEG-KEY";
+
+/// Current key file format version
+const KEY_FILE_VERSION: u32 = 1;
+
+/// Floor on per-bit reliability so zero-confidence reads still break ties by simple majority
+const MINIMUM_BIT_WEIGHT: f32 = 0.01;
+
 /// Default repetition factor for error correction
-const DEFAULT_REPETITION_FACTOR: usize = 5;
+pub const DEFAULT_REPETITION_FACTOR: usize = 5;
+
+/// Text encoding of a key written to a key file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyEncoding {
+    #[default]
+    Base64,
+    /// Lowercase hexadecimal, two digits per key byte
+    Hex,
+}
+
+impl KeyEncoding {
+    /// Looks up an encoding by its command-line name, `base64` or `hex`
+    pub fn from_name(encoding_name: &str) -> Option<Self> {
+        match encoding_name {
+            "base64" => Some(Self::Base64),
+            "hex" => Some(Self::Hex),
+            _ => None,
+        }
+    }
+
+    /// Encodes key bytes as text in this encoding
+    fn encode(self, encryption_key: &[u8; ENCRYPTION_KEY_SIZE]) -> String {
+        match self {
+            Self::Base64 => general_purpose::STANDARD.encode(encryption_key),
+            Self::Hex => encryption_key
+                .iter()
+                .map(|key_byte| format!("{:02x}", key_byte))
+                .collect(),
+        }
+    }
+}
+
+/// Settings recorded in the frame alongside an encrypted payload
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameOptions {
+    /// Deflate the payload first, unless that doesn't shrink it
+    pub compress: bool,
+    /// Carrier width and height before padding, so extraction can crop it back
+    pub original_dimensions: Option<(u16, u16)>,
+    /// What the plaintext is, so extraction can present it
+    pub content_type: ContentType,
+    /// Order the stego layer embeds the body's blocks in
+    pub block_visitation: BlockVisitation,
+    /// Salt the key was derived from a passphrase with, stored ahead of the nonce
+    pub passphrase_salt: Option<[u8; PASSPHRASE_SALT_SIZE]>,
+    /// Seal with ChaCha20-Poly1305 so a wrong key or damaged payload is detected
+    pub authenticated: bool,
+    /// Fingerprint of the stego layer's parameters, so extraction can detect a mismatch
+    pub configuration_fingerprint: Option<u16>,
+}
 
-/// Cryptographic engine handling ChaCha20 encryption and repetition coding
+/// Cryptographic engine handling ChaCha20 encryption and error correction coding
 pub struct CryptographicEngine {
     repetition_factor: usize,
+    /// Code applied to payloads this engine encrypts; decryption follows the frame header
+    coding_scheme: CodingScheme,
+    /// Next nonce counter value, or `None` to draw nonces from the thread RNG
+    nonce_counter: Option<AtomicU64>,
 }
 
 impl CryptographicEngine {
     /// Creates a new cryptographic engine with default settings
     pub fn new() -> Self {
-        Self {
-            repetition_factor: DEFAULT_REPETITION_FACTOR,
-        }
+        Self::with_repetition_factor(DEFAULT_REPETITION_FACTOR)
     }
 
     /// Creates a new cryptographic engine with custom repetition factor
     pub fn with_repetition_factor(repetition_factor: usize) -> Self {
-        Self { repetition_factor }
+        Self {
+            repetition_factor,
+            coding_scheme: CodingScheme::default(),
+            nonce_counter: None,
+        }
+    }
+
+    /// Creates an engine whose nonces come from a counter starting at `counter_start`
+    ///
+    /// ChaCha20 is only secure while no nonce is used twice with the same key. Random
+    /// nonces make a repeat astronomically unlikely; a counter rules it out, provided the
+    /// key is only ever encrypted with through counters whose ranges don't overlap. Persist
+    /// the next counter value between runs and never hand out the same start twice for a
+    /// key. Each nonce is four zero bytes followed by the counter in big-endian order, and
+    /// encryption fails once the counter is exhausted rather than wrapping around.
+    pub fn with_deterministic_nonce(counter_start: u64) -> Self {
+        Self {
+            nonce_counter: Some(AtomicU64::new(counter_start)),
+            ..Self::new()
+        }
+    }
+
+    /// Protects encrypted payloads with `coding_scheme` instead of repetition coding
+    ///
+    /// The scheme is recorded in the frame header, so any engine can decrypt the result.
+    pub fn with_coding_scheme(mut self, coding_scheme: CodingScheme) -> Self {
+        self.coding_scheme = coding_scheme;
+        self
+    }
+
+    /// Returns how many times each encrypted byte is repeated
+    pub fn repetition_factor(&self) -> usize {
+        self.repetition_factor
+    }
+
+    /// Returns the code applied to payloads this engine encrypts
+    pub fn coding_scheme(&self) -> CodingScheme {
+        self.coding_scheme
     }
 
     /// Generates a cryptographically secure random ChaCha20 key
@@ -41,144 +162,751 @@ impl CryptographicEngine {
         encryption_key
     }
 
-    /// Generates a random nonce for ChaCha20 encryption
-    fn generate_nonce(&self) -> [u8; NONCE_SIZE] {
+    /// Generates a random salt for deriving a key from a passphrase
+    pub fn generate_passphrase_salt() -> [u8; PASSPHRASE_SALT_SIZE] {
+        let mut passphrase_salt = [0u8; PASSPHRASE_SALT_SIZE];
+        rand::thread_rng().fill_bytes(&mut passphrase_salt);
+        passphrase_salt
+    }
+
+    /// Derives a ChaCha20 key from a passphrase and salt with Argon2id
+    ///
+    /// Uses the Argon2 crate's default cost parameters, so the same passphrase and salt always
+    /// give the same key. Fails if the salt is shorter than Argon2's 8-byte minimum.
+    pub fn derive_key_from_passphrase(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+    ) -> Result<[u8; ENCRYPTION_KEY_SIZE]> {
+        let mut encryption_key = [0u8; ENCRYPTION_KEY_SIZE];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut encryption_key)
+            .map_err(|error| {
+                SteganographyError::CryptoError(format!("Key derivation failed: {}", error))
+            })?;
+        Ok(encryption_key)
+    }
+
+    /// Compares keys, tags or checksums in constant time to avoid leaking where they differ
+    ///
+    /// Slices of different lengths compare unequal; only the length is revealed by timing.
+    pub fn constant_time_equals(left: &[u8], right: &[u8]) -> bool {
+        left.ct_eq(right).into()
+    }
+
+    /// Appends the big-endian CRC32 of `plaintext_data`
+    pub fn append_crc(plaintext_data: &[u8]) -> Vec<u8> {
+        let mut checksummed_data = plaintext_data.to_vec();
+        checksummed_data.extend_from_slice(&crc32fast::hash(plaintext_data).to_be_bytes());
+        checksummed_data
+    }
+
+    /// Checks the CRC32 written by `append_crc` and returns the data in front of it
+    pub fn verify_crc(checksummed_data: &[u8]) -> Result<&[u8]> {
+        let integrity_error =
+            || SteganographyError::CryptoError("integrity check failed".to_string());
+        let checksum_offset = checksummed_data
+            .len()
+            .checked_sub(PLAINTEXT_CHECKSUM_SIZE)
+            .ok_or_else(integrity_error)?;
+        let (plaintext_data, checksum) = checksummed_data.split_at(checksum_offset);
+
+        if Self::constant_time_equals(checksum, &crc32fast::hash(plaintext_data).to_be_bytes()) {
+            Ok(plaintext_data)
+        } else {
+            Err(integrity_error())
+        }
+    }
+
+    /// Calculates the largest plaintext whose encrypted frame fits in `available_bytes`
+    ///
+    /// Accounts for the frame header, the nonce, the plaintext checksum and the engine's
+    /// coding scheme, assuming an
+    /// uncompressed, unauthenticated payload without recorded carrier dimensions or
+    /// passphrase salt.
+    pub fn calculate_max_plaintext_length(&self, available_bytes: usize) -> usize {
+        let header_length = FrameHeader::default().encoded_length();
+        let body_length = available_bytes.saturating_sub(header_length);
+        let payload_length = match self.coding_scheme {
+            CodingScheme::Repetition => body_length / self.repetition_factor,
+            CodingScheme::ReedSolomon => reed_solomon::max_data_length(body_length),
+        };
+        payload_length.saturating_sub(NONCE_SIZE + PLAINTEXT_CHECKSUM_SIZE)
+    }
+
+    /// Generates a nonce for ChaCha20 encryption, from the counter if the engine has one
+    fn generate_nonce(&self) -> Result<[u8; NONCE_SIZE]> {
         let mut nonce = [0u8; NONCE_SIZE];
-        rand::thread_rng().fill_bytes(&mut nonce);
-        nonce
+        match &self.nonce_counter {
+            Some(nonce_counter) => {
+                let counter_value = nonce_counter
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |counter_value| {
+                        counter_value.checked_add(1)
+                    })
+                    .map_err(|_| {
+                        SteganographyError::CryptoError(
+                            "Nonce counter exhausted; start a new key".to_string(),
+                        )
+                    })?;
+                nonce[NONCE_SIZE - 8..].copy_from_slice(&counter_value.to_be_bytes());
+            }
+            None => rand::thread_rng().fill_bytes(&mut nonce),
+        }
+        Ok(nonce)
     }
 
     /// Applies repetition coding to data for error correction
-    fn apply_repetition_encoding(&self, original_data: &[u8]) -> Result<Vec<u8>> {
-        let mut encoded_data = Vec::new();
+    fn apply_repetition_encoding(
+        &self,
+        original_data: &[u8],
+        frame_header: &FrameHeader,
+    ) -> Result<Vec<u8>> {
+        Payload {
+            header: FrameHeader {
+                payload_length: original_data.len(),
+                ..*frame_header
+            },
+            body: self.repeat_bytes(original_data),
+        }
+        .to_bytes()
+    }
+
+    /// Repeats each byte `repetition_factor` times for redundancy
+    fn repeat_bytes(&self, original_data: &[u8]) -> Vec<u8> {
+        original_data
+            .iter()
+            .flat_map(|&data_byte| std::iter::repeat_n(data_byte, self.repetition_factor))
+            .collect()
+    }
 
-        // Store original data length as 4-byte header
-        encoded_data.extend_from_slice(&(original_data.len() as u32).to_le_bytes());
+    /// Applies Reed-Solomon coding to data, 32 parity bytes per block of up to 223 bytes
+    fn apply_reed_solomon_encoding(
+        &self,
+        original_data: &[u8],
+        frame_header: &FrameHeader,
+    ) -> Result<Vec<u8>> {
+        Payload {
+            header: FrameHeader {
+                payload_length: original_data.len(),
+                coding_scheme: CodingScheme::ReedSolomon,
+                ..*frame_header
+            },
+            body: reed_solomon::encode(original_data),
+        }
+        .to_bytes()
+    }
 
-        // Repeat each byte multiple times for redundancy
-        for &data_byte in original_data {
-            for _ in 0..self.repetition_factor {
-                encoded_data.push(data_byte);
+    /// Corrects up to 16 corrupted bytes per block of Reed-Solomon coded data
+    fn apply_reed_solomon_decoding(&self, encoded_data: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload::from_bytes(encoded_data, self.repetition_factor)?;
+        reed_solomon::decode(&payload.body)
+    }
+
+    /// Decodes the frame body with the coding scheme recorded in its header, by hard
+    /// decisions
+    fn apply_error_correction_decoding(&self, encoded_data: &[u8]) -> Result<Vec<u8>> {
+        self.require_payload_header(encoded_data)?;
+        match self.read_frame_header(encoded_data)?.coding_scheme {
+            CodingScheme::Repetition => {
+                self.apply_repetition_decoding(encoded_data)
+                    .map_err(|error| {
+                        SteganographyError::CryptoError(format!(
+                            "Repetition decoding failed: {}",
+                            error
+                        ))
+                    })
+            }
+            CodingScheme::ReedSolomon => {
+                self.apply_reed_solomon_decoding(encoded_data)
+                    .map_err(|error| {
+                        SteganographyError::CryptoError(format!(
+                            "Reed-Solomon decoding failed: {}",
+                            error
+                        ))
+                    })
             }
         }
+    }
+
+    /// Fails with "no valid payload header found" unless the header claims a payload that fits
+    ///
+    /// An image without hidden data usually reads back as an all-zero header claiming an
+    /// empty payload. Checking before decoding reports that plainly instead of as a decoding
+    /// or decryption failure.
+    fn require_payload_header(&self, encoded_data: &[u8]) -> Result<()> {
+        self.calculate_encoded_frame_length(encoded_data).map(drop)
+    }
+
+    /// Reads the payload length, flags and carrier dimensions from the frame header
+    pub fn read_frame_header(&self, encoded_data: &[u8]) -> Result<FrameHeader> {
+        FrameHeader::from_bytes(encoded_data)
+    }
+
+    /// Recovers the salt a passphrase key was derived with, if the frame carries one
+    ///
+    /// The salt is decoded by hard decisions, ahead of decryption, since the key can't be
+    /// derived without it.
+    pub fn read_passphrase_salt(
+        &self,
+        error_corrected_data: &[u8],
+    ) -> Result<Option<[u8; PASSPHRASE_SALT_SIZE]>> {
+        if !self
+            .read_frame_header(error_corrected_data)?
+            .has_passphrase_salt
+        {
+            return Ok(None);
+        }
 
-        Ok(encoded_data)
+        let encrypted_data = self.apply_error_correction_decoding(error_corrected_data)?;
+        let passphrase_salt = encrypted_data
+            .first_chunk::<PASSPHRASE_SALT_SIZE>()
+            .ok_or_else(|| {
+                SteganographyError::CryptoError(
+                    "Encrypted data too short to contain passphrase salt".to_string(),
+                )
+            })?;
+        Ok(Some(*passphrase_salt))
+    }
+
+    /// Calculates the length of the error-corrected frame from its length header
+    ///
+    /// This header is the single authoritative length; anything past the frame is padding.
+    pub fn calculate_encoded_frame_length(&self, encoded_data: &[u8]) -> Result<usize> {
+        Payload::encoded_frame_length(encoded_data, self.repetition_factor)
     }
 
     /// Decodes repetition-encoded data using majority voting
     fn apply_repetition_decoding(&self, encoded_data: &[u8]) -> Result<Vec<u8>> {
-        if encoded_data.len() < 4 {
-            return Err(SteganographyError::InvalidInput(
-                "Encoded data too short for length header".to_string(),
-            ));
-        }
+        let payload = Payload::from_bytes(encoded_data, self.repetition_factor)?;
+
+        // Decode each byte using majority voting for error correction
+        let decoded_data = payload
+            .body
+            .chunks_exact(self.repetition_factor)
+            .map(|repeated_bytes| {
+                // Count votes for each possible byte value
+                let mut vote_counts = [0u32; 256];
+                for &byte_value in repeated_bytes {
+                    vote_counts[byte_value as usize] += 1;
+                }
+
+                // Select the byte value with the most votes
+                let (winning_byte_value, _) = vote_counts
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, &vote_count)| vote_count)
+                    .unwrap();
+                winning_byte_value as u8
+            })
+            .collect();
+
+        Ok(decoded_data)
+    }
 
-        // Extract original data length from header
-        let original_data_length = u32::from_le_bytes([
-            encoded_data[0],
-            encoded_data[1],
-            encoded_data[2],
-            encoded_data[3],
-        ]) as usize;
+    /// Decodes repetition-encoded data with soft decisions weighted by per-bit reliability
+    ///
+    /// Each bit position of a byte is decided by summing its repetitions, weighted by how
+    /// confidently the stego layer read them, so a few confident reads outvote many shaky ones.
+    fn apply_soft_repetition_decoding(
+        &self,
+        encoded_data: &[u8],
+        bit_confidences: &[f32],
+    ) -> Result<Vec<u8>> {
+        let expected_encoded_length = self.calculate_encoded_frame_length(encoded_data)?;
+        let Payload {
+            header: frame_header,
+            body: repeated_body,
+        } = Payload::from_bytes(encoded_data, self.repetition_factor)?;
 
-        let expected_encoded_length = 4 + (original_data_length * self.repetition_factor);
-        if encoded_data.len() != expected_encoded_length {
+        if bit_confidences.len() < expected_encoded_length * 8 {
             return Err(SteganographyError::InvalidInput(format!(
-                "Invalid encoded data length: expected {}, got {}",
-                expected_encoded_length,
-                encoded_data.len()
+                "Insufficient bit reliabilities: expected {}, got {}",
+                expected_encoded_length * 8,
+                bit_confidences.len()
             )));
         }
 
-        let mut decoded_data = Vec::new();
-        let data_start_index = 4;
+        // Confidences cover the whole frame, so skip those belonging to the header
+        let body_confidences = &bit_confidences[frame_header.encoded_length() * 8..];
+        Ok(self.soft_decode_repetitions(
+            &repeated_body,
+            body_confidences,
+            frame_header.payload_length,
+        ))
+    }
 
-        // Decode each byte using majority voting for error correction
-        for byte_index in 0..original_data_length {
-            let repetition_start = data_start_index + (byte_index * self.repetition_factor);
-            let repetition_end = repetition_start + self.repetition_factor;
-
-            if repetition_end > encoded_data.len() {
-                return Err(SteganographyError::InvalidInput(format!(
-                    "Insufficient repetition data for byte {}",
-                    byte_index
-                )));
-            }
+    /// Soft-decodes the first `byte_count` bytes of a repetition-coded body
+    ///
+    /// `body_confidences` holds one reliability per bit of `repeated_body`.
+    fn soft_decode_repetitions(
+        &self,
+        repeated_body: &[u8],
+        body_confidences: &[f32],
+        byte_count: usize,
+    ) -> Vec<u8> {
+        let mut decoded_data = Vec::with_capacity(byte_count);
 
-            let repeated_bytes = &encoded_data[repetition_start..repetition_end];
+        for byte_index in 0..byte_count {
+            let repetition_start = byte_index * self.repetition_factor;
+            let mut decoded_byte = 0u8;
 
-            // Count votes for each possible byte value
-            let mut vote_counts = [0u32; 256];
-            for &byte_value in repeated_bytes {
-                vote_counts[byte_value as usize] += 1;
-            }
+            for bit_position in (0..8).rev() {
+                let bit_offset = 7 - bit_position;
+                let weighted_vote: f32 = (repetition_start
+                    ..repetition_start + self.repetition_factor)
+                    .map(|encoded_index| {
+                        let bit_weight = body_confidences[encoded_index * 8 + bit_offset]
+                            .max(MINIMUM_BIT_WEIGHT);
+                        if (repeated_body[encoded_index] >> bit_position) & 1 == 1 {
+                            bit_weight
+                        } else {
+                            -bit_weight
+                        }
+                    })
+                    .sum();
 
-            // Select the byte value with the most votes
-            let (winning_byte_value, _) = vote_counts
-                .iter()
-                .enumerate()
-                .max_by_key(|(_, &vote_count)| vote_count)
-                .unwrap();
+                decoded_byte = (decoded_byte << 1) | u8::from(weighted_vote > 0.0);
+            }
 
-            decoded_data.push(winning_byte_value as u8);
+            decoded_data.push(decoded_byte);
         }
 
-        Ok(decoded_data)
+        decoded_data
+    }
+
+    /// Encrypts data in a sentinel frame, which has no header for bit errors to corrupt
+    ///
+    /// The start marker, the nonce, the ciphertext of the plaintext and its CRC32, and the
+    /// end-of-message sentinel are repetition coded one after another. Without a header
+    /// nothing records options, so the payload is always uncompressed, unauthenticated text
+    /// and extraction must use the same repetition factor.
+    pub fn encrypt_with_sentinel_framing(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        plaintext_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let nonce = self.generate_nonce()?;
+        let mut ciphertext_data = Self::append_crc(plaintext_data);
+        ChaCha20::new(encryption_key.into(), &nonce.into()).apply_keystream(&mut ciphertext_data);
+
+        let mut framed_data = SENTINEL_START_MARKER.to_vec();
+        framed_data.extend_from_slice(&nonce);
+        framed_data.extend_from_slice(&ciphertext_data);
+        framed_data.extend_from_slice(&SENTINEL_END_MARKER);
+        let error_corrected_data = self.repeat_bytes(&framed_data);
+
+        diagnostic!(
+            "Encryption: {} bytes -> {} bytes in a sentinel frame with {}x repetition",
+            plaintext_data.len(),
+            error_corrected_data.len(),
+            self.repetition_factor
+        );
+
+        Ok(error_corrected_data)
+    }
+
+    /// Size of the repetition-coded sentinel frame around `plaintext_length` bytes
+    pub fn sentinel_frame_length(&self, plaintext_length: usize) -> usize {
+        let framed_length = SENTINEL_START_MARKER.len()
+            + NONCE_SIZE
+            + plaintext_length
+            + PLAINTEXT_CHECKSUM_SIZE
+            + SENTINEL_END_MARKER.len();
+        framed_length * self.repetition_factor
+    }
+
+    /// Decrypts a frame from `encrypt_with_sentinel_framing` read from the whole carrier
+    ///
+    /// Every whole group of repetitions is soft-decoded. The stream must open with the start
+    /// marker, and the payload ends at the first end-of-message sentinel whose preceding data
+    /// decrypts with a valid checksum, so the sentinel pattern turning up by chance in the
+    /// ciphertext is skipped.
+    pub fn decrypt_sentinel_frame(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        error_corrected_data: &[u8],
+        bit_confidences: &[f32],
+    ) -> Result<Vec<u8>> {
+        let byte_count =
+            error_corrected_data.len().min(bit_confidences.len() / 8) / self.repetition_factor;
+        let decoded_data =
+            self.soft_decode_repetitions(error_corrected_data, bit_confidences, byte_count);
+
+        let framed_data = decoded_data
+            .strip_prefix(&SENTINEL_START_MARKER)
+            .ok_or_else(|| {
+                SteganographyError::CryptoError(
+                    "no sentinel frame found: the start marker is missing".to_string(),
+                )
+            })?;
+
+        framed_data
+            .windows(SENTINEL_END_MARKER.len())
+            .enumerate()
+            .skip(NONCE_SIZE + PLAINTEXT_CHECKSUM_SIZE)
+            .filter(|(_, candidate_marker)| *candidate_marker == SENTINEL_END_MARKER)
+            .find_map(|(sentinel_offset, _)| {
+                let (nonce, ciphertext_data) = framed_data[..sentinel_offset].split_at(NONCE_SIZE);
+                let mut plaintext_data = ciphertext_data.to_vec();
+                ChaCha20::new(encryption_key.into(), Nonce::from_slice(nonce))
+                    .apply_keystream(&mut plaintext_data);
+                let plaintext_length = Self::verify_crc(&plaintext_data).ok()?.len();
+                plaintext_data.truncate(plaintext_length);
+                Some(plaintext_data)
+            })
+            .ok_or_else(|| {
+                SteganographyError::CryptoError(
+                    "no end-of-message sentinel with a valid checksum: wrong key, or the image is too damaged to recover"
+                        .to_string(),
+                )
+            })
     }
 
-    /// Encrypts data using ChaCha20 and applies repetition coding for error correction
+    /// Encrypts data using ChaCha20 and applies the engine's error correction coding
     pub fn encrypt_with_error_correction(
         &self,
         encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
         plaintext_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_frame_options(encryption_key, plaintext_data, &FrameOptions::default())
+    }
+
+    /// Deflates data before encryption, falling back to raw data if compression doesn't help
+    pub fn encrypt_with_compression(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        plaintext_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_frame_options(
+            encryption_key,
+            plaintext_data,
+            &FrameOptions {
+                compress: true,
+                ..FrameOptions::default()
+            },
+        )
+    }
+
+    /// Encrypts data with ChaCha20-Poly1305 so decryption detects a wrong key or damaged data
+    ///
+    /// The 16-byte tag is appended to the ciphertext before repetition coding.
+    pub fn encrypt_authenticated(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        plaintext_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_frame_options(
+            encryption_key,
+            plaintext_data,
+            &FrameOptions {
+                authenticated: true,
+                ..FrameOptions::default()
+            },
+        )
+    }
+
+    /// Encrypts data with the compression, carrier dimensions, content type, block visitation
+    /// order and passphrase salt given in `frame_options`
+    pub fn encrypt_with_frame_options(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        plaintext_data: &[u8],
+        frame_options: &FrameOptions,
+    ) -> Result<Vec<u8>> {
+        let frame_header = FrameHeader {
+            content_type: frame_options.content_type,
+            block_visitation: frame_options.block_visitation,
+            original_dimensions: frame_options.original_dimensions,
+            is_authenticated: frame_options.authenticated,
+            has_plaintext_checksum: true,
+            configuration_fingerprint: frame_options.configuration_fingerprint,
+            ..FrameHeader::default()
+        };
+        let passphrase_salt = frame_options.passphrase_salt.as_ref();
+
+        if !frame_options.compress {
+            return self.encrypt_frame(
+                encryption_key,
+                plaintext_data,
+                frame_header,
+                passphrase_salt,
+            );
+        }
+
+        let compressed_data = compress_payload(plaintext_data)?;
+
+        if compressed_data.len() < plaintext_data.len() {
+            diagnostic!(
+                "Compression: {} bytes -> {} bytes",
+                plaintext_data.len(),
+                compressed_data.len()
+            );
+            self.encrypt_frame(
+                encryption_key,
+                &compressed_data,
+                FrameHeader {
+                    is_compressed: true,
+                    ..frame_header
+                },
+                passphrase_salt,
+            )
+        } else {
+            diagnostic!("Compression skipped: payload does not shrink");
+            self.encrypt_frame(
+                encryption_key,
+                plaintext_data,
+                frame_header,
+                passphrase_salt,
+            )
+        }
+    }
+
+    /// Encrypts a payload and frames it with the engine's coding scheme and the given header
+    /// flags
+    ///
+    /// A passphrase salt goes ahead of the nonce, so it gets the same error correction, and
+    /// an authentication tag follows the ciphertext. If the header asks for a checksum, the
+    /// plaintext's CRC32 is encrypted along with it.
+    fn encrypt_frame(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        plaintext_data: &[u8],
+        frame_header: FrameHeader,
+        passphrase_salt: Option<&[u8; PASSPHRASE_SALT_SIZE]>,
     ) -> Result<Vec<u8>> {
         // Generate a random nonce for this encryption
-        let nonce = self.generate_nonce();
+        let nonce = self.generate_nonce()?;
 
-        // Create ChaCha20 stream cipher
-        let mut cipher = ChaCha20::new(encryption_key.into(), &nonce.into());
+        let checksummed_data;
+        let plaintext_data = if frame_header.has_plaintext_checksum {
+            checksummed_data = Self::append_crc(plaintext_data);
+            &checksummed_data
+        } else {
+            plaintext_data
+        };
 
-        // Encrypt the plaintext data
-        let mut ciphertext_data = plaintext_data.to_vec();
-        cipher.apply_keystream(&mut ciphertext_data);
+        // Encrypt the plaintext data, appending a Poly1305 tag if authenticated
+        let ciphertext_data = if frame_header.is_authenticated {
+            ChaCha20Poly1305::new(encryption_key.into())
+                .encrypt(&nonce.into(), plaintext_data)
+                .map_err(|_| SteganographyError::CryptoError("encryption failed".to_string()))?
+        } else {
+            let mut cipher = ChaCha20::new(encryption_key.into(), &nonce.into());
+            let mut ciphertext_data = plaintext_data.to_vec();
+            cipher.apply_keystream(&mut ciphertext_data);
+            ciphertext_data
+        };
 
-        // Prepend nonce to ciphertext for decryption
-        let mut encrypted_data = nonce.to_vec();
+        // Prepend the salt, if any, and nonce to ciphertext for decryption
+        let mut encrypted_data = passphrase_salt
+            .map(|salt| salt.to_vec())
+            .unwrap_or_default();
+        encrypted_data.extend_from_slice(&nonce);
         encrypted_data.extend_from_slice(&ciphertext_data);
 
-        // Apply repetition coding for error correction
-        let error_corrected_data = self.apply_repetition_encoding(&encrypted_data)?;
+        // Apply the configured error correction coding
+        let frame_header = FrameHeader {
+            payload_length: encrypted_data.len(),
+            has_passphrase_salt: passphrase_salt.is_some(),
+            ..frame_header
+        };
+        let (error_corrected_data, coding_description) = match self.coding_scheme {
+            CodingScheme::Repetition => (
+                self.apply_repetition_encoding(&encrypted_data, &frame_header)?,
+                format!("{}x repetition", self.repetition_factor),
+            ),
+            CodingScheme::ReedSolomon => (
+                self.apply_reed_solomon_encoding(&encrypted_data, &frame_header)?,
+                format!(
+                    "Reed-Solomon coding ({} parity bytes per block)",
+                    reed_solomon::PARITY_BYTES
+                ),
+            ),
+        };
 
-        println!(
-            "Encryption: {} bytes -> {} bytes with {}x repetition ({:.1}% overhead)",
+        diagnostic!(
+            "Encryption: {} bytes -> {} bytes with {} ({:.1}% overhead)",
             encrypted_data.len(),
             error_corrected_data.len(),
-            self.repetition_factor,
+            coding_description,
             (error_corrected_data.len() as f64 / encrypted_data.len() as f64 - 1.0) * 100.0
         );
 
         Ok(error_corrected_data)
     }
 
-    /// Decrypts data by first applying repetition decoding then ChaCha20 decryption
+    /// Decrypts data by applying error correction decoding, ChaCha20 decryption and, if
+    /// flagged, decompression
     pub fn decrypt_with_error_correction(
         &self,
         encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
         error_corrected_data: &[u8],
     ) -> Result<Vec<u8>> {
-        // First, apply repetition decoding to correct bit errors
-        let encrypted_data = self
-            .apply_repetition_decoding(error_corrected_data)
+        // First, decode with the recorded scheme to correct bit errors
+        let encrypted_data = self.apply_error_correction_decoding(error_corrected_data)?;
+
+        self.decrypt_decoded_frame(encryption_key, &encrypted_data, error_corrected_data)
+    }
+
+    /// Decrypts a frame from `encrypt_authenticated`, failing unless its tag verifies
+    ///
+    /// The other decryption methods verify tagged frames too, but also accept untagged ones;
+    /// this one rejects them.
+    pub fn decrypt_authenticated(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        error_corrected_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.require_authenticated_frame(error_corrected_data)?;
+        self.decrypt_with_error_correction(encryption_key, error_corrected_data)
+    }
+
+    /// Fails unless the frame header says the payload carries an authentication tag
+    pub fn require_authenticated_frame(&self, error_corrected_data: &[u8]) -> Result<()> {
+        if self
+            .read_frame_header(error_corrected_data)?
+            .is_authenticated
+        {
+            Ok(())
+        } else {
+            Err(SteganographyError::CryptoError(
+                "payload is not authenticated; it was hidden without --authenticated".to_string(),
+            ))
+        }
+    }
+
+    /// Fails if the frame records a configuration fingerprint other than `expected_fingerprint`
+    ///
+    /// Parameters that don't match the embedding read the whole header as garbage, so unless
+    /// the data is a well-formed frame from before fingerprints were recorded, the fingerprint
+    /// field is checked without trusting any other field. Frames that recorded none pass.
+    pub fn require_configuration_fingerprint(
+        &self,
+        error_corrected_data: &[u8],
+        expected_fingerprint: u16,
+    ) -> Result<()> {
+        let predates_fingerprints = self
+            .read_frame_header(error_corrected_data)
+            .is_ok_and(|frame_header| !frame_header.has_configuration_fingerprint_field())
+            && self
+                .calculate_encoded_frame_length(error_corrected_data)
+                .is_ok();
+        if predates_fingerprints {
+            return Ok(());
+        }
+
+        match FrameHeader::read_configuration_fingerprint(error_corrected_data) {
+            Some(recorded_fingerprint) if recorded_fingerprint != expected_fingerprint => {
+                Err(SteganographyError::InvalidInput(
+                    "configuration mismatch: image was embedded with different parameters"
+                        .to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Decrypts data using soft-decision repetition decoding driven by per-bit reliability
+    ///
+    /// Reed-Solomon frames are decoded by hard decisions, ignoring the reliabilities.
+    pub fn decrypt_with_bit_reliability(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        error_corrected_data: &[u8],
+        bit_confidences: &[f32],
+    ) -> Result<Vec<u8>> {
+        let encrypted_data =
+            self.decode_with_bit_reliability(error_corrected_data, bit_confidences)?;
+
+        self.decrypt_decoded_frame(encryption_key, &encrypted_data, error_corrected_data)
+    }
+
+    /// Checks that a frame opens under `encryption_key` without returning its payload
+    ///
+    /// The frame is decoded as `decrypt_with_bit_reliability` does, and the key is accepted
+    /// once the frame's authentication tag or plaintext CRC32 verifies. Frames carrying
+    /// neither are rejected, since any key decrypts them to plausible bytes.
+    pub fn verify_key_with_bit_reliability(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        error_corrected_data: &[u8],
+        bit_confidences: &[f32],
+    ) -> Result<()> {
+        let encrypted_data =
+            self.decode_with_bit_reliability(error_corrected_data, bit_confidences)?;
+        let frame_header = self.read_frame_header(error_corrected_data)?;
+        if !frame_header.is_authenticated && !frame_header.has_plaintext_checksum {
+            return Err(SteganographyError::CryptoError(
+                "payload has neither an authentication tag nor a checksum, so no key can be verified against it".to_string(),
+            ));
+        }
+
+        self.open_decoded_frame(encryption_key, &encrypted_data, error_corrected_data)?;
+        Ok(())
+    }
+
+    /// Undoes the frame's error correction, soft-decoding repetition frames by bit reliability
+    ///
+    /// Reed-Solomon frames are decoded by hard decisions, ignoring the reliabilities.
+    fn decode_with_bit_reliability(
+        &self,
+        error_corrected_data: &[u8],
+        bit_confidences: &[f32],
+    ) -> Result<Vec<u8>> {
+        self.require_payload_header(error_corrected_data)?;
+        if self.read_frame_header(error_corrected_data)?.coding_scheme != CodingScheme::Repetition {
+            return self.apply_error_correction_decoding(error_corrected_data);
+        }
+
+        self.apply_soft_repetition_decoding(error_corrected_data, bit_confidences)
             .map_err(|error| {
                 SteganographyError::CryptoError(format!("Repetition decoding failed: {}", error))
-            })?;
+            })
+    }
+
+    /// Decrypts an error-corrected frame, verifying its tag and decompressing it if the
+    /// header says so
+    fn decrypt_decoded_frame(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        encrypted_data: &[u8],
+        error_corrected_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let plaintext_data =
+            self.open_decoded_frame(encryption_key, encrypted_data, error_corrected_data)?;
 
-        println!(
+        if self.read_frame_header(error_corrected_data)?.is_compressed {
+            return decompress_payload(&plaintext_data);
+        }
+
+        Ok(plaintext_data)
+    }
+
+    /// Decrypts an error-corrected frame, verifying its tag and plaintext checksum, and
+    /// returns the plaintext still compressed if it was
+    fn open_decoded_frame(
+        &self,
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        encrypted_data: &[u8],
+        error_corrected_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        diagnostic!(
             "Error correction: Recovered {} bytes from {} bytes",
             encrypted_data.len(),
             error_corrected_data.len()
         );
 
+        let frame_header = self.read_frame_header(error_corrected_data)?;
+        let encrypted_data = if frame_header.has_passphrase_salt {
+            encrypted_data.get(PASSPHRASE_SALT_SIZE..).ok_or_else(|| {
+                SteganographyError::CryptoError(
+                    "Encrypted data too short to contain passphrase salt".to_string(),
+                )
+            })?
+        } else {
+            encrypted_data
+        };
+
         if encrypted_data.len() < NONCE_SIZE {
             return Err(SteganographyError::CryptoError(
                 "Encrypted data too short to contain nonce".to_string(),
@@ -189,28 +917,56 @@ impl CryptographicEngine {
         let nonce = &encrypted_data[..NONCE_SIZE];
         let ciphertext_data = &encrypted_data[NONCE_SIZE..];
 
-        // Create ChaCha20 cipher with the same key and extracted nonce
-        let mut cipher = ChaCha20::new(encryption_key.into(), nonce.try_into().unwrap());
+        let mut plaintext_data = if frame_header.is_authenticated {
+            if ciphertext_data.len() < AUTHENTICATION_TAG_SIZE {
+                return Err(SteganographyError::CryptoError(
+                    "Encrypted data too short to contain authentication tag".to_string(),
+                ));
+            }
+            ChaCha20Poly1305::new(encryption_key.into())
+                .decrypt(Nonce::from_slice(nonce), ciphertext_data)
+                .map_err(|_| {
+                    SteganographyError::CryptoError(
+                        "authentication failed: wrong key, or the image is too damaged to recover"
+                            .to_string(),
+                    )
+                })?
+        } else {
+            // Create ChaCha20 cipher with the same key and extracted nonce
+            let mut cipher = ChaCha20::new(encryption_key.into(), Nonce::from_slice(nonce));
+
+            // Decrypt by applying the same keystream
+            let mut plaintext_data = ciphertext_data.to_vec();
+            cipher.apply_keystream(&mut plaintext_data);
+            plaintext_data
+        };
 
-        // Decrypt by applying the same keystream
-        let mut plaintext_data = ciphertext_data.to_vec();
-        cipher.apply_keystream(&mut plaintext_data);
+        if frame_header.has_plaintext_checksum {
+            let plaintext_length = Self::verify_crc(&plaintext_data)?.len();
+            plaintext_data.truncate(plaintext_length);
+        }
 
         Ok(plaintext_data)
     }
 
-    /// Saves encryption key to file in base64 format
+    /// Saves encryption key to file in the versioned key file format
+    ///
+    /// `key_encoding` only changes how the key line is written; loading accepts either.
     pub fn save_key_to_file(
         &self,
         encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
         file_path: &str,
+        key_encoding: KeyEncoding,
     ) -> Result<()> {
-        let base64_encoded_key = general_purpose::STANDARD.encode(encryption_key);
-        fs::write(file_path, base64_encoded_key)?;
+        fs::write(
+            file_path,
+            Self::format_key_file(encryption_key, key_encoding),
+        )?;
         Ok(())
     }
 
-    /// Loads encryption key from file or parses from base64 string
+    /// Loads encryption key from file or parses from key file contents or a base64 or hex
+    /// string
     pub fn load_key_from_input(&self, key_input: &str) -> Result<[u8; ENCRYPTION_KEY_SIZE]> {
         let key_data = if Path::new(key_input).exists() {
             fs::read_to_string(key_input)?
@@ -218,8 +974,116 @@ impl CryptographicEngine {
             key_input.to_string()
         };
 
+        if key_data.trim_start().starts_with(KEY_FILE_MAGIC) {
+            Self::parse_key_file(&key_data)
+        } else {
+            // Bare keys predate the framed format and remain accepted
+            Self::decode_key_text(key_data.trim())
+        }
+    }
+
+    /// Renders a key as a framed key file with magic line, version and CRC32 checksum
+    fn format_key_file(
+        encryption_key: &[u8; ENCRYPTION_KEY_SIZE],
+        key_encoding: KeyEncoding,
+    ) -> String {
+        format!(
+            "{}\nversion: {}\nkey: {}\ncrc32: {:08x}\n",
+            KEY_FILE_MAGIC,
+            KEY_FILE_VERSION,
+            key_encoding.encode(encryption_key),
+            crc32fast::hash(encryption_key)
+        )
+    }
+
+    /// Parses a framed key file, rejecting unknown versions and corrupted keys
+    fn parse_key_file(key_file_contents: &str) -> Result<[u8; ENCRYPTION_KEY_SIZE]> {
+        let mut lines = key_file_contents.lines().map(str::trim);
+
+        if lines.next() != Some(KEY_FILE_MAGIC) {
+            return Err(SteganographyError::InvalidInput(
+                "Key file is missing the STEG-KEY header".to_string(),
+            ));
+        }
+
+        let version_field = Self::read_key_file_field(lines.next(), "version")?;
+        let version = version_field.parse::<u32>().map_err(|_| {
+            SteganographyError::InvalidInput(format!("Invalid key file version: {}", version_field))
+        })?;
+        if version != KEY_FILE_VERSION {
+            return Err(SteganographyError::InvalidInput(format!(
+                "Unsupported key file version {} (this build reads version {})",
+                version, KEY_FILE_VERSION
+            )));
+        }
+
+        let encryption_key =
+            Self::decode_key_text(Self::read_key_file_field(lines.next(), "key")?)?;
+
+        let checksum_field = Self::read_key_file_field(lines.next(), "crc32")?;
+        let stored_checksum = u32::from_str_radix(checksum_field, 16).map_err(|_| {
+            SteganographyError::InvalidInput(format!(
+                "Invalid key file checksum: {}",
+                checksum_field
+            ))
+        })?;
+        if !Self::constant_time_equals(
+            &crc32fast::hash(&encryption_key).to_be_bytes(),
+            &stored_checksum.to_be_bytes(),
+        ) {
+            return Err(SteganographyError::InvalidInput(
+                "Key file checksum mismatch: the key file is corrupted".to_string(),
+            ));
+        }
+
+        Ok(encryption_key)
+    }
+
+    /// Reads a `name: value` line from a key file, reporting truncation clearly
+    fn read_key_file_field<'a>(line: Option<&'a str>, field_name: &str) -> Result<&'a str> {
+        line.and_then(|line| line.strip_prefix(field_name))
+            .and_then(|remainder| remainder.strip_prefix(':'))
+            .map(str::trim)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "Key file is truncated or malformed: missing '{}' field",
+                    field_name
+                ))
+            })
+    }
+
+    /// Decodes a key written in either encoding
+    ///
+    /// Exactly 64 hex digits is read as hex. Base64 of a 32-byte key is 44 characters, and
+    /// 64 characters of base64 would decode to the wrong length, so the two never overlap.
+    fn decode_key_text(key_text: &str) -> Result<[u8; ENCRYPTION_KEY_SIZE]> {
+        if key_text.len() == ENCRYPTION_KEY_SIZE * 2
+            && key_text
+                .bytes()
+                .all(|key_char| key_char.is_ascii_hexdigit())
+        {
+            Self::decode_hex_key(key_text)
+        } else {
+            Self::decode_base64_key(key_text)
+        }
+    }
+
+    /// Decodes a key written as 64 hex digits
+    fn decode_hex_key(hex_key: &str) -> Result<[u8; ENCRYPTION_KEY_SIZE]> {
+        let mut encryption_key = [0u8; ENCRYPTION_KEY_SIZE];
+        for (key_byte, hex_pair) in encryption_key.iter_mut().zip(hex_key.as_bytes().chunks(2)) {
+            *key_byte = std::str::from_utf8(hex_pair)
+                .ok()
+                .and_then(|hex_pair| u8::from_str_radix(hex_pair, 16).ok())
+                .ok_or_else(|| SteganographyError::InvalidInput("Invalid hex key".to_string()))?;
+        }
+        Ok(encryption_key)
+    }
+
+    /// Decodes a base64 key and checks that it has the expected length
+    fn decode_base64_key(base64_key: &str) -> Result<[u8; ENCRYPTION_KEY_SIZE]> {
         let key_bytes = general_purpose::STANDARD
-            .decode(key_data.trim())
+            .decode(base64_key)
             .map_err(|error| SteganographyError::Base64Error(error.to_string()))?;
 
         if key_bytes.len() != ENCRYPTION_KEY_SIZE {
@@ -245,6 +1109,8 @@ impl Default for CryptographicEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::steganography::SteganographyEngine;
+    use image::{ImageBuffer, Rgb, RgbImage};
 
     #[test]
     fn test_encryption_decryption_roundtrip() {
@@ -262,19 +1128,601 @@ mod tests {
         assert_eq!(test_data.to_vec(), decrypted_data);
     }
 
+    #[test]
+    fn test_deterministic_nonces_count_up_and_stop_at_the_end() {
+        let crypto_engine = CryptographicEngine::with_deterministic_nonce(41);
+        let mut first_nonce = [0u8; NONCE_SIZE];
+        first_nonce[NONCE_SIZE - 1] = 41;
+        let mut second_nonce = first_nonce;
+        second_nonce[NONCE_SIZE - 1] = 42;
+        assert_eq!(crypto_engine.generate_nonce().unwrap(), first_nonce);
+        assert_eq!(crypto_engine.generate_nonce().unwrap(), second_nonce);
+
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, b"counter nonce")
+            .unwrap();
+        let decrypted_data = CryptographicEngine::new()
+            .decrypt_with_error_correction(&encryption_key, &encrypted_data)
+            .unwrap();
+        assert_eq!(decrypted_data, b"counter nonce");
+
+        let exhausted_engine = CryptographicEngine::with_deterministic_nonce(u64::MAX);
+        assert!(matches!(
+            exhausted_engine.encrypt_with_error_correction(&encryption_key, b"one too many"),
+            Err(SteganographyError::CryptoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_plaintext_length_fills_available_bytes() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let available_bytes = 512;
+
+        let max_plaintext_length = crypto_engine.calculate_max_plaintext_length(available_bytes);
+        let encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, &vec![0u8; max_plaintext_length])
+            .unwrap();
+        assert!(encrypted_data.len() <= available_bytes);
+        assert!(encrypted_data.len() + 5 > available_bytes);
+
+        assert_eq!(crypto_engine.calculate_max_plaintext_length(10), 0);
+    }
+
+    #[test]
+    fn test_reed_solomon_payload_corrects_byte_errors_and_fits_more() {
+        let crypto_engine =
+            CryptographicEngine::new().with_coding_scheme(CodingScheme::ReedSolomon);
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let plaintext_data = b"Reed-Solomon protected message";
+
+        let encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, plaintext_data)
+            .unwrap();
+        let frame_header = crypto_engine.read_frame_header(&encrypted_data).unwrap();
+        assert_eq!(frame_header.coding_scheme, CodingScheme::ReedSolomon);
+        let repetition_data = CryptographicEngine::new()
+            .encrypt_with_error_correction(&encryption_key, plaintext_data)
+            .unwrap();
+        assert!(encrypted_data.len() < repetition_data.len() / 2);
+
+        // Sixteen corrupted body bytes are corrected, and the scheme comes from the header
+        let mut corrupted_data = encrypted_data.clone();
+        for byte_index in (frame_header.encoded_length()..corrupted_data.len()).step_by(4) {
+            if byte_index < frame_header.encoded_length() + 16 * 4 {
+                corrupted_data[byte_index] ^= 0xA5;
+            }
+        }
+        let bit_confidences = vec![1.0; corrupted_data.len() * 8];
+        assert_eq!(
+            CryptographicEngine::new()
+                .decrypt_with_bit_reliability(&encryption_key, &corrupted_data, &bit_confidences)
+                .unwrap(),
+            plaintext_data.to_vec()
+        );
+
+        // A corrupted byte more than the code can correct is reported, not miscorrected
+        let body_bytes = corrupted_data
+            .iter_mut()
+            .skip(frame_header.encoded_length());
+        for corrupted_byte in body_bytes.take(17) {
+            *corrupted_byte ^= 0x3C;
+        }
+        assert!(matches!(
+            crypto_engine.decrypt_with_error_correction(&encryption_key, &corrupted_data),
+            Err(SteganographyError::CryptoError(message)) if message.contains("Reed-Solomon")
+        ));
+
+        let max_plaintext_length = crypto_engine.calculate_max_plaintext_length(512);
+        let largest_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, &vec![0u8; max_plaintext_length])
+            .unwrap();
+        assert!(largest_data.len() <= 512);
+        assert!(
+            max_plaintext_length
+                > CryptographicEngine::new().calculate_max_plaintext_length(512) * 3
+        );
+    }
+
+    #[test]
+    fn test_compressed_payload_fits_where_raw_does_not() {
+        let crypto_engine = CryptographicEngine::new();
+        let stego_engine = SteganographyEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let cover_image: RgbImage =
+            ImageBuffer::from_fn(512, 512, |x, y| Rgb([(x / 2) as u8, (y / 2) as u8, 128]));
+        let test_message = "All work and no play makes a dull payload. ".repeat(120);
+        assert!(test_message.len() >= 5 * 1024);
+
+        let raw_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, test_message.as_bytes())
+            .unwrap();
+        assert!(matches!(
+            stego_engine.hide_data_in_rgb_image(&cover_image, &raw_data, 85),
+            Err(SteganographyError::CapacityError { .. })
+        ));
+
+        let compressed_data = crypto_engine
+            .encrypt_with_compression(&encryption_key, test_message.as_bytes())
+            .unwrap();
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&cover_image, &compressed_data, 85)
+            .unwrap();
+        let extracted_data = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, None)
+            .unwrap()
+            .data;
+        let decrypted_data = crypto_engine
+            .decrypt_with_error_correction(&encryption_key, &extracted_data)
+            .unwrap();
+
+        assert_eq!(test_message.as_bytes(), decrypted_data.as_slice());
+    }
+
+    #[test]
+    fn test_incompressible_payload_is_stored_raw() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let random_data = CryptographicEngine::generate_encryption_key();
+
+        let encrypted_data = crypto_engine
+            .encrypt_with_compression(&encryption_key, &random_data)
+            .unwrap();
+        let frame_header = crypto_engine.read_frame_header(&encrypted_data).unwrap();
+        let decrypted_data = crypto_engine
+            .decrypt_with_error_correction(&encryption_key, &encrypted_data)
+            .unwrap();
+
+        assert!(!frame_header.is_compressed);
+        assert_eq!(random_data.to_vec(), decrypted_data);
+    }
+
+    #[test]
+    fn test_carrier_dimensions_roundtrip_through_frame_header() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let test_data = b"padded carrier";
+
+        let encrypted_data = crypto_engine
+            .encrypt_with_frame_options(
+                &encryption_key,
+                test_data,
+                &FrameOptions {
+                    original_dimensions: Some((1023, 767)),
+                    content_type: ContentType::Binary,
+                    block_visitation: BlockVisitation::Zigzag,
+                    ..FrameOptions::default()
+                },
+            )
+            .unwrap();
+        let frame_header = crypto_engine.read_frame_header(&encrypted_data).unwrap();
+        let decrypted_data = crypto_engine
+            .decrypt_with_error_correction(&encryption_key, &encrypted_data)
+            .unwrap();
+
+        assert_eq!(frame_header.original_dimensions, Some((1023, 767)));
+        assert_eq!(frame_header.content_type, ContentType::Binary);
+        assert_eq!(frame_header.block_visitation, BlockVisitation::Zigzag);
+        assert_eq!(test_data.to_vec(), decrypted_data);
+    }
+
+    #[test]
+    fn test_passphrase_key_roundtrips_through_stored_salt() {
+        let crypto_engine = CryptographicEngine::new();
+        let passphrase_salt = CryptographicEngine::generate_passphrase_salt();
+        let encryption_key = crypto_engine
+            .derive_key_from_passphrase("correct horse battery staple", &passphrase_salt)
+            .unwrap();
+        let test_data = b"passphrase protected";
+
+        let encrypted_data = crypto_engine
+            .encrypt_with_frame_options(
+                &encryption_key,
+                test_data,
+                &FrameOptions {
+                    passphrase_salt: Some(passphrase_salt),
+                    ..FrameOptions::default()
+                },
+            )
+            .unwrap();
+        let stored_salt = crypto_engine
+            .read_passphrase_salt(&encrypted_data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored_salt, passphrase_salt);
+
+        let derived_key = crypto_engine
+            .derive_key_from_passphrase("correct horse battery staple", &stored_salt)
+            .unwrap();
+        assert_eq!(derived_key, encryption_key);
+        assert_eq!(
+            crypto_engine
+                .decrypt_with_error_correction(&derived_key, &encrypted_data)
+                .unwrap(),
+            test_data.to_vec()
+        );
+
+        // Another passphrase or salt gives another key, and key-file frames carry no salt
+        assert_ne!(
+            crypto_engine
+                .derive_key_from_passphrase("correct horse battery stapler", &stored_salt)
+                .unwrap(),
+            encryption_key
+        );
+        assert_ne!(
+            crypto_engine
+                .derive_key_from_passphrase("correct horse battery staple", &[0u8; 16])
+                .unwrap(),
+            encryption_key
+        );
+        assert!(crypto_engine
+            .derive_key_from_passphrase("correct horse battery staple", &[0u8; 4])
+            .is_err());
+        let unsalted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, test_data)
+            .unwrap();
+        assert_eq!(
+            crypto_engine.read_passphrase_salt(&unsalted_data).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sentinel_frame_is_found_without_a_length_header() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let test_data = b"no header needed";
+
+        let mut encrypted_data = crypto_engine
+            .encrypt_with_sentinel_framing(&encryption_key, test_data)
+            .unwrap();
+        assert_eq!(
+            encrypted_data.len(),
+            crypto_engine.sentinel_frame_length(test_data.len())
+        );
+
+        // Unused capacity follows the frame, and two of every five repetitions are damaged
+        let frame_length = encrypted_data.len();
+        encrypted_data.extend((0..203u32).map(|padding_index| (padding_index * 97) as u8));
+        for repetition_start in (0..frame_length).step_by(5) {
+            encrypted_data[repetition_start] ^= 0xFF;
+            encrypted_data[repetition_start + 3] ^= 0x5A;
+        }
+        let bit_confidences = vec![1.0; encrypted_data.len() * 8];
+        assert_eq!(
+            crypto_engine
+                .decrypt_sentinel_frame(&encryption_key, &encrypted_data, &bit_confidences)
+                .unwrap(),
+            test_data.to_vec()
+        );
+
+        let wrong_key = CryptographicEngine::generate_encryption_key();
+        assert!(matches!(
+            crypto_engine.decrypt_sentinel_frame(&wrong_key, &encrypted_data, &bit_confidences),
+            Err(SteganographyError::CryptoError(message)) if message.contains("valid checksum")
+        ));
+        let cover_data = vec![0x3C; 400];
+        assert!(matches!(
+            crypto_engine.decrypt_sentinel_frame(&encryption_key, &cover_data, &[1.0; 3200]),
+            Err(SteganographyError::CryptoError(message)) if message.contains("start marker")
+        ));
+    }
+
+    #[test]
+    fn test_plaintext_checksum_detects_corruption() {
+        let checksummed_data = CryptographicEngine::append_crc(b"checked message");
+        assert_eq!(checksummed_data.len(), 15 + PLAINTEXT_CHECKSUM_SIZE);
+        assert_eq!(
+            CryptographicEngine::verify_crc(&checksummed_data).unwrap(),
+            b"checked message"
+        );
+
+        let mut corrupted_data = checksummed_data.clone();
+        corrupted_data[3] ^= 0x01;
+        assert!(matches!(
+            CryptographicEngine::verify_crc(&corrupted_data),
+            Err(SteganographyError::CryptoError(message)) if message == "integrity check failed"
+        ));
+        assert!(CryptographicEngine::verify_crc(&checksummed_data[..3]).is_err());
+
+        // Every repetition of a ciphertext byte flipped gets past repetition decoding,
+        // but not the checksum
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let mut encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, b"checked message")
+            .unwrap();
+        let frame_header = crypto_engine.read_frame_header(&encrypted_data).unwrap();
+        assert!(frame_header.has_plaintext_checksum);
+        let ciphertext_start = frame_header.encoded_length() + NONCE_SIZE * 5;
+        encrypted_data[ciphertext_start..ciphertext_start + 5]
+            .iter_mut()
+            .for_each(|repeated_byte| *repeated_byte ^= 0x01);
+        assert!(matches!(
+            crypto_engine.decrypt_with_error_correction(&encryption_key, &encrypted_data),
+            Err(SteganographyError::CryptoError(message)) if message == "integrity check failed"
+        ));
+    }
+
+    #[test]
+    fn test_authenticated_payload_rejects_wrong_key_and_tampering() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let test_data = b"sealed message";
+
+        let encrypted_data = crypto_engine
+            .encrypt_authenticated(&encryption_key, test_data)
+            .unwrap();
+        let frame_header = crypto_engine.read_frame_header(&encrypted_data).unwrap();
+        assert!(frame_header.is_authenticated);
+        assert_eq!(
+            frame_header.payload_length,
+            NONCE_SIZE + test_data.len() + PLAINTEXT_CHECKSUM_SIZE + AUTHENTICATION_TAG_SIZE
+        );
+        assert_eq!(
+            crypto_engine
+                .decrypt_authenticated(&encryption_key, &encrypted_data)
+                .unwrap(),
+            test_data.to_vec()
+        );
+
+        let wrong_key = CryptographicEngine::generate_encryption_key();
+        assert!(matches!(
+            crypto_engine.decrypt_authenticated(&wrong_key, &encrypted_data),
+            Err(SteganographyError::CryptoError(message)) if message.starts_with("authentication failed")
+        ));
+
+        // Flipping every repetition of one ciphertext byte gets past the majority vote
+        let mut tampered_data = encrypted_data.clone();
+        let ciphertext_start = frame_header.encoded_length() + NONCE_SIZE * 5;
+        for tampered_byte in &mut tampered_data[ciphertext_start..ciphertext_start + 5] {
+            *tampered_byte ^= 0x01;
+        }
+        assert!(crypto_engine
+            .decrypt_with_error_correction(&encryption_key, &tampered_data)
+            .is_err());
+
+        let untagged_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, test_data)
+            .unwrap();
+        assert!(crypto_engine
+            .decrypt_authenticated(&encryption_key, &untagged_data)
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_verification_checks_tag_or_checksum() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let wrong_key = CryptographicEngine::generate_encryption_key();
+
+        for authenticated in [false, true] {
+            let encrypted_data = crypto_engine
+                .encrypt_with_frame_options(
+                    &encryption_key,
+                    b"key check message key check message",
+                    &FrameOptions {
+                        compress: true,
+                        authenticated,
+                        ..FrameOptions::default()
+                    },
+                )
+                .unwrap();
+            let bit_confidences = vec![1.0; encrypted_data.len() * 8];
+
+            assert!(crypto_engine
+                .verify_key_with_bit_reliability(&encryption_key, &encrypted_data, &bit_confidences)
+                .is_ok());
+            assert!(matches!(
+                crypto_engine.verify_key_with_bit_reliability(
+                    &wrong_key,
+                    &encrypted_data,
+                    &bit_confidences
+                ),
+                Err(SteganographyError::CryptoError(_))
+            ));
+        }
+
+        let unchecked_data = crypto_engine
+            .encrypt_frame(&encryption_key, b"unchecked", FrameHeader::default(), None)
+            .unwrap();
+        assert!(matches!(
+            crypto_engine.verify_key_with_bit_reliability(
+                &encryption_key,
+                &unchecked_data,
+                &vec![1.0; unchecked_data.len() * 8]
+            ),
+            Err(SteganographyError::CryptoError(message)) if message.contains("neither")
+        ));
+    }
+
+    #[test]
+    fn test_repetition_decoding_ignores_trailing_padding() {
+        let crypto_engine = CryptographicEngine::new();
+        let test_data = vec![0x10, 0x20, 0x30];
+
+        let mut framed_data = crypto_engine
+            .apply_repetition_encoding(&test_data, &FrameHeader::default())
+            .unwrap();
+        framed_data.extend_from_slice(&[0xAA; 16]);
+
+        let decoded_data = crypto_engine
+            .apply_repetition_decoding(&framed_data)
+            .unwrap();
+        assert_eq!(test_data, decoded_data);
+    }
+
+    #[test]
+    fn test_repetition_decoding_rejects_oversized_header() {
+        let crypto_engine = CryptographicEngine::new();
+        let mut framed_data = crypto_engine
+            .apply_repetition_encoding(&[0x01], &FrameHeader::default())
+            .unwrap();
+        framed_data[..4].copy_from_slice(&((1u32 << 28) - 1).to_le_bytes());
+
+        assert!(crypto_engine
+            .apply_repetition_decoding(&framed_data)
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_file_roundtrip() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+
+        let key_file_contents =
+            CryptographicEngine::format_key_file(&encryption_key, KeyEncoding::Base64);
+        let loaded_key = crypto_engine
+            .load_key_from_input(&key_file_contents)
+            .unwrap();
+
+        assert!(key_file_contents.starts_with("STEG-KEY\nversion: 1\n"));
+        assert_eq!(encryption_key, loaded_key);
+    }
+
+    #[test]
+    fn test_constant_time_equals() {
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let mut other_key = encryption_key;
+        other_key[ENCRYPTION_KEY_SIZE - 1] ^= 0x01;
+
+        assert!(CryptographicEngine::constant_time_equals(
+            &encryption_key,
+            &encryption_key
+        ));
+        assert!(!CryptographicEngine::constant_time_equals(
+            &encryption_key,
+            &other_key
+        ));
+        assert!(!CryptographicEngine::constant_time_equals(
+            &encryption_key,
+            &encryption_key[..16]
+        ));
+    }
+
+    #[test]
+    fn test_bare_base64_key_still_accepted() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let bare_key = general_purpose::STANDARD.encode(encryption_key);
+
+        let loaded_key = crypto_engine.load_key_from_input(&bare_key).unwrap();
+        assert_eq!(encryption_key, loaded_key);
+    }
+
+    #[test]
+    fn test_key_encodings_read_back_interchangeably() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        for key_encoding in [KeyEncoding::Base64, KeyEncoding::Hex] {
+            let key_path = temp_dir.path().join(format!("{:?}.key", key_encoding));
+            let key_path = key_path.to_str().unwrap();
+            crypto_engine
+                .save_key_to_file(&encryption_key, key_path, key_encoding)
+                .unwrap();
+
+            assert_eq!(
+                crypto_engine.load_key_from_input(key_path).unwrap(),
+                encryption_key
+            );
+        }
+
+        let base64_key = KeyEncoding::Base64.encode(&encryption_key);
+        let hex_key = KeyEncoding::Hex.encode(&encryption_key);
+        assert_eq!(hex_key.len(), 64);
+        assert_eq!(
+            crypto_engine.load_key_from_input(&base64_key).unwrap(),
+            crypto_engine
+                .load_key_from_input(&hex_key.to_uppercase())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_malformed_hex_key_rejected() {
+        let crypto_engine = CryptographicEngine::new();
+        let hex_key = KeyEncoding::Hex.encode(&CryptographicEngine::generate_encryption_key());
+
+        assert!(crypto_engine.load_key_from_input(&hex_key[..62]).is_err());
+        assert!(crypto_engine
+            .load_key_from_input(&format!("{}zz", &hex_key[..62]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_file_corruption_detected() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = [7u8; ENCRYPTION_KEY_SIZE];
+        let key_file_contents =
+            CryptographicEngine::format_key_file(&encryption_key, KeyEncoding::Base64);
+
+        // Flip a base64 character so the key still decodes but no longer matches its CRC
+        let corrupted_contents = key_file_contents.replacen("key: B", "key: C", 1);
+        assert!(crypto_engine
+            .load_key_from_input(&corrupted_contents)
+            .is_err());
+
+        let truncated_contents: String = key_file_contents
+            .lines()
+            .take(2)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(crypto_engine
+            .load_key_from_input(&truncated_contents)
+            .is_err());
+
+        let future_contents = key_file_contents.replace("version: 1", "version: 2");
+        assert!(crypto_engine.load_key_from_input(&future_contents).is_err());
+    }
+
+    #[test]
+    fn test_soft_decision_outvotes_unreliable_majority() {
+        let crypto_engine = CryptographicEngine::new();
+        let test_data = vec![0x42];
+
+        let mut encoded_data = crypto_engine
+            .apply_repetition_encoding(&test_data, &FrameHeader::default())
+            .unwrap();
+        let mut bit_confidences = vec![1.0f32; encoded_data.len() * 8];
+
+        // Three of five repetitions agree on a wrong value, but were read with low confidence
+        let header_length = FrameHeader::default().encoded_length();
+        for encoded_index in header_length..header_length + 3 {
+            encoded_data[encoded_index] = 0xBD;
+            bit_confidences[encoded_index * 8..(encoded_index + 1) * 8].fill(0.1);
+        }
+
+        let hard_decoded = crypto_engine
+            .apply_repetition_decoding(&encoded_data)
+            .unwrap();
+        let soft_decoded = crypto_engine
+            .apply_soft_repetition_decoding(&encoded_data, &bit_confidences)
+            .unwrap();
+
+        assert_eq!(hard_decoded, vec![0xBD]);
+        assert_eq!(soft_decoded, test_data);
+    }
+
     #[test]
     fn test_repetition_coding_with_errors() {
         let crypto_engine = CryptographicEngine::with_repetition_factor(3);
         let test_data = vec![0x42, 0x73, 0xA5];
 
-        let encoded_data = crypto_engine.apply_repetition_encoding(&test_data).unwrap();
-        
+        let encoded_data = crypto_engine
+            .apply_repetition_encoding(&test_data, &FrameHeader::default())
+            .unwrap();
+
         // Simulate bit errors by corrupting some bytes
         let mut corrupted_data = encoded_data;
-        corrupted_data[5] = 0xFF; // Corrupt one repetition
-        corrupted_data[8] = 0x00; // Corrupt another repetition
+        corrupted_data[6] = 0xFF; // Corrupt one repetition
+        corrupted_data[9] = 0x00; // Corrupt another repetition
 
-        let decoded_data = crypto_engine.apply_repetition_decoding(&corrupted_data).unwrap();
+        let decoded_data = crypto_engine
+            .apply_repetition_decoding(&corrupted_data)
+            .unwrap();
         assert_eq!(test_data, decoded_data);
     }
 }
@@ -1,37 +1,191 @@
 use crate::error::Result;
 
-/// Discrete Cosine Transform processor for 8x8 image blocks
-pub struct DctProcessor {
-    cosine_lookup_table: [[f32; 8]; 8],
+/// cos(pi/4), the rotation shared by the even and odd parts of the AAN butterflies
+const AAN_COS_4: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Discrete Cosine Transform processor for NxN image blocks, 8x8 unless stated otherwise
+///
+/// Coefficients use the JPEG FDCT scaling of `C(u)C(v)/4`, which is also what libjpeg's
+/// scaled AAN transform produces once its factor of 8 is folded into the quantizer, so the
+/// AC coefficients match what the codec quantizes. Samples aren't level-shifted by 128, which
+/// only moves the DC coefficient that embedding never writes.
+///
+/// Larger blocks such as 16x16 concentrate a bit's energy at lower spatial frequencies,
+/// which survive downscaling better. Only 8x8 blocks have the fast AAN transform.
+pub struct DctProcessor<const N: usize = 8> {
+    cosine_lookup_table: [[f32; N]; N],
+    /// Per-frequency factors mapping scaled AAN outputs to the direct transform's coefficients,
+    /// or `None` to evaluate the direct O(n²) sums
+    aan_scale_factors: Option<[f32; 8]>,
 }
 
 impl DctProcessor {
+    /// Creates a DCT processor using the fast Arai-Agui-Nakajima transform
+    pub fn new_fast() -> Self {
+        Self::new().with_fast_transform()
+    }
+
+    /// Switches to the Arai-Agui-Nakajima fast DCT
+    ///
+    /// Its 8-point butterflies need 5 multiplications per pass plus 8 for scaling, instead of
+    /// 64. Coefficients match the direct transform to within floating point rounding.
+    pub fn with_fast_transform(mut self) -> Self {
+        // The AAN transform leaves frequency k scaled by sqrt(2) cos(k pi / 16), on top of the
+        // 2 sqrt(2) separating its DC output from the direct transform's
+        self.aan_scale_factors = Some(std::array::from_fn(|frequency_index| {
+            let aan_scale = if frequency_index == 0 {
+                1.0
+            } else {
+                std::f32::consts::SQRT_2
+                    * (frequency_index as f32 * std::f32::consts::PI / 16.0).cos()
+            };
+            1.0 / (2.0 * std::f32::consts::SQRT_2 * aan_scale)
+        }));
+        self
+    }
+
+    /// Scaled 8-point forward DCT of Arai, Agui and Nakajima, as in libjpeg's `jfdctflt`
+    fn apply_aan_dct_1d(input_values: &[f32; 8]) -> [f32; 8] {
+        let sum_07 = input_values[0] + input_values[7];
+        let difference_07 = input_values[0] - input_values[7];
+        let sum_16 = input_values[1] + input_values[6];
+        let difference_16 = input_values[1] - input_values[6];
+        let sum_25 = input_values[2] + input_values[5];
+        let difference_25 = input_values[2] - input_values[5];
+        let sum_34 = input_values[3] + input_values[4];
+        let difference_34 = input_values[3] - input_values[4];
+
+        // Even part
+        let even_sum_outer = sum_07 + sum_34;
+        let even_difference_outer = sum_07 - sum_34;
+        let even_sum_inner = sum_16 + sum_25;
+        let even_difference_inner = sum_16 - sum_25;
+        let rotated_even = (even_difference_inner + even_difference_outer) * AAN_COS_4;
+
+        // Odd part
+        let odd_sum_low = difference_34 + difference_25;
+        let odd_sum_middle = difference_25 + difference_16;
+        let odd_sum_high = difference_16 + difference_07;
+        let rotation_shared = (odd_sum_low - odd_sum_high) * 0.382_683_43;
+        let rotated_low = 0.541_196_1 * odd_sum_low + rotation_shared;
+        let rotated_high = 1.306_563 * odd_sum_high + rotation_shared;
+        let rotated_middle = odd_sum_middle * AAN_COS_4;
+        let odd_outer_sum = difference_07 + rotated_middle;
+        let odd_outer_difference = difference_07 - rotated_middle;
+
+        [
+            even_sum_outer + even_sum_inner,
+            odd_outer_sum + rotated_high,
+            even_difference_outer + rotated_even,
+            odd_outer_difference - rotated_low,
+            even_sum_outer - even_sum_inner,
+            odd_outer_difference + rotated_low,
+            even_difference_outer - rotated_even,
+            odd_outer_sum - rotated_high,
+        ]
+    }
+
+    /// Scaled 8-point inverse DCT of Arai, Agui and Nakajima, as in libjpeg's `jidctflt`
+    fn apply_aan_inverse_dct_1d(input_coefficients: &[f32; 8]) -> [f32; 8] {
+        // Even part
+        let even_sum_outer = input_coefficients[0] + input_coefficients[4];
+        let even_difference_outer = input_coefficients[0] - input_coefficients[4];
+        let even_sum_inner = input_coefficients[2] + input_coefficients[6];
+        let even_difference_inner = (input_coefficients[2] - input_coefficients[6])
+            * std::f32::consts::SQRT_2
+            - even_sum_inner;
+        let even_0 = even_sum_outer + even_sum_inner;
+        let even_3 = even_sum_outer - even_sum_inner;
+        let even_1 = even_difference_outer + even_difference_inner;
+        let even_2 = even_difference_outer - even_difference_inner;
+
+        // Odd part
+        let sum_53 = input_coefficients[5] + input_coefficients[3];
+        let difference_53 = input_coefficients[5] - input_coefficients[3];
+        let sum_17 = input_coefficients[1] + input_coefficients[7];
+        let difference_17 = input_coefficients[1] - input_coefficients[7];
+        let odd_7 = sum_17 + sum_53;
+        let rotated_sum = (sum_17 - sum_53) * std::f32::consts::SQRT_2;
+        let rotation_shared = (difference_53 + difference_17) * 1.847_759;
+        let rotated_low = 1.082_392_2 * difference_17 - rotation_shared;
+        let rotated_high = -2.613_126 * difference_53 + rotation_shared;
+        let odd_6 = rotated_high - odd_7;
+        let odd_5 = rotated_sum - odd_6;
+        let odd_4 = rotated_low + odd_5;
+
+        [
+            even_0 + odd_7,
+            even_1 + odd_6,
+            even_2 + odd_5,
+            even_3 - odd_4,
+            even_3 + odd_4,
+            even_2 - odd_5,
+            even_1 - odd_6,
+            even_0 - odd_7,
+        ]
+    }
+}
+
+impl<const N: usize> DctProcessor<N> {
     /// Creates a new DCT processor with precomputed cosine values
     pub fn new() -> Self {
-        let mut cosine_lookup_table = [[0f32; 8]; 8];
+        let mut cosine_lookup_table = [[0f32; N]; N];
 
-        // Precompute cosine values for 8x8 DCT to optimize performance
-        for frequency_index in 0..8 {
-            for spatial_index in 0..8 {
-                cosine_lookup_table[frequency_index][spatial_index] = ((2 * spatial_index + 1)
-                    as f32
+        // Precompute cosine values for the NxN DCT to optimize performance
+        for (frequency_index, cosine_row) in cosine_lookup_table.iter_mut().enumerate() {
+            for (spatial_index, cosine_value) in cosine_row.iter_mut().enumerate() {
+                *cosine_value = ((2 * spatial_index + 1) as f32
                     * frequency_index as f32
                     * std::f32::consts::PI
-                    / 16.0)
+                    / (2 * N) as f32)
                     .cos();
             }
         }
 
         Self {
             cosine_lookup_table,
+            aan_scale_factors: None,
         }
     }
 
+    /// Side length of the blocks this processor transforms
+    pub fn block_size(&self) -> usize {
+        N
+    }
+
+    /// Reports whether the fast AAN transform is in use
+    pub fn uses_fast_transform(&self) -> bool {
+        self.aan_scale_factors.is_some()
+    }
+
+    /// Scale of the orthonormal N-point basis, sqrt(2/N), which is 1/2 for 8x8 blocks
+    fn basis_scale(&self) -> f32 {
+        (2.0 / N as f32).sqrt()
+    }
+
     /// Applies 1D DCT transformation to a single row or column
-    fn apply_dct_1d(&self, input_values: &[f32; 8]) -> [f32; 8] {
-        let mut output_coefficients = [0f32; 8];
+    fn apply_dct_1d(&self, input_values: &[f32; N]) -> [f32; N] {
+        let mut output_coefficients = [0f32; N];
 
-        for frequency_index in 0..8 {
+        if let Some(aan_scale_factors) = &self.aan_scale_factors {
+            // Only set on 8x8 processors, so N is 8 here
+            let aan_coefficients = DctProcessor::apply_aan_dct_1d(
+                input_values
+                    .as_slice()
+                    .try_into()
+                    .expect("AAN blocks are 8 wide"),
+            );
+            for ((output_coefficient, aan_coefficient), scale_factor) in output_coefficients
+                .iter_mut()
+                .zip(aan_coefficients)
+                .zip(aan_scale_factors)
+            {
+                *output_coefficient = aan_coefficient * scale_factor;
+            }
+            return output_coefficients;
+        }
+
+        for (frequency_index, output_coefficient) in output_coefficients.iter_mut().enumerate() {
             // DC coefficient normalization factor
             let normalization_factor = if frequency_index == 0 {
                 1.0 / (2.0_f32).sqrt()
@@ -39,26 +193,39 @@ impl DctProcessor {
                 1.0
             };
 
-            let mut coefficient_sum = 0.0;
-            for spatial_index in 0..8 {
-                coefficient_sum += input_values[spatial_index]
-                    * self.cosine_lookup_table[frequency_index][spatial_index];
-            }
+            let coefficient_sum: f32 = input_values
+                .iter()
+                .zip(self.cosine_lookup_table[frequency_index].iter())
+                .map(|(input_value, cosine_value)| input_value * cosine_value)
+                .sum();
 
-            output_coefficients[frequency_index] = 0.5 * normalization_factor * coefficient_sum;
+            *output_coefficient = self.basis_scale() * normalization_factor * coefficient_sum;
         }
 
         output_coefficients
     }
 
     /// Applies 1D inverse DCT transformation to convert back to spatial domain
-    fn apply_inverse_dct_1d(&self, input_coefficients: &[f32; 8]) -> [f32; 8] {
-        let mut output_values = [0f32; 8];
+    fn apply_inverse_dct_1d(&self, input_coefficients: &[f32; N]) -> [f32; N] {
+        let mut output_values = [0f32; N];
 
-        for spatial_index in 0..8 {
+        if let Some(aan_scale_factors) = &self.aan_scale_factors {
+            // The inverse butterflies expect frequency k scaled by sqrt(2) cos(k pi / 16) over
+            // 2 sqrt(2), which is one eighth of the inverse of the forward factor
+            let prescaled_coefficients: [f32; 8] = std::array::from_fn(|frequency_index| {
+                let aan_scale_factor = aan_scale_factors[frequency_index];
+                input_coefficients[frequency_index] / (8.0 * aan_scale_factor)
+            });
+            output_values.copy_from_slice(&DctProcessor::apply_aan_inverse_dct_1d(
+                &prescaled_coefficients,
+            ));
+            return output_values;
+        }
+
+        for (spatial_index, output_value) in output_values.iter_mut().enumerate() {
             let mut pixel_sum = 0.0;
 
-            for frequency_index in 0..8 {
+            for (frequency_index, input_coefficient) in input_coefficients.iter().enumerate() {
                 // DC coefficient normalization factor
                 let normalization_factor = if frequency_index == 0 {
                     1.0 / (2.0_f32).sqrt()
@@ -67,32 +234,30 @@ impl DctProcessor {
                 };
 
                 pixel_sum += normalization_factor
-                    * input_coefficients[frequency_index]
+                    * input_coefficient
                     * self.cosine_lookup_table[frequency_index][spatial_index];
             }
 
-            output_values[spatial_index] = 0.5 * pixel_sum;
+            *output_value = self.basis_scale() * pixel_sum;
         }
 
         output_values
     }
 
-    /// Applies 2D DCT to an 8x8 image block
-    pub fn apply_forward_dct(&self, image_block: &mut [[f32; 8]; 8]) -> Result<()> {
+    /// Applies 2D DCT to an NxN image block
+    pub fn apply_forward_dct(&self, image_block: &mut [[f32; N]; N]) -> Result<()> {
         // Apply 1D DCT to each row first
         for row in image_block.iter_mut() {
             *row = self.apply_dct_1d(row);
         }
 
         // Apply 1D DCT to each column
-        for column_index in 0..8 {
-            let mut column_values = [0f32; 8];
-            for row_index in 0..8 {
-                column_values[row_index] = image_block[row_index][column_index];
-            }
+        for column_index in 0..N {
+            let column_values: [f32; N] =
+                std::array::from_fn(|row_index| image_block[row_index][column_index]);
             let dct_column = self.apply_dct_1d(&column_values);
-            for row_index in 0..8 {
-                image_block[row_index][column_index] = dct_column[row_index];
+            for (row, dct_value) in image_block.iter_mut().zip(dct_column) {
+                row[column_index] = dct_value;
             }
         }
 
@@ -100,16 +265,14 @@ impl DctProcessor {
     }
 
     /// Applies 2D inverse DCT to convert DCT coefficients back to pixel values
-    pub fn apply_inverse_dct(&self, dct_block: &mut [[f32; 8]; 8]) -> Result<()> {
+    pub fn apply_inverse_dct(&self, dct_block: &mut [[f32; N]; N]) -> Result<()> {
         // Apply 1D inverse DCT to each column first
-        for column_index in 0..8 {
-            let mut column_coefficients = [0f32; 8];
-            for row_index in 0..8 {
-                column_coefficients[row_index] = dct_block[row_index][column_index];
-            }
+        for column_index in 0..N {
+            let column_coefficients: [f32; N] =
+                std::array::from_fn(|row_index| dct_block[row_index][column_index]);
             let spatial_column = self.apply_inverse_dct_1d(&column_coefficients);
-            for row_index in 0..8 {
-                dct_block[row_index][column_index] = spatial_column[row_index];
+            for (row, spatial_value) in dct_block.iter_mut().zip(spatial_column) {
+                row[column_index] = spatial_value;
             }
         }
 
@@ -122,7 +285,7 @@ impl DctProcessor {
     }
 }
 
-impl Default for DctProcessor {
+impl<const N: usize> Default for DctProcessor<N> {
     fn default() -> Self {
         Self::new()
     }
@@ -131,6 +294,7 @@ impl Default for DctProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::Rng;
 
     #[test]
     fn test_dct_roundtrip_accuracy() {
@@ -156,4 +320,81 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_fast_dct_matches_direct_transform() {
+        let direct_processor = DctProcessor::new();
+        let fast_processor = DctProcessor::new_fast();
+        assert!(fast_processor.uses_fast_transform());
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let source_block: [[f32; 8]; 8] =
+                std::array::from_fn(|_| std::array::from_fn(|_| rng.gen_range(0.0..255.0)));
+
+            let mut direct_block = source_block;
+            let mut fast_block = source_block;
+            direct_processor
+                .apply_forward_dct(&mut direct_block)
+                .unwrap();
+            fast_processor.apply_forward_dct(&mut fast_block).unwrap();
+            for (direct_value, fast_value) in direct_block
+                .iter()
+                .flatten()
+                .zip(fast_block.iter().flatten())
+            {
+                assert!((direct_value - fast_value).abs() < 1e-3);
+            }
+
+            direct_processor
+                .apply_inverse_dct(&mut direct_block)
+                .unwrap();
+            fast_processor.apply_inverse_dct(&mut fast_block).unwrap();
+            for (restored_value, source_value) in fast_block
+                .iter()
+                .flatten()
+                .zip(source_block.iter().flatten())
+            {
+                assert!((restored_value - source_value).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_16x16_dct_is_orthonormal_and_roundtrips() {
+        let dct_processor = DctProcessor::<16>::new();
+        assert_eq!(dct_processor.block_size(), 16);
+        let mut rng = rand::thread_rng();
+        let source_block: [[f32; 16]; 16] =
+            std::array::from_fn(|_| std::array::from_fn(|_| rng.gen_range(0.0..255.0)));
+
+        // A flat block puts all of its energy in DC: 256 samples of 100 scaled by 1/16
+        let mut flat_block = [[100.0f32; 16]; 16];
+        dct_processor.apply_forward_dct(&mut flat_block).unwrap();
+        assert!((flat_block[0][0] - 1600.0).abs() < 0.1);
+        assert!(flat_block
+            .iter()
+            .flatten()
+            .skip(1)
+            .all(|value| value.abs() < 0.01));
+
+        let mut test_block = source_block;
+        dct_processor.apply_forward_dct(&mut test_block).unwrap();
+        let spatial_energy: f32 = source_block
+            .iter()
+            .flatten()
+            .map(|value| value * value)
+            .sum();
+        let coefficient_energy: f32 = test_block.iter().flatten().map(|value| value * value).sum();
+        assert!((spatial_energy - coefficient_energy).abs() / spatial_energy < 1e-4);
+
+        dct_processor.apply_inverse_dct(&mut test_block).unwrap();
+        for (restored_value, source_value) in test_block
+            .iter()
+            .flatten()
+            .zip(source_block.iter().flatten())
+        {
+            assert!((restored_value - source_value).abs() < 1e-2);
+        }
+    }
 }
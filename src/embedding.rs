@@ -0,0 +1,1224 @@
+use crate::dct::DctProcessor;
+use crate::error::{Result, SteganographyError};
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher, StreamCipherSeek},
+    ChaCha20,
+};
+use std::fmt;
+use std::str::FromStr;
+
+/// Standard JPEG luminance quantization table
+const JPEG_LUMINANCE_QUANTIZATION_TABLE: [[f32; 8]; 8] = [
+    [16.0, 11.0, 10.0, 16.0, 24.0, 40.0, 51.0, 61.0],
+    [12.0, 12.0, 14.0, 19.0, 26.0, 58.0, 60.0, 55.0],
+    [14.0, 13.0, 16.0, 24.0, 40.0, 57.0, 69.0, 56.0],
+    [14.0, 17.0, 22.0, 29.0, 51.0, 87.0, 80.0, 62.0],
+    [18.0, 22.0, 37.0, 56.0, 68.0, 109.0, 103.0, 77.0],
+    [24.0, 35.0, 55.0, 64.0, 81.0, 104.0, 113.0, 92.0],
+    [49.0, 64.0, 78.0, 87.0, 103.0, 121.0, 120.0, 101.0],
+    [72.0, 92.0, 95.0, 98.0, 112.0, 100.0, 103.0, 99.0],
+];
+
+/// Side of the blocks the JPEG quantization table and the default strengths are given for
+const DCT_BLOCK_SIZE: usize = 8;
+
+/// Mid-frequency coefficients that keyed position selection draws from
+const KEYED_POSITION_CANDIDATES: [(usize, usize); 11] = [
+    (0, 4),
+    (1, 3),
+    (2, 2),
+    (3, 1),
+    (4, 0),
+    (0, 5),
+    (1, 4),
+    (2, 3),
+    (3, 2),
+    (4, 1),
+    (5, 0),
+];
+
+/// Nonce separating the position keystream from any keystream used for encryption
+const POSITION_SEED_NONCE: [u8; 12] = *b"steg-posseed";
+
+/// Keystream bytes reserved for selecting the positions of one block
+const POSITION_SEED_BYTES_PER_BLOCK: usize = 64;
+
+/// Upper bound on re-centering passes, since each pass can push more samples into clipping
+const MAX_BRIGHTNESS_CORRECTION_PASSES: usize = 4;
+
+/// Number of embedded positions consulted in the first voting tier
+const PRIMARY_VOTE_POSITIONS: usize = 4;
+
+/// Share of the embedding strength a coefficient must exceed to vote
+///
+/// 0.4 leaves room for the amplitude lost to JPEG quantization and rounding while keeping
+/// unembedded coefficients, which are mostly small, out of the vote.
+pub const DEFAULT_VOTE_THRESHOLD_FRACTION: f32 = 0.4;
+
+/// Quantization steps an embedded coefficient is pushed to at least, so recompression at the
+/// target quality rounds it to a nonzero value of the right sign
+const MINIMUM_AMPLITUDE_QUANTIZATION_STEPS: f32 = 1.8;
+
+/// A single recovered bit together with how decisively it was recovered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtractedBit {
+    pub bit_value: u8,
+    /// Confidence in the range 0.0 (coin flip) to 1.0 (unanimous)
+    pub confidence: f32,
+}
+
+/// Pluggable algorithm that hides one bit in each `N`x`N` spatial-domain image block
+///
+/// Blocks may be processed on several threads at once, so implementations must be `Sync`.
+pub trait EmbeddingAlgorithm<const N: usize = 8>: Sync {
+    /// Side length in pixels of the square blocks the algorithm operates on
+    fn block_size(&self) -> usize {
+        N
+    }
+
+    /// Embeds a single bit into a block of luminance values
+    ///
+    /// `block_index` is the block's position in visitation order, so algorithms can vary
+    /// their behaviour per block as long as extraction sees the same index.
+    fn embed_bit(
+        &self,
+        block_index: usize,
+        luminance_block: &mut [[f32; N]; N],
+        bit_value: u8,
+        jpeg_quality: u8,
+    ) -> Result<()>;
+
+    /// Recovers a single bit and its confidence from a block of luminance values
+    fn extract_bit(
+        &self,
+        block_index: usize,
+        luminance_block: &[[f32; N]; N],
+    ) -> Result<ExtractedBit>;
+
+    /// Calculates how many bits fit in an image of the given dimensions
+    ///
+    /// Fails with [`SteganographyError::InvalidInput`] if the count doesn't fit in a `usize`.
+    fn capacity(&self, image_width: u32, image_height: u32) -> Result<usize>;
+
+    /// YCbCr channels whose blocks carry bits, luminance only unless overridden
+    fn embedding_channel(&self) -> EmbeddingChannel {
+        EmbeddingChannel::Luma
+    }
+
+    /// Weights defining the luminance the luma channel carries, BT.601 unless overridden
+    fn color_standard(&self) -> ColorStandard {
+        ColorStandard::Bt601
+    }
+}
+
+/// One component of a YCbCr pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YCbCrComponent {
+    /// Luminance (Y)
+    Luma,
+    /// Blue-difference chroma (Cb)
+    BlueChroma,
+    /// Red-difference chroma (Cr)
+    RedChroma,
+}
+
+impl YCbCrComponent {
+    /// Short conventional name such as `Cb`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Luma => "Y",
+            Self::BlueChroma => "Cb",
+            Self::RedChroma => "Cr",
+        }
+    }
+}
+
+/// Channels of an RGB carrier that receive embedded bits
+///
+/// Chroma channels lose more to JPEG recompression than luminance, so `Luma` is the default
+/// and the other settings trade robustness for two or three times the capacity. Bits fill
+/// every block of the first channel before moving on to the next, which keeps the frame
+/// header in the most robust channel selected. Grayscale images only have luminance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingChannel {
+    /// Luminance only
+    #[default]
+    Luma,
+    /// The Cb and Cr chroma channels
+    Chroma,
+    /// Luminance followed by both chroma channels
+    All,
+}
+
+impl EmbeddingChannel {
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Luma => "luma",
+            Self::Chroma => "chroma",
+            Self::All => "all",
+        }
+    }
+
+    /// Looks up a channel selection by the name returned from `name`
+    pub fn from_name(channel_name: &str) -> Option<Self> {
+        [Self::Luma, Self::Chroma, Self::All]
+            .into_iter()
+            .find(|embedding_channel| embedding_channel.name() == channel_name)
+    }
+
+    /// Components receiving bits, in the order they are filled
+    pub fn components(self) -> &'static [YCbCrComponent] {
+        match self {
+            Self::Luma => &[YCbCrComponent::Luma],
+            Self::Chroma => &[YCbCrComponent::BlueChroma, YCbCrComponent::RedChroma],
+            Self::All => &[
+                YCbCrComponent::Luma,
+                YCbCrComponent::BlueChroma,
+                YCbCrComponent::RedChroma,
+            ],
+        }
+    }
+
+    /// Whether any chroma component receives bits
+    pub fn includes_chroma(self) -> bool {
+        self != Self::Luma
+    }
+}
+
+impl fmt::Display for EmbeddingChannel {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// Standard whose RGB weights define the luminance carrying luma bits
+///
+/// Embed and extract must use the same standard. Chroma channels always use the BT.601
+/// YCbCr conversion of JPEG itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorStandard {
+    /// ITU-R BT.601, the weights JPEG uses
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, the weights of HD video and sRGB
+    Bt709,
+}
+
+impl ColorStandard {
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bt601 => "bt601",
+            Self::Bt709 => "bt709",
+        }
+    }
+
+    /// Looks up a standard by the name returned from `name`
+    pub fn from_name(standard_name: &str) -> Option<Self> {
+        [Self::Bt601, Self::Bt709]
+            .into_iter()
+            .find(|color_standard| color_standard.name() == standard_name)
+    }
+
+    /// Red, green and blue weights of luminance, which sum to one
+    pub fn luminance_weights(self) -> [f32; 3] {
+        match self {
+            Self::Bt601 => [0.299, 0.587, 0.114],
+            Self::Bt709 => [0.2126, 0.7152, 0.0722],
+        }
+    }
+}
+
+impl fmt::Display for ColorStandard {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// How the embedding strength follows the texture of each block
+///
+/// Changes to a flat block stand out, while a busy block hides larger ones, so scaling the
+/// strength with texture trades robustness where it is cheap for invisibility where it is
+/// not. Extraction only looks at signs against the voting threshold, so it needs no setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AdaptiveStrength {
+    /// Every block is embedded with `embedding_strength`
+    #[default]
+    Fixed,
+    /// `embedding_strength` is scaled by the block's RMS AC coefficient over
+    /// `reference_deviation`, clamped to `minimum_scale..=maximum_scale`
+    BlockVariance {
+        reference_deviation: f32,
+        minimum_scale: f32,
+        maximum_scale: f32,
+    },
+}
+
+impl AdaptiveStrength {
+    /// Block variance scaling suited to photographs with both flat and textured areas
+    pub const PHOTOGRAPHIC: Self = Self::BlockVariance {
+        reference_deviation: 16.0,
+        minimum_scale: 0.6,
+        maximum_scale: 1.6,
+    };
+
+    /// Returns the factor applied to the embedding strength of a block
+    ///
+    /// The AC energy is measured before embedding and skips the carrying coefficients,
+    /// which are about to be overwritten. With an orthonormal DCT the RMS AC coefficient is
+    /// close to the standard deviation of the block's samples.
+    pub fn strength_scale<const N: usize>(
+        self,
+        dct_block: &[[f32; N]; N],
+        carrying_positions: &[(usize, usize)],
+    ) -> f32 {
+        let Self::BlockVariance {
+            reference_deviation,
+            minimum_scale,
+            maximum_scale,
+        } = self
+        else {
+            return 1.0;
+        };
+
+        let (ac_energy, ac_count) = (0..N)
+            .flat_map(|coefficient_y| {
+                (0..N).map(move |coefficient_x| (coefficient_y, coefficient_x))
+            })
+            .filter(|&position| position != (0, 0) && !carrying_positions.contains(&position))
+            .fold(
+                (0.0f32, 0usize),
+                |(ac_energy, ac_count), (coefficient_y, coefficient_x)| {
+                    (
+                        ac_energy + dct_block[coefficient_y][coefficient_x].powi(2),
+                        ac_count + 1,
+                    )
+                },
+            );
+        if ac_count == 0 {
+            return 1.0;
+        }
+        ((ac_energy / ac_count as f32).sqrt() / reference_deviation)
+            .clamp(minimum_scale, maximum_scale)
+    }
+}
+
+/// Configuration for steganography embedding parameters
+#[derive(Debug, Clone)]
+pub struct EmbeddingConfiguration {
+    /// Side of the DCT block in pixels, which must match the block size of the algorithm using
+    /// the configuration; positions and strengths are measured on blocks of this size
+    pub block_size: usize,
+    pub embedding_positions: Vec<(usize, usize)>,
+    /// How many of `embedding_positions` carry each bit; used identically by embed and extract
+    pub redundant_position_count: usize,
+    pub embedding_strength: f32,
+    pub minimum_quantization_step: f32,
+    /// Largest allowed change in a block's mean luminance after clamping to 0-255, or `None`
+    /// to skip the brightness correction
+    pub dc_guard_epsilon: Option<f32>,
+    /// Voting threshold as a fraction of `embedding_strength`, so it scales with the strength
+    pub vote_threshold_fraction: f32,
+    /// Fixed voting threshold used instead of the fraction, such as one calibrated from the
+    /// image being read
+    pub vote_threshold_override: Option<f32>,
+    /// Channels of an RGB carrier that receive bits; used identically by embed and extract
+    pub embedding_channel: EmbeddingChannel,
+    /// Whether embedding strength is scaled by each block's texture
+    pub adaptive_strength: AdaptiveStrength,
+    /// Luminance weights of the luma channel; used identically by embed and extract
+    pub color_standard: ColorStandard,
+}
+
+impl Default for EmbeddingConfiguration {
+    fn default() -> Self {
+        Self {
+            block_size: DCT_BLOCK_SIZE,
+            // Most robust DCT coefficient positions for JPEG compression survival
+            embedding_positions: vec![
+                (4, 1), // Primary robust position
+                (1, 4), // Secondary robust position
+                (3, 2), // Backup position
+                (2, 3), // Backup position
+                (5, 0),
+                (0, 5),
+                (3, 4),
+                (4, 3),
+            ],
+            // Positions beyond the first four break tied votes when redundancy is raised
+            redundant_position_count: 4,
+            embedding_strength: 16.0, // Strong enough to survive JPEG compression
+            minimum_quantization_step: 4.0,
+            dc_guard_epsilon: Some(0.5),
+            vote_threshold_fraction: DEFAULT_VOTE_THRESHOLD_FRACTION,
+            vote_threshold_override: None,
+            embedding_channel: EmbeddingChannel::Luma,
+            adaptive_strength: AdaptiveStrength::Fixed,
+            color_standard: ColorStandard::Bt601,
+        }
+    }
+}
+
+impl EmbeddingConfiguration {
+    /// Default configuration for `block_size`-sided DCT blocks
+    ///
+    /// The positions keep their indices, which in a larger block are lower spatial
+    /// frequencies that survive downscaling. An orthonormal basis function of an NxN block
+    /// changes each pixel by `1/N` of its coefficient, so the strengths are multiplied by
+    /// `block_size / 8` to change pixels as much as the 8x8 default does.
+    pub fn for_block_size(block_size: usize) -> Self {
+        let default_configuration = Self::default();
+        let amplitude_scale = block_size as f32 / DCT_BLOCK_SIZE as f32;
+        Self {
+            block_size,
+            embedding_strength: default_configuration.embedding_strength * amplitude_scale,
+            minimum_quantization_step: default_configuration.minimum_quantization_step
+                * amplitude_scale,
+            ..default_configuration
+        }
+    }
+
+    /// Coefficient magnitude a position must exceed to vote, also the scale of confidence
+    pub fn vote_threshold(&self) -> f32 {
+        self.vote_threshold_override
+            .unwrap_or(self.embedding_strength * self.vote_threshold_fraction)
+    }
+}
+
+/// Embedded amplitude and noise level estimated from one image's coefficients
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseFloorEstimate {
+    /// Typical magnitude of coefficients that carry a bit
+    pub embedded_amplitude: f32,
+    /// Typical magnitude of coefficients that carry nothing or were flattened
+    pub noise_level: f32,
+    /// Voting threshold chosen between the two
+    pub vote_threshold: f32,
+}
+
+impl NoiseFloorEstimate {
+    /// Splits coefficient magnitudes into a noise class and an embedded class
+    ///
+    /// The split maximizes the between-class variance (Otsu's method). Images whose payload
+    /// fills every block have no real noise class, so the threshold is also capped at half
+    /// the embedded amplitude. Returns `None` when there are no usable samples.
+    pub fn from_coefficient_magnitudes(coefficient_magnitudes: &[f32]) -> Option<Self> {
+        let mut sorted_magnitudes: Vec<f32> = coefficient_magnitudes
+            .iter()
+            .copied()
+            .filter(|magnitude| magnitude.is_finite())
+            .collect();
+        if sorted_magnitudes.len() < 2 {
+            return None;
+        }
+        sorted_magnitudes.sort_unstable_by(f32::total_cmp);
+
+        let sample_count = sorted_magnitudes.len() as f64;
+        let total_sum: f64 = sorted_magnitudes.iter().map(|&m| m as f64).sum();
+        let mut lower_sum = 0.0f64;
+        let mut best_split = (0.0f64, 1, sorted_magnitudes[0] as f64);
+        for split_index in 1..sorted_magnitudes.len() {
+            lower_sum += sorted_magnitudes[split_index - 1] as f64;
+            let lower_weight = split_index as f64 / sample_count;
+            let lower_mean = lower_sum / split_index as f64;
+            let upper_mean = (total_sum - lower_sum) / (sample_count - split_index as f64);
+            let between_class_variance =
+                lower_weight * (1.0 - lower_weight) * (upper_mean - lower_mean).powi(2);
+            if between_class_variance > best_split.0 {
+                best_split = (between_class_variance, split_index, lower_mean);
+            }
+        }
+
+        let (_, split_index, noise_level) = best_split;
+        let embedded_amplitude =
+            (total_sum - noise_level * split_index as f64) / (sample_count - split_index as f64);
+        let split_magnitude =
+            (sorted_magnitudes[split_index - 1] + sorted_magnitudes[split_index]) as f64 / 2.0;
+
+        Some(Self {
+            embedded_amplitude: embedded_amplitude as f32,
+            noise_level: noise_level as f32,
+            vote_threshold: split_magnitude.min(embedded_amplitude / 2.0) as f32,
+        })
+    }
+}
+
+/// Validated list of `(row, column)` DCT coefficient positions, parsed from `"4:1,1:4,3:2"`
+/// or `"4,1;1,4;3,2"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingPositions(Vec<(usize, usize)>);
+
+impl EmbeddingPositions {
+    /// Parses a comma-separated `row:column` list or a semicolon-separated `row,column` list,
+    /// checking each index is below `block_size` and that no position is repeated or the DC
+    /// coefficient
+    pub fn parse_for_block_size(position_list: &str, block_size: usize) -> Result<Self> {
+        let mut positions = Vec::new();
+        let entry_separator = if position_list.contains(':') && !position_list.contains(';') {
+            ','
+        } else {
+            ';'
+        };
+
+        for position_entry in position_list.split(entry_separator).map(str::trim) {
+            let (row_text, column_text) =
+                position_entry.split_once([':', ',']).ok_or_else(|| {
+                    SteganographyError::PositionParseError(format!(
+                        "'{}' is not in row,column or row:column form",
+                        position_entry
+                    ))
+                })?;
+
+            let parse_index = |index_text: &str| {
+                index_text.trim().parse::<usize>().map_err(|_| {
+                    SteganographyError::PositionParseError(format!(
+                        "'{}' in '{}' is not a non-negative integer",
+                        index_text, position_entry
+                    ))
+                })
+            };
+            let position = (parse_index(row_text)?, parse_index(column_text)?);
+
+            if position.0 >= block_size || position.1 >= block_size {
+                return Err(SteganographyError::PositionParseError(format!(
+                    "'{}' is outside the {}x{} block",
+                    position_entry, block_size, block_size
+                )));
+            }
+            // The DC coefficient sets the block's brightness, which embedding must not touch
+            if position == (0, 0) {
+                return Err(SteganographyError::PositionParseError(format!(
+                    "'{}' is the DC coefficient",
+                    position_entry
+                )));
+            }
+            if positions.contains(&position) {
+                return Err(SteganographyError::PositionParseError(format!(
+                    "'{}' is listed more than once",
+                    position_entry
+                )));
+            }
+            positions.push(position);
+        }
+
+        Ok(Self(positions))
+    }
+
+    /// Returns the parsed positions
+    pub fn positions(&self) -> &[(usize, usize)] {
+        &self.0
+    }
+
+    /// Consumes the wrapper, returning the parsed positions
+    pub fn into_inner(self) -> Vec<(usize, usize)> {
+        self.0
+    }
+}
+
+impl FromStr for EmbeddingPositions {
+    type Err = SteganographyError;
+
+    fn from_str(position_list: &str) -> Result<Self> {
+        Self::parse_for_block_size(position_list, EmbeddingConfiguration::default().block_size)
+    }
+}
+
+impl TryFrom<&str> for EmbeddingPositions {
+    type Error = SteganographyError;
+
+    fn try_from(position_list: &str) -> Result<Self> {
+        position_list.parse()
+    }
+}
+
+impl From<EmbeddingPositions> for Vec<(usize, usize)> {
+    fn from(embedding_positions: EmbeddingPositions) -> Self {
+        embedding_positions.into_inner()
+    }
+}
+
+/// Quantization-aware DCT embedding that survives JPEG recompression
+///
+/// Each bit is carried by an `N`x`N` block, 8x8 unless stated otherwise. Larger blocks
+/// spread a bit over more pixels, which survives downscaling better at the cost of capacity.
+pub struct DctEmbeddingAlgorithm<const N: usize = 8> {
+    configuration: EmbeddingConfiguration,
+    dct_processor: DctProcessor<N>,
+    /// Key that selects embedding positions per block instead of the fixed configured list
+    position_seed: Option<[u8; 32]>,
+}
+
+impl DctEmbeddingAlgorithm {
+    /// Creates a new DCT embedding algorithm with default configuration
+    pub fn new() -> Self {
+        Self::with_configuration(EmbeddingConfiguration::default())
+    }
+
+    /// Creates a new DCT embedding algorithm with custom configuration
+    pub fn with_configuration(configuration: EmbeddingConfiguration) -> Self {
+        Self::with_block_configuration(configuration)
+    }
+
+    /// Creates a DCT embedding algorithm whose positions are chosen per block from a key
+    ///
+    /// Each block uses `redundant_position_count` distinct mid-frequency coefficients drawn
+    /// from a ChaCha20 keystream, so without the key an observer cannot tell which
+    /// coefficients carry the payload. Extraction must use the same key.
+    pub fn with_keyed_positions(
+        configuration: EmbeddingConfiguration,
+        position_seed: [u8; 32],
+    ) -> Self {
+        Self::with_keyed_block_positions(configuration, position_seed)
+    }
+}
+
+impl<const N: usize> DctEmbeddingAlgorithm<N> {
+    /// Creates a DCT embedding algorithm on `N`x`N` blocks with custom configuration
+    ///
+    /// `configuration.block_size` must be `N`, as it is for
+    /// [`EmbeddingConfiguration::for_block_size`]; embedding and extraction fail otherwise.
+    pub fn with_block_configuration(configuration: EmbeddingConfiguration) -> Self {
+        Self {
+            dct_processor: DctProcessor::new(),
+            configuration,
+            position_seed: None,
+        }
+    }
+
+    /// Creates a DCT embedding algorithm on `N`x`N` blocks whose positions are chosen per
+    /// block from a key, as [`DctEmbeddingAlgorithm::with_keyed_positions`] does for 8x8 blocks
+    pub fn with_keyed_block_positions(
+        configuration: EmbeddingConfiguration,
+        position_seed: [u8; 32],
+    ) -> Self {
+        Self {
+            position_seed: Some(position_seed),
+            ..Self::with_block_configuration(configuration)
+        }
+    }
+
+    /// Returns the embedding configuration in use
+    pub fn configuration(&self) -> &EmbeddingConfiguration {
+        &self.configuration
+    }
+
+    /// Returns whether positions are chosen per block from a key
+    pub fn has_keyed_positions(&self) -> bool {
+        self.position_seed.is_some()
+    }
+
+    /// Replaces the voting threshold, e.g. with one calibrated from the image being read
+    pub fn set_vote_threshold(&mut self, vote_threshold: f32) {
+        self.configuration.vote_threshold_override = Some(vote_threshold);
+    }
+
+    /// Returns the magnitudes of the coefficients that carry the bit of a block
+    ///
+    /// Sampling these across an image is the first pass of noise-floor calibration.
+    pub fn embedded_coefficient_magnitudes(
+        &self,
+        block_index: usize,
+        luminance_block: &[[f32; N]; N],
+    ) -> Result<Vec<f32>> {
+        self.ensure_supported_block_size()?;
+        let mut dct_block = *luminance_block;
+        self.dct_processor.apply_forward_dct(&mut dct_block)?;
+
+        Ok(self
+            .positions_for_block(block_index)
+            .into_iter()
+            .map(|(coefficient_y, coefficient_x)| dct_block[coefficient_y][coefficient_x].abs())
+            .collect())
+    }
+
+    /// Returns whether a block's carrying coefficients look like they already hold a bit
+    ///
+    /// Embedding pushes every carrying coefficient of a block to the embedded amplitude with
+    /// one shared sign, so across an embedded image these coefficients cluster at plus and
+    /// minus that amplitude. A block matches when its coefficients share a sign and their mean
+    /// magnitude clears a quarter of the voting threshold, which leaves room for the amplitude
+    /// lost to RGB rounding and recompression. Natural content rarely does both.
+    pub fn shows_embedding_signature(
+        &self,
+        block_index: usize,
+        luminance_block: &[[f32; N]; N],
+    ) -> Result<bool> {
+        self.ensure_supported_block_size()?;
+        let mut dct_block = *luminance_block;
+        self.dct_processor.apply_forward_dct(&mut dct_block)?;
+
+        let coefficients: Vec<f32> = self
+            .positions_for_block(block_index)
+            .into_iter()
+            .map(|(coefficient_y, coefficient_x)| dct_block[coefficient_y][coefficient_x])
+            .collect();
+        let shared_sign = coefficients.iter().all(|&coefficient| coefficient > 0.0)
+            || coefficients.iter().all(|&coefficient| coefficient < 0.0);
+        let mean_magnitude = coefficients
+            .iter()
+            .map(|coefficient| coefficient.abs())
+            .sum::<f32>()
+            / coefficients.len() as f32;
+        Ok(shared_sign && mean_magnitude > self.configuration.vote_threshold() / 4.0)
+    }
+
+    /// Returns the quantization step used when embedding at the primary position
+    ///
+    /// This is the JPEG quantizer for that coefficient at `jpeg_quality`, floored at the
+    /// configured minimum step.
+    pub fn primary_quantization_step(&self, jpeg_quality: u8) -> f32 {
+        let (primary_y, primary_x) = self.configuration.embedding_positions[0];
+        self.calculate_quantization_table(jpeg_quality)[primary_y][primary_x]
+            .max(self.configuration.minimum_quantization_step)
+    }
+
+    /// Returns the magnitude the primary coefficient is pushed to when embedding
+    pub fn primary_embedding_amplitude(&self, jpeg_quality: u8) -> f32 {
+        self.configuration.embedding_strength.max(
+            self.primary_quantization_step(jpeg_quality) * MINIMUM_AMPLITUDE_QUANTIZATION_STEPS,
+        )
+    }
+
+    /// Returns the positions that carry each bit, shared by embedding and extraction
+    fn redundant_positions(&self) -> &[(usize, usize)] {
+        let embedding_positions = &self.configuration.embedding_positions;
+        &embedding_positions[..self
+            .configuration
+            .redundant_position_count
+            .clamp(1, embedding_positions.len())]
+    }
+
+    /// Rejects a configured `block_size` other than the side of the blocks being transformed
+    fn ensure_supported_block_size(&self) -> Result<()> {
+        if self.configuration.block_size != N {
+            return Err(SteganographyError::InvalidInput(format!(
+                "this algorithm transforms {0}x{0} blocks but is configured for block_size {1}; use EmbeddingConfiguration::for_block_size({0})",
+                N, self.configuration.block_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the positions carrying the bit of the given block, primary position first
+    fn positions_for_block(&self, block_index: usize) -> Vec<(usize, usize)> {
+        match &self.position_seed {
+            Some(position_seed) => self.select_keyed_positions(position_seed, block_index),
+            None => self.redundant_positions().to_vec(),
+        }
+    }
+
+    /// Draws distinct candidate positions for a block with a keyed partial Fisher-Yates shuffle
+    fn select_keyed_positions(
+        &self,
+        position_seed: &[u8; 32],
+        block_index: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut keystream = [0u8; POSITION_SEED_BYTES_PER_BLOCK];
+        let mut cipher = ChaCha20::new(position_seed.into(), &POSITION_SEED_NONCE.into());
+        cipher.seek(block_index as u64 * POSITION_SEED_BYTES_PER_BLOCK as u64);
+        cipher.apply_keystream(&mut keystream);
+
+        let position_count = self
+            .configuration
+            .redundant_position_count
+            .clamp(1, KEYED_POSITION_CANDIDATES.len());
+        let mut candidates = KEYED_POSITION_CANDIDATES;
+        for (selection_index, random_bytes) in
+            keystream.chunks_exact(4).take(position_count).enumerate()
+        {
+            let random_value = u32::from_le_bytes(random_bytes.try_into().unwrap()) as usize;
+            let swap_index = selection_index + random_value % (candidates.len() - selection_index);
+            candidates.swap(selection_index, swap_index);
+        }
+
+        candidates[..position_count].to_vec()
+    }
+
+    /// Calculates quantization table based on JPEG quality factor
+    ///
+    /// Larger blocks read the quantizer of the 8x8 coefficient nearest in spatial frequency,
+    /// scaled up by `N / 8` to match their coefficients' larger amplitude.
+    fn calculate_quantization_table(&self, jpeg_quality: u8) -> [[f32; N]; N] {
+        let quality_factor = jpeg_quality.clamp(1, 100) as f32;
+        let scaling_factor = if quality_factor < 50.0 {
+            5000.0 / quality_factor
+        } else {
+            200.0 - 2.0 * quality_factor
+        };
+        let amplitude_scale = N as f32 / DCT_BLOCK_SIZE as f32;
+
+        let mut quantization_table = [[0.0f32; N]; N];
+        for (coefficient_y, quantization_row) in quantization_table.iter_mut().enumerate() {
+            for (coefficient_x, quantized_value) in quantization_row.iter_mut().enumerate() {
+                let standard_value = JPEG_LUMINANCE_QUANTIZATION_TABLE
+                    [coefficient_y * DCT_BLOCK_SIZE / N][coefficient_x * DCT_BLOCK_SIZE / N];
+                *quantized_value = ((standard_value * scaling_factor + 50.0) / 100.0)
+                    .floor()
+                    .clamp(1.0, 255.0)
+                    * amplitude_scale;
+            }
+        }
+        quantization_table
+    }
+
+    /// Embeds a bit robustly using multiple DCT coefficients for redundancy
+    fn embed_bit_robustly(
+        &self,
+        dct_block: &mut [[f32; N]; N],
+        bit_value: u8,
+        quantization_table: &[[f32; N]; N],
+        positions_to_use: &[(usize, usize)],
+    ) {
+        // Flat blocks get gentler changes, though never below what quantization would erase
+        let strength_scale = self
+            .configuration
+            .adaptive_strength
+            .strength_scale(dct_block, positions_to_use);
+
+        // Use multiple positions for redundancy
+        for &(coefficient_y, coefficient_x) in positions_to_use {
+            let coefficient = &mut dct_block[coefficient_y][coefficient_x];
+            let quantization_step = quantization_table[coefficient_y][coefficient_x]
+                .max(self.configuration.minimum_quantization_step);
+            let embedding_strength = (self.configuration.embedding_strength * strength_scale)
+                .max(quantization_step * MINIMUM_AMPLITUDE_QUANTIZATION_STEPS);
+
+            // Use strong coefficient modification for JPEG compression survival
+            if bit_value == 1 {
+                *coefficient = embedding_strength; // Strongly positive for bit 1
+            } else {
+                *coefficient = -embedding_strength; // Strongly negative for bit 0
+            }
+        }
+    }
+
+    /// Shifts a block so its mean luminance after clamping matches `original_mean` as closely
+    /// as clipping allows
+    ///
+    /// Changing AC coefficients leaves the mean untouched until samples are clipped to 0-255,
+    /// which brightens or darkens the block. A uniform shift only alters the DC coefficient,
+    /// so the embedded bit is unaffected.
+    fn preserve_block_brightness(
+        &self,
+        luminance_block: &mut [[f32; N]; N],
+        original_mean: f32,
+        epsilon: f32,
+    ) {
+        for _ in 0..MAX_BRIGHTNESS_CORRECTION_PASSES {
+            let clamped_mean = luminance_block
+                .iter()
+                .flatten()
+                .map(|luminance_value| luminance_value.clamp(0.0, 255.0))
+                .sum::<f32>()
+                / (N * N) as f32;
+            let brightness_shift = original_mean - clamped_mean;
+            if brightness_shift.abs() < epsilon {
+                return;
+            }
+
+            luminance_block
+                .iter_mut()
+                .flatten()
+                .for_each(|luminance_value| *luminance_value += brightness_shift);
+        }
+    }
+
+    /// Extracts a bit robustly using majority voting from multiple coefficients
+    ///
+    /// The first four embedded positions vote first; if they tie, any further embedded
+    /// positions are consulted before falling back to the primary coefficient's sign.
+    /// Confidence is each coefficient's signed distance towards the voting threshold,
+    /// averaged over the embedded positions, so neutral or dissenting coefficients lower it.
+    fn extract_bit_robustly(
+        &self,
+        dct_block: &[[f32; N]; N],
+        positions_to_check: &[(usize, usize)],
+    ) -> ExtractedBit {
+        let (primary_positions, secondary_positions) =
+            positions_to_check.split_at(PRIMARY_VOTE_POSITIONS.min(positions_to_check.len()));
+
+        let bit_value = self
+            .vote_on_positions(dct_block, primary_positions)
+            .or_else(|| self.vote_on_positions(dct_block, secondary_positions))
+            .unwrap_or_else(|| {
+                // If still tied, check the primary coefficient with lower threshold
+                let (primary_y, primary_x) = positions_to_check[0];
+                let primary_value = dct_block[primary_y][primary_x];
+
+                if primary_value > 0.0 {
+                    1
+                } else {
+                    0
+                }
+            });
+
+        let bit_sign = if bit_value == 1 { 1.0 } else { -1.0 };
+        let agreement = positions_to_check
+            .iter()
+            .map(|&(coefficient_y, coefficient_x)| {
+                (dct_block[coefficient_y][coefficient_x] * bit_sign
+                    / self.configuration.vote_threshold())
+                .clamp(-1.0, 1.0)
+            })
+            .sum::<f32>()
+            / positions_to_check.len() as f32;
+
+        ExtractedBit {
+            bit_value,
+            confidence: agreement.max(0.0),
+        }
+    }
+
+    /// Takes a majority vote over the given positions, returning `None` on a tie
+    fn vote_on_positions(
+        &self,
+        dct_block: &[[f32; N]; N],
+        positions: &[(usize, usize)],
+    ) -> Option<u8> {
+        let vote_threshold = self.configuration.vote_threshold();
+        let mut votes_for_1 = 0;
+        let mut votes_for_0 = 0;
+
+        for &(coefficient_y, coefficient_x) in positions {
+            let coefficient_value = dct_block[coefficient_y][coefficient_x];
+
+            if coefficient_value > vote_threshold {
+                votes_for_1 += 1;
+            } else if coefficient_value < -vote_threshold {
+                votes_for_0 += 1;
+            }
+            // Values within the threshold of zero are considered neutral (no vote)
+        }
+
+        match votes_for_1.cmp(&votes_for_0) {
+            std::cmp::Ordering::Greater => Some(1),
+            std::cmp::Ordering::Less => Some(0),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+impl<const N: usize> EmbeddingAlgorithm<N> for DctEmbeddingAlgorithm<N> {
+    fn embed_bit(
+        &self,
+        block_index: usize,
+        luminance_block: &mut [[f32; N]; N],
+        bit_value: u8,
+        jpeg_quality: u8,
+    ) -> Result<()> {
+        self.ensure_supported_block_size()?;
+        let quantization_table = self.calculate_quantization_table(jpeg_quality);
+        let positions_to_use = self.positions_for_block(block_index);
+        let original_mean = luminance_block.iter().flatten().sum::<f32>() / (N * N) as f32;
+
+        self.dct_processor.apply_forward_dct(luminance_block)?;
+        self.embed_bit_robustly(
+            luminance_block,
+            bit_value,
+            &quantization_table,
+            &positions_to_use,
+        );
+        self.dct_processor.apply_inverse_dct(luminance_block)?;
+
+        if let Some(epsilon) = self.configuration.dc_guard_epsilon {
+            self.preserve_block_brightness(luminance_block, original_mean, epsilon);
+        }
+
+        Ok(())
+    }
+
+    fn extract_bit(
+        &self,
+        block_index: usize,
+        luminance_block: &[[f32; N]; N],
+    ) -> Result<ExtractedBit> {
+        self.ensure_supported_block_size()?;
+        let mut dct_block = *luminance_block;
+        self.dct_processor.apply_forward_dct(&mut dct_block)?;
+
+        Ok(self.extract_bit_robustly(&dct_block, &self.positions_for_block(block_index)))
+    }
+
+    fn capacity(&self, image_width: u32, image_height: u32) -> Result<usize> {
+        let horizontal_blocks = (image_width as usize).div_ceil(N);
+        let vertical_blocks = (image_height as usize).div_ceil(N);
+        // One bit per block for robustness; the block count can overflow on 32-bit targets
+        horizontal_blocks
+            .checked_mul(vertical_blocks)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "a {}x{} image has more blocks than this platform can count",
+                    image_width, image_height
+                ))
+            })
+    }
+
+    fn embedding_channel(&self) -> EmbeddingChannel {
+        self.configuration.embedding_channel
+    }
+
+    fn color_standard(&self) -> ColorStandard {
+        self.configuration.color_standard
+    }
+}
+
+impl<const N: usize> Default for DctEmbeddingAlgorithm<N> {
+    fn default() -> Self {
+        Self::with_block_configuration(EmbeddingConfiguration::for_block_size(N))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_positions_parsing() {
+        let embedding_positions = EmbeddingPositions::try_from("4:1, 1:4,3:2").unwrap();
+        assert_eq!(embedding_positions.positions(), &[(4, 1), (1, 4), (3, 2)]);
+
+        let parsed_positions: EmbeddingPositions = "0:7".parse().unwrap();
+        assert_eq!(Vec::from(parsed_positions), vec![(0, 7)]);
+
+        let semicolon_positions = EmbeddingPositions::try_from("4,1; 1,4;3,2").unwrap();
+        assert_eq!(semicolon_positions.positions(), &[(4, 1), (1, 4), (3, 2)]);
+        assert_eq!(
+            EmbeddingPositions::try_from("4,1").unwrap().positions(),
+            &[(4, 1)]
+        );
+    }
+
+    #[test]
+    fn test_embedding_positions_rejects_invalid_entries() {
+        for invalid_list in [
+            "",
+            "4-1",
+            "4:x",
+            "8:0",
+            "1:4,",
+            "-1:2",
+            "0:0",
+            "4,1;0,0",
+            "4,1;1,4;4,1",
+            "4,1;",
+        ] {
+            assert!(
+                matches!(
+                    EmbeddingPositions::try_from(invalid_list),
+                    Err(SteganographyError::PositionParseError(_))
+                ),
+                "'{}' should be rejected",
+                invalid_list
+            );
+        }
+
+        assert!(EmbeddingPositions::parse_for_block_size("9:9", 16).is_ok());
+    }
+
+    #[test]
+    fn test_tied_vote_consults_secondary_positions() {
+        let embedding_algorithm =
+            DctEmbeddingAlgorithm::with_configuration(EmbeddingConfiguration {
+                redundant_position_count: 8,
+                ..EmbeddingConfiguration::default()
+            });
+
+        // The first four positions split 2-2 with a negative primary coefficient
+        let mut dct_block = [[0.0f32; 8]; 8];
+        for (position_index, &(coefficient_y, coefficient_x)) in
+            embedding_algorithm.redundant_positions().iter().enumerate()
+        {
+            dct_block[coefficient_y][coefficient_x] = match position_index {
+                0 | 1 => -30.0,
+                _ => 30.0,
+            };
+        }
+        let extracted_bit = embedding_algorithm
+            .extract_bit_robustly(&dct_block, embedding_algorithm.redundant_positions());
+        assert_eq!(extracted_bit.bit_value, 1);
+
+        // With only four positions embedded the primary sign still decides
+        let default_algorithm = DctEmbeddingAlgorithm::new();
+        let extracted_bit = default_algorithm
+            .extract_bit_robustly(&dct_block, default_algorithm.redundant_positions());
+        assert_eq!(extracted_bit.bit_value, 0);
+    }
+
+    #[test]
+    fn test_high_redundancy_embeds_every_configured_position() {
+        let embedding_algorithm =
+            DctEmbeddingAlgorithm::with_configuration(EmbeddingConfiguration {
+                redundant_position_count: 8,
+                ..EmbeddingConfiguration::default()
+            });
+        let quantization_table = embedding_algorithm.calculate_quantization_table(85);
+
+        let mut dct_block = [[0.0f32; 8]; 8];
+        embedding_algorithm.embed_bit_robustly(
+            &mut dct_block,
+            1,
+            &quantization_table,
+            embedding_algorithm.redundant_positions(),
+        );
+
+        for &(coefficient_y, coefficient_x) in
+            &embedding_algorithm.configuration().embedding_positions
+        {
+            assert!(dct_block[coefficient_y][coefficient_x] > 10.0);
+        }
+    }
+
+    #[test]
+    fn test_keyed_positions_are_deterministic_and_key_dependent() {
+        let first_algorithm =
+            DctEmbeddingAlgorithm::with_keyed_positions(EmbeddingConfiguration::default(), [7; 32]);
+        let same_key_algorithm =
+            DctEmbeddingAlgorithm::with_keyed_positions(EmbeddingConfiguration::default(), [7; 32]);
+        let other_key_algorithm =
+            DctEmbeddingAlgorithm::with_keyed_positions(EmbeddingConfiguration::default(), [8; 32]);
+
+        let first_positions: Vec<_> = (0..32)
+            .map(|i| first_algorithm.positions_for_block(i))
+            .collect();
+        let same_key_positions: Vec<_> = (0..32)
+            .map(|i| same_key_algorithm.positions_for_block(i))
+            .collect();
+        let other_key_positions: Vec<_> = (0..32)
+            .map(|i| other_key_algorithm.positions_for_block(i))
+            .collect();
+
+        assert_eq!(first_positions, same_key_positions);
+        assert_ne!(first_positions, other_key_positions);
+        assert!(first_positions.windows(2).any(|pair| pair[0] != pair[1]));
+
+        for block_positions in &first_positions {
+            assert_eq!(block_positions.len(), 4);
+            let mut distinct_positions = block_positions.clone();
+            distinct_positions.sort();
+            distinct_positions.dedup();
+            assert_eq!(distinct_positions.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_noise_floor_estimate_separates_noise_from_embedded_amplitude() {
+        let coefficient_magnitudes: Vec<f32> = (0..40)
+            .map(|i| 1.0 + (i % 5) as f32 * 0.5)
+            .chain((0..56).map(|i| 18.0 + (i % 7) as f32))
+            .collect();
+
+        let noise_floor_estimate =
+            NoiseFloorEstimate::from_coefficient_magnitudes(&coefficient_magnitudes).unwrap();
+        assert!((noise_floor_estimate.noise_level - 2.0).abs() < 0.01);
+        assert!((noise_floor_estimate.embedded_amplitude - 21.0).abs() < 0.1);
+        assert!(noise_floor_estimate.vote_threshold > 3.0);
+        assert!(noise_floor_estimate.vote_threshold <= 10.5);
+
+        // Without a noise class the threshold stays well below the embedded amplitude
+        let saturated_estimate =
+            NoiseFloorEstimate::from_coefficient_magnitudes(&[24.0, 25.0, 26.0, 27.0]).unwrap();
+        assert!(saturated_estimate.vote_threshold <= saturated_estimate.embedded_amplitude / 2.0);
+        assert!(NoiseFloorEstimate::from_coefficient_magnitudes(&[f32::NAN, 3.0]).is_none());
+    }
+
+    #[test]
+    fn test_block_size_must_match_the_transform() {
+        let embedding_algorithm =
+            DctEmbeddingAlgorithm::with_configuration(EmbeddingConfiguration {
+                block_size: 16,
+                ..EmbeddingConfiguration::default()
+            });
+        let mut luminance_block = [[128.0; 8]; 8];
+
+        assert!(matches!(
+            embedding_algorithm.embed_bit(0, &mut luminance_block, 1, 85),
+            Err(SteganographyError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            embedding_algorithm.extract_bit(0, &luminance_block),
+            Err(SteganographyError::InvalidInput(_))
+        ));
+
+        let mismatched_algorithm = DctEmbeddingAlgorithm::<16>::with_block_configuration(
+            EmbeddingConfiguration::default(),
+        );
+        assert!(mismatched_algorithm
+            .extract_bit(0, &[[128.0; 16]; 16])
+            .is_err());
+    }
+
+    #[test]
+    fn test_larger_blocks_scale_strength_and_quantizers_to_the_block() {
+        let eight_pixel_algorithm = DctEmbeddingAlgorithm::new();
+        let sixteen_pixel_algorithm = DctEmbeddingAlgorithm::<16>::default();
+        assert_eq!(sixteen_pixel_algorithm.block_size(), 16);
+        assert_eq!(sixteen_pixel_algorithm.configuration().block_size, 16);
+        assert_eq!(
+            sixteen_pixel_algorithm.configuration().embedding_positions,
+            eight_pixel_algorithm.configuration().embedding_positions
+        );
+        assert_eq!(
+            sixteen_pixel_algorithm.configuration().embedding_strength,
+            2.0 * eight_pixel_algorithm.configuration().embedding_strength
+        );
+        assert_eq!(
+            sixteen_pixel_algorithm.calculate_quantization_table(85)[4][1],
+            2.0 * eight_pixel_algorithm.calculate_quantization_table(85)[2][0]
+        );
+
+        for bit_value in [0, 1] {
+            let mut luminance_block: [[f32; 16]; 16] =
+                std::array::from_fn(|y| std::array::from_fn(|x| 90.0 + (x + 2 * y) as f32));
+            sixteen_pixel_algorithm
+                .embed_bit(0, &mut luminance_block, bit_value, 85)
+                .unwrap();
+            let extracted_bit = sixteen_pixel_algorithm
+                .extract_bit(0, &luminance_block)
+                .unwrap();
+            assert_eq!(extracted_bit.bit_value, bit_value);
+            assert!(extracted_bit.confidence > 0.9);
+        }
+    }
+
+    #[test]
+    fn test_dc_guard_preserves_block_brightness() {
+        let epsilon = 0.5;
+        let embedding_algorithm = DctEmbeddingAlgorithm::new();
+        let mean_after_clamping = |luminance_block: &[[f32; 8]; 8]| {
+            luminance_block
+                .iter()
+                .flatten()
+                .map(|luminance_value| luminance_value.round().clamp(0.0, 255.0))
+                .sum::<f32>()
+                / 64.0
+        };
+
+        for base_luminance in [2.0, 40.0, 128.0, 230.0, 245.0] {
+            for bit_value in [0, 1] {
+                let original_block: [[f32; 8]; 8] = std::array::from_fn(|y| {
+                    std::array::from_fn(|x| (base_luminance + (x + y) as f32 * 0.3).min(255.0))
+                });
+                let original_mean = mean_after_clamping(&original_block);
+
+                let mut luminance_block = original_block;
+                embedding_algorithm
+                    .embed_bit(0, &mut luminance_block, bit_value, 85)
+                    .unwrap();
+
+                assert!(
+                    (mean_after_clamping(&luminance_block) - original_mean).abs() < epsilon,
+                    "block at {} shifted brightness",
+                    base_luminance
+                );
+                assert_eq!(
+                    embedding_algorithm
+                        .extract_bit(0, &luminance_block)
+                        .unwrap()
+                        .bit_value,
+                    bit_value
+                );
+            }
+        }
+    }
+}
@@ -19,6 +19,10 @@ pub enum SteganographyError {
     Base64Error(String),
     /// UTF-8 conversion errors
     Utf8Error(std::string::FromUtf8Error),
+    /// Payload compression/decompression errors
+    CompressionError(String),
+    /// Embedding position list parsing errors
+    PositionParseError(String),
 }
 
 impl fmt::Display for SteganographyError {
@@ -39,8 +43,11 @@ impl fmt::Display for SteganographyError {
             } => {
                 write!(
                     formatter,
-                    "Insufficient capacity: need {} bits, only {} available",
-                    required, available
+                    "Insufficient capacity: need {} bytes ({} bits) but only {} bytes ({} bits) are available",
+                    required.div_ceil(8),
+                    required,
+                    available / 8,
+                    available
                 )
             }
             SteganographyError::InvalidInput(message) => {
@@ -55,11 +62,25 @@ impl fmt::Display for SteganographyError {
             SteganographyError::Utf8Error(error) => {
                 write!(formatter, "UTF-8 conversion error: {}", error)
             }
+            SteganographyError::CompressionError(message) => {
+                write!(formatter, "Compression error: {}", message)
+            }
+            SteganographyError::PositionParseError(message) => {
+                write!(formatter, "Invalid embedding positions: {}", message)
+            }
         }
     }
 }
 
-impl std::error::Error for SteganographyError {}
+impl std::error::Error for SteganographyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SteganographyError::IoError(error) => Some(error),
+            SteganographyError::Utf8Error(error) => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for SteganographyError {
     fn from(error: std::io::Error) -> Self {
@@ -87,3 +108,54 @@ impl From<base64::DecodeError> for SteganographyError {
 
 /// Result type alias for steganography operations
 pub type Result<T> = std::result::Result<T, SteganographyError>;
+
+/// Describes what was being attempted when an operation failed
+///
+/// Implemented for any result whose error can be displayed, so a downstream caller can
+/// write `steg::extract(&stego, &key).context("reading the invoice image")?`.
+pub trait ErrorContext<T> {
+    /// Replaces the error with an [`SteganographyError::InvalidInput`] reading
+    /// `"<message>: <error>"`
+    fn context(self, message: impl fmt::Display) -> Result<T>;
+}
+
+impl<T, E: fmt::Display> ErrorContext<T> for std::result::Result<T, E> {
+    fn context(self, message: impl fmt::Display) -> Result<T> {
+        self.map_err(|error| SteganographyError::InvalidInput(format!("{}: {}", message, error)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_wrapped_errors_are_exposed_as_source() {
+        let io_error: SteganographyError =
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no such file").into();
+        assert_eq!(io_error.source().unwrap().to_string(), "no such file");
+
+        let utf8_error: SteganographyError = String::from_utf8(vec![0xff]).unwrap_err().into();
+        assert!(utf8_error.source().is_some());
+
+        assert!(SteganographyError::InvalidInput("bad".to_string())
+            .source()
+            .is_none());
+    }
+
+    #[test]
+    fn test_context_prefixes_the_error_message() {
+        let failed: std::result::Result<(), SteganographyError> =
+            Err(SteganographyError::CryptoError("wrong key".to_string()));
+
+        match failed.context("reading holiday.jpg") {
+            Err(SteganographyError::InvalidInput(message)) => assert_eq!(
+                message,
+                "reading holiday.jpg: Cryptography error: wrong key"
+            ),
+            other => panic!("expected a context error, got {:?}", other),
+        }
+        assert_eq!(Ok::<_, SteganographyError>(7).context("unused").unwrap(), 7);
+    }
+}
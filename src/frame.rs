@@ -0,0 +1,991 @@
+use crate::error::{Result, SteganographyError};
+use crate::reed_solomon;
+use std::fmt;
+
+/// Frame format version written by this build
+pub const FRAME_VERSION: u8 = 3;
+
+/// Oldest frame version still readable; version 0 frames carry no content type byte
+const LEGACY_FRAME_VERSION: u8 = 0;
+
+/// Last frame version without a coding scheme byte; such frames are repetition coded
+const REPETITION_ONLY_FRAME_VERSION: u8 = 1;
+
+/// Last frame version without a configuration fingerprint
+const UNFINGERPRINTED_FRAME_VERSION: u8 = 2;
+
+/// Length header bit marking a payload that was deflated before encryption
+const COMPRESSED_PAYLOAD_FLAG: u32 = 1 << 31;
+
+/// Length header bit marking a frame followed by the carrier's pre-padding dimensions
+const CARRIER_DIMENSIONS_FLAG: u32 = 1 << 30;
+
+/// Position of the two-bit format version within the length header
+const FRAME_VERSION_SHIFT: u32 = 28;
+
+/// Length header bits holding the format version
+const FRAME_VERSION_MASK: u32 = 0b11 << FRAME_VERSION_SHIFT;
+
+/// Position of the two-bit block visitation order within the length header
+const BLOCK_VISITATION_SHIFT: u32 = 26;
+
+/// Length header bits holding the block visitation order
+const BLOCK_VISITATION_MASK: u32 = 0b11 << BLOCK_VISITATION_SHIFT;
+
+/// Length header bit marking a body that starts with the salt a passphrase key was derived with
+const PASSPHRASE_SALT_FLAG: u32 = 1 << 25;
+
+/// Length header bit marking a payload sealed with ChaCha20-Poly1305 and ending in its tag
+const AUTHENTICATED_PAYLOAD_FLAG: u32 = 1 << 24;
+
+/// Length header bits reserved for flags and version rather than the payload length
+const FRAME_FLAG_MASK: u32 = COMPRESSED_PAYLOAD_FLAG
+    | CARRIER_DIMENSIONS_FLAG
+    | FRAME_VERSION_MASK
+    | BLOCK_VISITATION_MASK
+    | PASSPHRASE_SALT_FLAG
+    | AUTHENTICATED_PAYLOAD_FLAG;
+
+/// Largest payload length representable in the header bits not reserved for flags
+///
+/// This 2^24 - 1 byte cap is kept on purpose instead of widening the field to 64 bits: with
+/// repetition coding a payload that large already needs a carrier of about 43 gigapixels.
+const MAX_FRAMED_PAYLOAD_LENGTH: u64 = !FRAME_FLAG_MASK as u64;
+
+/// Size of the little-endian length header in bytes
+const LENGTH_HEADER_SIZE: usize = 4;
+
+/// Size of the content type that follows the length header
+const CONTENT_TYPE_SIZE: usize = 1;
+
+/// Size of the coding scheme that follows the content type
+const CODING_SCHEME_SIZE: usize = 1;
+
+/// Coding scheme byte bit marking a plaintext that ends in its CRC32
+const PLAINTEXT_CHECKSUM_FLAG: u8 = 1 << 7;
+
+/// Size of the embedding configuration fingerprint that follows the coding scheme
+const CONFIGURATION_FINGERPRINT_SIZE: usize = 2;
+
+/// Offset of the configuration fingerprint, which only depends on the frame version
+const CONFIGURATION_FINGERPRINT_OFFSET: usize =
+    LENGTH_HEADER_SIZE + CONTENT_TYPE_SIZE + CODING_SCHEME_SIZE;
+
+/// Size of the optional width and height that follow the content type
+const CARRIER_DIMENSIONS_SIZE: usize = 4;
+
+/// Marker opening a sentinel frame, so extraction can tell there is a payload at all
+pub const SENTINEL_START_MARKER: [u8; 4] = *b"ST3G";
+
+/// End-of-message sentinel closing a sentinel frame
+pub const SENTINEL_END_MARKER: [u8; 4] = *b"\x00EOM";
+
+/// Size of the largest header, with carrier dimensions present
+pub const MAX_FRAME_HEADER_LENGTH: usize = LENGTH_HEADER_SIZE
+    + CONTENT_TYPE_SIZE
+    + CODING_SCHEME_SIZE
+    + CONFIGURATION_FINGERPRINT_SIZE
+    + CARRIER_DIMENSIONS_SIZE;
+
+/// Size of the header in front of each part of a payload split across several carriers
+pub const PART_HEADER_SIZE: usize = 8;
+
+/// Kind of data carried by a frame, telling extraction how to present the plaintext
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContentType {
+    /// UTF-8 text, printed as-is
+    #[default]
+    Text,
+    /// Arbitrary bytes, hex-dumped or written to a file
+    Binary,
+    /// A gzip stream, decompressed on extraction
+    Gzip,
+    /// A JSON document, printed as text
+    Json,
+}
+
+impl ContentType {
+    /// Every content type, in encoding order
+    pub const ALL: [ContentType; 4] = [Self::Text, Self::Binary, Self::Gzip, Self::Json];
+
+    /// Encodes the content type as its header byte
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Text => 0,
+            Self::Binary => 1,
+            Self::Gzip => 2,
+            Self::Json => 3,
+        }
+    }
+
+    /// Decodes a header byte written by `to_byte`
+    pub fn from_byte(content_type_byte: u8) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|content_type| content_type.to_byte() == content_type_byte)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "Unknown content type {}",
+                    content_type_byte
+                ))
+            })
+    }
+
+    /// Short lowercase name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Binary => "binary",
+            Self::Gzip => "gzip",
+            Self::Json => "json",
+        }
+    }
+
+    /// Looks up a content type by the name returned from `name`
+    pub fn from_name(content_type_name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|content_type| content_type.name() == content_type_name)
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// Error-correcting code protecting the encrypted payload in the frame body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CodingScheme {
+    /// Every byte repeated and decided by (soft) majority vote
+    #[default]
+    Repetition,
+    /// Reed-Solomon blocks with 32 parity bytes each
+    ReedSolomon,
+}
+
+impl CodingScheme {
+    /// Every coding scheme, in encoding order
+    pub const ALL: [CodingScheme; 2] = [Self::Repetition, Self::ReedSolomon];
+
+    /// Encodes the scheme as its header byte
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Repetition => 0,
+            Self::ReedSolomon => 1,
+        }
+    }
+
+    /// Decodes a header byte written by `to_byte`
+    pub fn from_byte(coding_scheme_byte: u8) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|coding_scheme| coding_scheme.to_byte() == coding_scheme_byte)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "Unknown coding scheme {}",
+                    coding_scheme_byte
+                ))
+            })
+    }
+
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Repetition => "repetition",
+            Self::ReedSolomon => "rs",
+        }
+    }
+
+    /// Looks up a coding scheme by the name returned from `name`
+    pub fn from_name(coding_scheme_name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|coding_scheme| coding_scheme.name() == coding_scheme_name)
+    }
+
+    /// Length of a body holding `payload_length` bytes coded with this scheme
+    ///
+    /// Returns `None` if the length doesn't fit in `usize`.
+    pub fn body_length(self, payload_length: usize, repetition_factor: usize) -> Option<usize> {
+        match self {
+            Self::Repetition => payload_length.checked_mul(repetition_factor),
+            Self::ReedSolomon => reed_solomon::encoded_length(payload_length),
+        }
+    }
+}
+
+impl fmt::Display for CodingScheme {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// Order in which the carrier's blocks receive the bits following the frame header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockVisitation {
+    /// Left to right along each row, top row first
+    #[default]
+    RowMajor,
+    /// Top to bottom along each column, left column first
+    ColumnMajor,
+    /// Back and forth along the anti-diagonals, as in the JPEG coefficient scan
+    Zigzag,
+    /// A pseudo-random permutation of the blocks derived from the encryption key
+    Keyed,
+}
+
+impl BlockVisitation {
+    /// Every visitation order, in encoding order
+    pub const ALL: [BlockVisitation; 4] =
+        [Self::RowMajor, Self::ColumnMajor, Self::Zigzag, Self::Keyed];
+
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RowMajor => "row-major",
+            Self::ColumnMajor => "column-major",
+            Self::Zigzag => "zigzag",
+            Self::Keyed => "keyed",
+        }
+    }
+
+    /// Looks up a visitation order by the name returned from `name`
+    pub fn from_name(visitation_name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|block_visitation| block_visitation.name() == visitation_name)
+    }
+
+    /// Encodes the order as its two header bits
+    fn to_bits(self) -> u32 {
+        match self {
+            Self::RowMajor => 0,
+            Self::ColumnMajor => 1,
+            Self::Zigzag => 2,
+            Self::Keyed => 3,
+        }
+    }
+
+    /// Decodes two header bits written by `to_bits`
+    fn from_bits(visitation_bits: u32) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|block_visitation| block_visitation.to_bits() == visitation_bits)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "Unknown block visitation order {}",
+                    visitation_bits
+                ))
+            })
+    }
+}
+
+impl fmt::Display for BlockVisitation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// How the extent of the embedded payload is marked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// A frame header records the payload length and options
+    #[default]
+    LengthHeader,
+    /// Start and end markers delimit the payload, with no header for a bit error to corrupt
+    Sentinel,
+}
+
+impl Framing {
+    /// Every framing, in command-line order
+    pub const ALL: [Framing; 2] = [Self::LengthHeader, Self::Sentinel];
+
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::LengthHeader => "header",
+            Self::Sentinel => "sentinel",
+        }
+    }
+
+    /// Looks up a framing by the name returned from `name`
+    pub fn from_name(framing_name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|framing| framing.name() == framing_name)
+    }
+}
+
+impl fmt::Display for Framing {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// Metadata stored in the unencrypted header ahead of the error-corrected payload
+///
+/// Serialized as a 32-bit little-endian length whose top bits hold the compression flag,
+/// the carrier dimensions flag, the format version, the block visitation order, the
+/// passphrase salt flag and the authentication flag, then the content type byte and the
+/// coding scheme byte, whose top bit flags a plaintext checksum, and the 16-bit little-endian
+/// fingerprint of the embedding configuration, optionally followed by the carrier's width
+/// and height as 16-bit little-endian values. Version 2 frames omit the fingerprint. Version
+/// 1 frames also omit the coding scheme byte, so they are repetition coded without a
+/// checksum; legacy version 0 frames also omit the content type byte and are read as text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameHeader {
+    pub version: u8,
+    /// Length in bytes of the encrypted payload before error correction coding
+    pub payload_length: usize,
+    pub is_compressed: bool,
+    /// What the plaintext is, so extraction can present it without guessing
+    pub content_type: ContentType,
+    /// Order of the blocks holding the bits after the header
+    pub block_visitation: BlockVisitation,
+    /// Carrier width and height before it was padded up to a block multiple
+    pub original_dimensions: Option<(u16, u16)>,
+    /// Whether the encrypted payload starts with the salt of a passphrase-derived key
+    pub has_passphrase_salt: bool,
+    /// Whether the ciphertext carries a Poly1305 tag that decryption must verify
+    pub is_authenticated: bool,
+    /// Error-correcting code applied to the encrypted payload
+    pub coding_scheme: CodingScheme,
+    /// Whether the plaintext ends in a CRC32 that decryption must verify
+    pub has_plaintext_checksum: bool,
+    /// Fingerprint of the embedding parameters the frame was hidden with, if recorded
+    pub configuration_fingerprint: Option<u16>,
+}
+
+impl Default for FrameHeader {
+    fn default() -> Self {
+        Self {
+            version: FRAME_VERSION,
+            payload_length: 0,
+            is_compressed: false,
+            content_type: ContentType::default(),
+            block_visitation: BlockVisitation::default(),
+            original_dimensions: None,
+            has_passphrase_salt: false,
+            is_authenticated: false,
+            coding_scheme: CodingScheme::default(),
+            has_plaintext_checksum: false,
+            configuration_fingerprint: None,
+        }
+    }
+}
+
+impl FrameHeader {
+    /// Size of the serialized header in bytes
+    pub fn encoded_length(&self) -> usize {
+        let mut encoded_length = LENGTH_HEADER_SIZE
+            + self.content_type_length()
+            + self.coding_scheme_length()
+            + self.configuration_fingerprint_length();
+        if self.original_dimensions.is_some() {
+            encoded_length += CARRIER_DIMENSIONS_SIZE;
+        }
+        encoded_length
+    }
+
+    /// Size of the content type field, which legacy frames don't have
+    fn content_type_length(&self) -> usize {
+        if self.version == LEGACY_FRAME_VERSION {
+            0
+        } else {
+            CONTENT_TYPE_SIZE
+        }
+    }
+
+    /// Size of the coding scheme field, which frames before version 2 don't have
+    fn coding_scheme_length(&self) -> usize {
+        if self.version <= REPETITION_ONLY_FRAME_VERSION {
+            0
+        } else {
+            CODING_SCHEME_SIZE
+        }
+    }
+
+    /// Size of the configuration fingerprint field, which frames before version 3 don't have
+    fn configuration_fingerprint_length(&self) -> usize {
+        if self.version <= UNFINGERPRINTED_FRAME_VERSION {
+            0
+        } else {
+            CONFIGURATION_FINGERPRINT_SIZE
+        }
+    }
+
+    /// Returns whether frames of this header's version have a configuration fingerprint field
+    pub fn has_configuration_fingerprint_field(&self) -> bool {
+        self.configuration_fingerprint_length() > 0
+    }
+
+    /// Reads the configuration fingerprint field without validating the rest of the header
+    ///
+    /// The field sits at a fixed offset, so it can be checked even when a mismatched
+    /// configuration has read every other field, the version included, as garbage. Returns
+    /// `None` if the data is too short or the field holds zero, which means none was recorded.
+    pub fn read_configuration_fingerprint(encoded_data: &[u8]) -> Option<u16> {
+        let fingerprint_bytes = encoded_data
+            .get(CONFIGURATION_FINGERPRINT_OFFSET..)?
+            .first_chunk::<CONFIGURATION_FINGERPRINT_SIZE>()?;
+        Some(u16::from_le_bytes(*fingerprint_bytes)).filter(|&fingerprint| fingerprint != 0)
+    }
+
+    /// Size of the header plus the coded payload, repeated `repetition_factor` times if
+    /// repetition coded
+    ///
+    /// Returns `None` if the size doesn't fit in `usize`.
+    pub fn frame_length(&self, repetition_factor: usize) -> Option<usize> {
+        self.coding_scheme
+            .body_length(self.payload_length, repetition_factor)
+            .and_then(|body_length| body_length.checked_add(self.encoded_length()))
+    }
+
+    /// Serializes the header
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut header_bytes = Vec::with_capacity(self.encoded_length());
+        header_bytes.extend_from_slice(&self.encode_length_field()?.to_le_bytes());
+        if self.content_type_length() > 0 {
+            header_bytes.push(self.content_type.to_byte());
+        }
+        if self.coding_scheme_length() > 0 {
+            let mut coding_scheme_byte = self.coding_scheme.to_byte();
+            if self.has_plaintext_checksum {
+                coding_scheme_byte |= PLAINTEXT_CHECKSUM_FLAG;
+            }
+            header_bytes.push(coding_scheme_byte);
+        } else if self.has_plaintext_checksum {
+            return Err(SteganographyError::InvalidInput(format!(
+                "Version {} frames can't record a plaintext checksum",
+                self.version
+            )));
+        }
+
+        // An unrecorded fingerprint is written as zero, which no configuration hashes to
+        if self.configuration_fingerprint_length() > 0 {
+            header_bytes
+                .extend_from_slice(&self.configuration_fingerprint.unwrap_or(0).to_le_bytes());
+        } else if self.configuration_fingerprint.is_some() {
+            return Err(SteganographyError::InvalidInput(format!(
+                "Version {} frames can't record a configuration fingerprint",
+                self.version
+            )));
+        }
+
+        if let Some((original_width, original_height)) = self.original_dimensions {
+            header_bytes.extend_from_slice(&original_width.to_le_bytes());
+            header_bytes.extend_from_slice(&original_height.to_le_bytes());
+        }
+
+        Ok(header_bytes)
+    }
+
+    /// Parses a header from the start of `encoded_data`, ignoring anything after it
+    pub fn from_bytes(encoded_data: &[u8]) -> Result<Self> {
+        let length_header_bytes = encoded_data
+            .first_chunk::<LENGTH_HEADER_SIZE>()
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(
+                    "Encoded data too short for length header".to_string(),
+                )
+            })?;
+        let length_header = u32::from_le_bytes(*length_header_bytes);
+
+        let version = ((length_header & FRAME_VERSION_MASK) >> FRAME_VERSION_SHIFT) as u8;
+        if version > FRAME_VERSION {
+            return Err(SteganographyError::InvalidInput(format!(
+                "Unsupported frame version {}",
+                version
+            )));
+        }
+
+        let (content_type, coding_scheme_offset) = if version == LEGACY_FRAME_VERSION {
+            (ContentType::Text, LENGTH_HEADER_SIZE)
+        } else {
+            let content_type_byte = *encoded_data.get(LENGTH_HEADER_SIZE).ok_or_else(|| {
+                SteganographyError::InvalidInput(
+                    "Encoded data too short for content type".to_string(),
+                )
+            })?;
+            (
+                ContentType::from_byte(content_type_byte)?,
+                LENGTH_HEADER_SIZE + CONTENT_TYPE_SIZE,
+            )
+        };
+
+        let (coding_scheme_byte, dimensions_offset) = if version <= REPETITION_ONLY_FRAME_VERSION {
+            (CodingScheme::Repetition.to_byte(), coding_scheme_offset)
+        } else {
+            let coding_scheme_byte = *encoded_data.get(coding_scheme_offset).ok_or_else(|| {
+                SteganographyError::InvalidInput(
+                    "Encoded data too short for coding scheme".to_string(),
+                )
+            })?;
+            (
+                coding_scheme_byte,
+                coding_scheme_offset + CODING_SCHEME_SIZE,
+            )
+        };
+
+        let (configuration_fingerprint, dimensions_offset) =
+            if version <= UNFINGERPRINTED_FRAME_VERSION {
+                (None, dimensions_offset)
+            } else {
+                if encoded_data.len() < dimensions_offset + CONFIGURATION_FINGERPRINT_SIZE {
+                    return Err(SteganographyError::InvalidInput(
+                        "Encoded data too short for configuration fingerprint".to_string(),
+                    ));
+                }
+                (
+                    Self::read_configuration_fingerprint(encoded_data),
+                    dimensions_offset + CONFIGURATION_FINGERPRINT_SIZE,
+                )
+            };
+
+        let original_dimensions = if length_header & CARRIER_DIMENSIONS_FLAG != 0 {
+            let dimension_bytes = encoded_data
+                .get(dimensions_offset..dimensions_offset + CARRIER_DIMENSIONS_SIZE)
+                .ok_or_else(|| {
+                    SteganographyError::InvalidInput(
+                        "Encoded data too short for carrier dimensions".to_string(),
+                    )
+                })?;
+            Some((
+                u16::from_le_bytes([dimension_bytes[0], dimension_bytes[1]]),
+                u16::from_le_bytes([dimension_bytes[2], dimension_bytes[3]]),
+            ))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            version,
+            payload_length: (length_header & !FRAME_FLAG_MASK) as usize,
+            is_compressed: length_header & COMPRESSED_PAYLOAD_FLAG != 0,
+            content_type,
+            block_visitation: BlockVisitation::from_bits(
+                (length_header & BLOCK_VISITATION_MASK) >> BLOCK_VISITATION_SHIFT,
+            )?,
+            original_dimensions,
+            has_passphrase_salt: length_header & PASSPHRASE_SALT_FLAG != 0,
+            is_authenticated: length_header & AUTHENTICATED_PAYLOAD_FLAG != 0,
+            coding_scheme: CodingScheme::from_byte(coding_scheme_byte & !PLAINTEXT_CHECKSUM_FLAG)?,
+            has_plaintext_checksum: coding_scheme_byte & PLAINTEXT_CHECKSUM_FLAG != 0,
+            configuration_fingerprint,
+        })
+    }
+
+    /// Returns a payload length as the low bits of the length field, failing if it needs
+    /// bits reserved for flags
+    fn checked_length_bits(payload_length: u64) -> Result<u32> {
+        if payload_length > MAX_FRAMED_PAYLOAD_LENGTH {
+            return Err(SteganographyError::InvalidInput(format!(
+                "Payload of {} bytes exceeds the {} byte limit of the frame header",
+                payload_length, MAX_FRAMED_PAYLOAD_LENGTH
+            )));
+        }
+        Ok(payload_length as u32)
+    }
+
+    /// Packs the payload length, flags and version into the 32-bit length field
+    ///
+    /// The length is checked in 64-bit arithmetic so oversized payloads are rejected rather
+    /// than silently truncated into a smaller, valid-looking length.
+    fn encode_length_field(&self) -> Result<u32> {
+        let mut length_field = Self::checked_length_bits(self.payload_length as u64)?;
+        length_field |= (u32::from(self.version) << FRAME_VERSION_SHIFT) & FRAME_VERSION_MASK;
+        length_field |= self.block_visitation.to_bits() << BLOCK_VISITATION_SHIFT;
+        if self.is_compressed {
+            length_field |= COMPRESSED_PAYLOAD_FLAG;
+        }
+        if self.original_dimensions.is_some() {
+            length_field |= CARRIER_DIMENSIONS_FLAG;
+        }
+        if self.has_passphrase_salt {
+            length_field |= PASSPHRASE_SALT_FLAG;
+        }
+        if self.is_authenticated {
+            length_field |= AUTHENTICATED_PAYLOAD_FLAG;
+        }
+        Ok(length_field)
+    }
+}
+
+/// A complete frame: the header followed by the error-corrected body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payload {
+    pub header: FrameHeader,
+    /// Encrypted payload after error correction coding
+    pub body: Vec<u8>,
+}
+
+impl Payload {
+    /// Serializes the header followed by the body
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut frame_bytes = self.header.to_bytes()?;
+        frame_bytes.extend_from_slice(&self.body);
+        Ok(frame_bytes)
+    }
+
+    /// Parses a frame, whose body was repeated `repetition_factor` times if repetition coded
+    ///
+    /// The header is the single authoritative length; anything past the frame is padding.
+    pub fn from_bytes(encoded_data: &[u8], repetition_factor: usize) -> Result<Self> {
+        let header = FrameHeader::from_bytes(encoded_data)?;
+        let frame_length = Self::checked_frame_length(&header, repetition_factor, encoded_data)?;
+
+        Ok(Self {
+            header,
+            body: encoded_data[header.encoded_length()..frame_length].to_vec(),
+        })
+    }
+
+    /// Calculates the frame length from its header, checking it fits in `encoded_data`
+    pub fn encoded_frame_length(encoded_data: &[u8], repetition_factor: usize) -> Result<usize> {
+        let header = FrameHeader::from_bytes(encoded_data)?;
+        Self::checked_frame_length(&header, repetition_factor, encoded_data)
+    }
+
+    /// Returns the frame length if the header's claimed length is available in `encoded_data`
+    ///
+    /// Every real payload holds at least a nonce, so a header claiming zero bytes is what
+    /// reading an image without hidden data usually produces.
+    fn checked_frame_length(
+        header: &FrameHeader,
+        repetition_factor: usize,
+        encoded_data: &[u8],
+    ) -> Result<usize> {
+        if header.payload_length == 0 {
+            return Err(SteganographyError::InvalidInput(
+                "no valid payload header found: the header claims an empty payload".to_string(),
+            ));
+        }
+
+        header
+            .frame_length(repetition_factor)
+            .filter(|&frame_length| frame_length <= encoded_data.len())
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "no valid payload header found: the header claims {} bytes but only {} are available",
+                    header.payload_length,
+                    encoded_data.len()
+                ))
+            })
+    }
+}
+
+/// Place of one part of a payload split across several carriers
+///
+/// Serialized as the 16-bit little-endian part index and part count, then the 32-bit
+/// little-endian length of the chunk of the encrypted stream that follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartHeader {
+    /// Zero-based position of this part's chunk in the encrypted stream
+    pub part_index: u16,
+    /// Number of carriers the stream was split across
+    pub part_count: u16,
+    /// Length in bytes of the chunk following the header
+    pub chunk_length: u32,
+}
+
+impl PartHeader {
+    /// Serializes the header
+    pub fn to_bytes(&self) -> [u8; PART_HEADER_SIZE] {
+        let mut header_bytes = [0u8; PART_HEADER_SIZE];
+        header_bytes[..2].copy_from_slice(&self.part_index.to_le_bytes());
+        header_bytes[2..4].copy_from_slice(&self.part_count.to_le_bytes());
+        header_bytes[4..].copy_from_slice(&self.chunk_length.to_le_bytes());
+        header_bytes
+    }
+
+    /// Parses a part header, rejecting ones whose index doesn't fall within the part count
+    pub fn from_bytes(encoded_data: &[u8]) -> Result<Self> {
+        let header_bytes = encoded_data
+            .first_chunk::<PART_HEADER_SIZE>()
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(
+                    "no split payload part found: too little data for a part header".to_string(),
+                )
+            })?;
+        let part_header = Self {
+            part_index: u16::from_le_bytes([header_bytes[0], header_bytes[1]]),
+            part_count: u16::from_le_bytes([header_bytes[2], header_bytes[3]]),
+            chunk_length: u32::from_le_bytes([
+                header_bytes[4],
+                header_bytes[5],
+                header_bytes[6],
+                header_bytes[7],
+            ]),
+        };
+        if part_header.part_index >= part_header.part_count {
+            return Err(SteganographyError::InvalidInput(format!(
+                "no split payload part found: part index {} is outside a split into {} parts",
+                part_header.part_index, part_header.part_count
+            )));
+        }
+        Ok(part_header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_roundtrip_ignores_trailing_padding() {
+        let payload = Payload {
+            header: FrameHeader {
+                version: FRAME_VERSION,
+                payload_length: 2,
+                is_compressed: true,
+                content_type: ContentType::Gzip,
+                block_visitation: BlockVisitation::ColumnMajor,
+                original_dimensions: Some((301, 203)),
+                has_passphrase_salt: true,
+                is_authenticated: true,
+                coding_scheme: CodingScheme::Repetition,
+                has_plaintext_checksum: true,
+                configuration_fingerprint: Some(0xBEEF),
+            },
+            body: vec![0x11, 0x11, 0x11, 0x22, 0x22, 0x22],
+        };
+
+        let mut frame_bytes = payload.to_bytes().unwrap();
+        assert_eq!(frame_bytes.len(), 12 + 6);
+        frame_bytes.extend_from_slice(&[0xAA; 5]);
+
+        assert_eq!(Payload::from_bytes(&frame_bytes, 3).unwrap(), payload);
+        assert_eq!(Payload::encoded_frame_length(&frame_bytes, 3).unwrap(), 18);
+        assert!(Payload::from_bytes(&frame_bytes[..13], 3).is_err());
+    }
+
+    #[test]
+    fn test_configuration_fingerprint_is_read_ahead_of_the_other_fields() {
+        let fingerprinted_header = FrameHeader {
+            payload_length: 4,
+            configuration_fingerprint: Some(0x1234),
+            ..FrameHeader::default()
+        };
+        let mut header_bytes = fingerprinted_header.to_bytes().unwrap();
+        assert_eq!(header_bytes.len(), 8);
+        assert_eq!(
+            FrameHeader::from_bytes(&header_bytes).unwrap(),
+            fingerprinted_header
+        );
+
+        // Found even when the content type is garbage, as a mismatched configuration reads it
+        header_bytes[LENGTH_HEADER_SIZE] = 0xFF;
+        assert!(FrameHeader::from_bytes(&header_bytes).is_err());
+        assert_eq!(
+            FrameHeader::read_configuration_fingerprint(&header_bytes),
+            Some(0x1234)
+        );
+
+        let unrecorded_bytes = FrameHeader::default().to_bytes().unwrap();
+        assert_eq!(
+            FrameHeader::read_configuration_fingerprint(&unrecorded_bytes),
+            None
+        );
+
+        // Version 2 frames have no fingerprint field and still read back
+        let version_two_header = FrameHeader {
+            version: UNFINGERPRINTED_FRAME_VERSION,
+            payload_length: 4,
+            ..FrameHeader::default()
+        };
+        let version_two_bytes = version_two_header.to_bytes().unwrap();
+        assert_eq!(version_two_bytes.len(), 6);
+        assert_eq!(
+            FrameHeader::from_bytes(&version_two_bytes).unwrap(),
+            version_two_header
+        );
+        assert!(!version_two_header.has_configuration_fingerprint_field());
+        assert!(FrameHeader {
+            configuration_fingerprint: Some(0x1234),
+            ..version_two_header
+        }
+        .to_bytes()
+        .is_err());
+    }
+
+    #[test]
+    fn test_coding_scheme_roundtrips_through_header() {
+        for coding_scheme in CodingScheme::ALL {
+            assert_eq!(
+                CodingScheme::from_name(coding_scheme.name()),
+                Some(coding_scheme)
+            );
+
+            for has_plaintext_checksum in [false, true] {
+                let frame_header = FrameHeader {
+                    payload_length: 40,
+                    coding_scheme,
+                    has_plaintext_checksum,
+                    ..FrameHeader::default()
+                };
+                let header_bytes = frame_header.to_bytes().unwrap();
+                assert_eq!(header_bytes.len(), frame_header.encoded_length());
+                assert_eq!(
+                    FrameHeader::from_bytes(&header_bytes).unwrap(),
+                    frame_header
+                );
+            }
+        }
+
+        let reed_solomon_header = FrameHeader {
+            payload_length: 40,
+            coding_scheme: CodingScheme::ReedSolomon,
+            ..FrameHeader::default()
+        };
+        assert_eq!(reed_solomon_header.frame_length(5), Some(8 + 40 + 32));
+        assert!(CodingScheme::from_byte(CodingScheme::ALL.len() as u8).is_err());
+    }
+
+    #[test]
+    fn test_version_one_frame_reads_as_repetition_coded() {
+        let version_one_header = FrameHeader {
+            version: REPETITION_ONLY_FRAME_VERSION,
+            payload_length: 3,
+            content_type: ContentType::Binary,
+            ..FrameHeader::default()
+        };
+        let header_bytes = version_one_header.to_bytes().unwrap();
+        assert_eq!(header_bytes.len(), LENGTH_HEADER_SIZE + CONTENT_TYPE_SIZE);
+
+        let parsed_header = FrameHeader::from_bytes(&header_bytes).unwrap();
+        assert_eq!(parsed_header, version_one_header);
+        assert_eq!(parsed_header.coding_scheme, CodingScheme::Repetition);
+        assert_eq!(parsed_header.frame_length(5), Some(5 + 15));
+
+        // Without a coding scheme byte there is nowhere to flag a checksum
+        assert!(FrameHeader {
+            has_plaintext_checksum: true,
+            ..version_one_header
+        }
+        .to_bytes()
+        .is_err());
+    }
+
+    #[test]
+    fn test_content_type_roundtrips_through_header() {
+        for content_type in ContentType::ALL {
+            assert_eq!(
+                ContentType::from_byte(content_type.to_byte()).unwrap(),
+                content_type
+            );
+            assert_eq!(
+                ContentType::from_name(content_type.name()),
+                Some(content_type)
+            );
+
+            let frame_header = FrameHeader {
+                payload_length: 7,
+                content_type,
+                ..FrameHeader::default()
+            };
+            let header_bytes = frame_header.to_bytes().unwrap();
+            assert_eq!(header_bytes.len(), frame_header.encoded_length());
+            assert_eq!(
+                FrameHeader::from_bytes(&header_bytes).unwrap(),
+                frame_header
+            );
+        }
+
+        assert!(ContentType::from_byte(ContentType::ALL.len() as u8).is_err());
+    }
+
+    #[test]
+    fn test_block_visitation_roundtrips_through_length_field() {
+        for block_visitation in BlockVisitation::ALL {
+            assert_eq!(
+                BlockVisitation::from_name(block_visitation.name()),
+                Some(block_visitation)
+            );
+
+            let frame_header = FrameHeader {
+                payload_length: 300,
+                is_compressed: true,
+                block_visitation,
+                ..FrameHeader::default()
+            };
+            let parsed_header = FrameHeader::from_bytes(&frame_header.to_bytes().unwrap()).unwrap();
+            assert_eq!(parsed_header, frame_header);
+        }
+    }
+
+    #[test]
+    fn test_legacy_frame_without_content_type_reads_as_text() {
+        let legacy_header = FrameHeader {
+            version: LEGACY_FRAME_VERSION,
+            payload_length: 3,
+            original_dimensions: Some((40, 30)),
+            ..FrameHeader::default()
+        };
+        let header_bytes = legacy_header.to_bytes().unwrap();
+        assert_eq!(
+            header_bytes.len(),
+            LENGTH_HEADER_SIZE + CARRIER_DIMENSIONS_SIZE
+        );
+
+        let parsed_header = FrameHeader::from_bytes(&header_bytes).unwrap();
+        assert_eq!(parsed_header, legacy_header);
+        assert_eq!(parsed_header.content_type, ContentType::Text);
+    }
+
+    #[test]
+    fn test_length_field_rejects_payloads_beyond_header_range() {
+        let frame_header = |payload_length: u64| FrameHeader {
+            payload_length: payload_length as usize,
+            is_compressed: true,
+            ..FrameHeader::default()
+        };
+
+        assert_eq!(
+            frame_header(MAX_FRAMED_PAYLOAD_LENGTH)
+                .encode_length_field()
+                .unwrap(),
+            MAX_FRAMED_PAYLOAD_LENGTH as u32
+                | COMPRESSED_PAYLOAD_FLAG
+                | u32::from(FRAME_VERSION) << FRAME_VERSION_SHIFT
+        );
+        for oversized_length in [
+            MAX_FRAMED_PAYLOAD_LENGTH + 1,
+            u32::MAX as u64,
+            u32::MAX as u64 + 1,
+            u32::MAX as u64 + 17,
+            u64::MAX,
+        ] {
+            assert!(matches!(
+                FrameHeader::checked_length_bits(oversized_length),
+                Err(SteganographyError::InvalidInput(_))
+            ));
+        }
+        assert!(matches!(
+            frame_header(MAX_FRAMED_PAYLOAD_LENGTH + 1).encode_length_field(),
+            Err(SteganographyError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_part_header_roundtrips_and_rejects_out_of_range_indices() {
+        let part_header = PartHeader {
+            part_index: 2,
+            part_count: 3,
+            chunk_length: 70_000,
+        };
+        assert_eq!(
+            PartHeader::from_bytes(&part_header.to_bytes()).unwrap(),
+            part_header
+        );
+
+        let out_of_range = PartHeader {
+            part_index: 3,
+            ..part_header
+        };
+        assert!(PartHeader::from_bytes(&out_of_range.to_bytes()).is_err());
+        assert!(PartHeader::from_bytes(&[0u8; PART_HEADER_SIZE]).is_err());
+        assert!(PartHeader::from_bytes(&[1, 0, 2]).is_err());
+    }
+}
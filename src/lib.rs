@@ -1,7 +1,236 @@
+pub mod carrier;
 pub mod cli;
+pub mod compression;
 pub mod crypto;
 pub mod dct;
+pub mod embedding;
 pub mod error;
+pub mod frame;
+pub mod output;
+pub mod reed_solomon;
 pub mod steganography;
 
-pub use error::{Result, SteganographyError};
+pub use error::{ErrorContext, Result, SteganographyError};
+
+use crypto::{CryptographicEngine, FrameOptions};
+use frame::ContentType;
+use image::RgbImage;
+use steganography::{ExtractionResult, SteganographyEngine};
+
+/// Encrypts `plaintext` with `key` and hides it in a copy of `cover`
+///
+/// This does what `steg hide` does with its defaults: ChaCha20 encryption, 5x repetition
+/// coding and one bit per 8x8 luminance block, with the strength tuned to survive JPEG
+/// compression at `quality`. Save the result as a JPEG at that quality, or losslessly.
+/// The payload is recorded as text when it is valid UTF-8 and as binary otherwise, so
+/// `steg extract` can read it too.
+pub fn hide(cover: &RgbImage, plaintext: &[u8], key: &[u8; 32], quality: u8) -> Result<RgbImage> {
+    let steganography_engine = SteganographyEngine::new();
+    let encrypted_data = encrypt_for_hiding(&steganography_engine, plaintext, key, quality)?;
+    steganography_engine.hide_data_in_rgb_image(cover, &encrypted_data, quality)
+}
+
+/// Encrypts `plaintext` with `key` and hides it split across copies of several `covers`
+///
+/// Works like [`hide`], but each cover carries a share of the encrypted stream in proportion
+/// to its capacity, with a header recording the share's part index and the part count.
+/// Every returned image is needed to [`extract_split`] the payload.
+pub fn hide_split(
+    covers: &[RgbImage],
+    plaintext: &[u8],
+    key: &[u8; 32],
+    quality: u8,
+) -> Result<Vec<RgbImage>> {
+    let steganography_engine = SteganographyEngine::new();
+    let encrypted_data = encrypt_for_hiding(&steganography_engine, plaintext, key, quality)?;
+    steganography_engine.hide_data_across_rgb_images(covers, &encrypted_data, quality)
+}
+
+/// Frames and encrypts `plaintext` the way [`hide`] and [`hide_split`] embed it
+fn encrypt_for_hiding(
+    steganography_engine: &SteganographyEngine,
+    plaintext: &[u8],
+    key: &[u8; 32],
+    quality: u8,
+) -> Result<Vec<u8>> {
+    if !(1..=100).contains(&quality) {
+        return Err(SteganographyError::InvalidInput(
+            "JPEG quality must be between 1 and 100".to_string(),
+        ));
+    }
+
+    let content_type = if std::str::from_utf8(plaintext).is_ok() {
+        ContentType::Text
+    } else {
+        ContentType::Binary
+    };
+    CryptographicEngine::new().encrypt_with_frame_options(
+        key,
+        plaintext,
+        &FrameOptions {
+            content_type,
+            configuration_fingerprint: Some(steganography_engine.configuration_fingerprint()),
+            ..FrameOptions::default()
+        },
+    )
+}
+
+/// Hides `plaintext` under a freshly generated key and returns the key with the image
+///
+/// The key exists only in memory; nothing is written to disk. Keep it to [`extract`] the
+/// payload later, or save it with [`CryptographicEngine::save_key_to_file`] if a key file
+/// is wanted after all.
+pub fn hide_with_new_key(
+    cover: &RgbImage,
+    plaintext: &[u8],
+    quality: u8,
+) -> Result<(RgbImage, [u8; 32])> {
+    let key = CryptographicEngine::generate_encryption_key();
+    let stego = hide(cover, plaintext, &key, quality)?;
+    Ok((stego, key))
+}
+
+/// Recovers and decrypts a payload hidden by [`hide`] or by `steg hide` with its defaults
+///
+/// `stego` may have been JPEG-compressed since; each bit's reliability drives the
+/// repetition decoding.
+pub fn extract(stego: &RgbImage, key: &[u8; 32]) -> Result<Vec<u8>> {
+    let steganography_engine = SteganographyEngine::new();
+    let extraction_result = steganography_engine
+        .extract_frame_from_rgb_image(stego, CryptographicEngine::new().repetition_factor())?;
+    decrypt_extraction(&steganography_engine, &extraction_result, key)
+}
+
+/// Reassembles and decrypts a payload hidden by [`hide_split`]
+///
+/// `stegos` may come in any order. If one of the parts is missing, the error names its
+/// part index.
+pub fn extract_split(stegos: &[RgbImage], key: &[u8; 32]) -> Result<Vec<u8>> {
+    let steganography_engine = SteganographyEngine::new();
+    let extraction_result = steganography_engine.extract_data_from_rgb_images(stegos)?;
+    decrypt_extraction(&steganography_engine, &extraction_result, key)
+}
+
+/// Decrypts an extracted frame, first checking it was embedded with the default parameters
+fn decrypt_extraction(
+    steganography_engine: &SteganographyEngine,
+    extraction_result: &ExtractionResult,
+    key: &[u8; 32],
+) -> Result<Vec<u8>> {
+    let crypto_engine = CryptographicEngine::new();
+    crypto_engine.require_configuration_fingerprint(
+        &extraction_result.data,
+        steganography_engine.configuration_fingerprint(),
+    )?;
+    crypto_engine.decrypt_with_bit_reliability(
+        key,
+        &extraction_result.data,
+        &extraction_result.bit_confidences,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_hide_and_extract_roundtrip_through_jpeg() {
+        let cover = RgbImage::from_fn(384, 384, |x, y| {
+            Rgb([(x / 3) as u8 + 40, (y / 3) as u8 + 40, 120])
+        });
+        let key = [0x42; 32];
+        let plaintext = b"library round trip";
+
+        let stego = hide(&cover, plaintext, &key, 85).unwrap();
+        let jpeg_buffer = SteganographyEngine::new()
+            .encode_rgb_image_as_jpeg(&stego, 85)
+            .unwrap();
+        let recompressed = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+
+        assert_eq!(extract(&recompressed, &key).unwrap(), plaintext);
+
+        assert_ne!(
+            extract(&recompressed, &[0x43; 32]).ok().as_deref(),
+            Some(&plaintext[..])
+        );
+        assert!(matches!(
+            hide(&cover, plaintext, &key, 0),
+            Err(SteganographyError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_hide_with_new_key_returns_a_key_that_extracts() {
+        let cover = RgbImage::from_fn(384, 384, |x, y| {
+            Rgb([(x / 3) as u8 + 40, (y / 3) as u8 + 40, 120])
+        });
+        let plaintext = b"library round trip";
+
+        let (stego, key) = hide_with_new_key(&cover, plaintext, 85).unwrap();
+        let (second_stego, second_key) = hide_with_new_key(&cover, plaintext, 85).unwrap();
+
+        assert_ne!(key, second_key);
+        assert_eq!(extract(&stego, &key).unwrap(), plaintext);
+        assert_eq!(extract(&second_stego, &second_key).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_extract_from_clean_image_reports_missing_header() {
+        let cover = RgbImage::from_fn(256, 256, |x, y| {
+            Rgb([(x / 3) as u8 + 40, (y / 2) as u8 + 40, 120])
+        });
+
+        match extract(&cover, &[0x42; 32]) {
+            Err(SteganographyError::InvalidInput(message)) => {
+                assert!(
+                    message.starts_with("no valid payload header found"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("expected a missing header error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_hide_split_and_extract_split_roundtrip_through_jpeg() {
+        let covers: Vec<RgbImage> = (0..3)
+            .map(|cover_index| {
+                RgbImage::from_fn(256, 256, |x, y| {
+                    Rgb([
+                        (x / 3) as u8 + 40,
+                        (y / 3) as u8 + 40,
+                        100 + cover_index * 20,
+                    ])
+                })
+            })
+            .collect();
+        let key = [0x42; 32];
+        let plaintext = b"split across three covers";
+
+        let stegos: Vec<RgbImage> = hide_split(&covers, plaintext, &key, 85)
+            .unwrap()
+            .iter()
+            .map(|stego| {
+                let jpeg_buffer = SteganographyEngine::new()
+                    .encode_rgb_image_as_jpeg(stego, 85)
+                    .unwrap();
+                image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8()
+            })
+            .collect();
+
+        let reversed: Vec<RgbImage> = stegos.iter().rev().cloned().collect();
+        assert_eq!(extract_split(&reversed, &key).unwrap(), plaintext);
+        match extract_split(&[stegos[0].clone(), stegos[2].clone()], &key) {
+            Err(SteganographyError::InvalidInput(message)) => {
+                assert!(
+                    message.contains("part index 1 of 3 is missing"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("expected a missing part error, got {:?}", other),
+        }
+    }
+}
@@ -1,12 +1,17 @@
 use clap::Parser;
 use steg::cli::{CommandLineHandler, CommandLineInterface};
-use steg::Result;
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let command_line_interface = CommandLineInterface::parse();
     let mut cli_handler = CommandLineHandler::new();
 
-    cli_handler.process_command(command_line_interface)?;
-
-    Ok(())
+    // Print the readable message rather than the debug form so hints reach the user
+    match cli_handler.process_command(command_line_interface) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
 }
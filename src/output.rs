@@ -0,0 +1,213 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether progress messages go to stderr, leaving stdout to a machine-readable result
+static DIAGNOSTICS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Whether long block loops draw a progress bar on stderr
+static PROGRESS_BARS: AtomicBool = AtomicBool::new(false);
+
+/// Sends progress and diagnostic messages to stderr instead of stdout
+pub fn route_diagnostics_to_stderr(to_stderr: bool) {
+    DIAGNOSTICS_TO_STDERR.store(to_stderr, Ordering::Relaxed);
+}
+
+/// Returns whether progress and diagnostic messages currently go to stderr
+pub fn diagnostics_to_stderr() -> bool {
+    DIAGNOSTICS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Prints a progress or diagnostic line to stdout, or to stderr once `--json` claims stdout
+macro_rules! diagnostic {
+    ($($argument:tt)*) => {
+        if $crate::output::diagnostics_to_stderr() {
+            eprintln!($($argument)*)
+        } else {
+            println!($($argument)*)
+        }
+    };
+}
+pub(crate) use diagnostic;
+
+/// Turns the progress bars of long block loops on or off
+pub fn show_progress_bars(show: bool) {
+    PROGRESS_BARS.store(show, Ordering::Relaxed);
+}
+
+/// Returns whether this build can draw progress bars, which needs the `progress` feature
+pub fn progress_bars_supported() -> bool {
+    cfg!(feature = "progress")
+}
+
+/// Progress of a loop over image blocks, drawn on stderr when progress bars are on
+///
+/// The bar is cleared when the loop ends, before any further diagnostics are printed, and
+/// stays hidden when stderr is not a terminal.
+pub(crate) struct BlockProgress {
+    #[cfg(feature = "progress")]
+    progress_bar: Option<indicatif::ProgressBar>,
+}
+
+impl BlockProgress {
+    /// Starts a bar for `block_count` blocks labelled with what the loop is doing
+    #[cfg_attr(not(feature = "progress"), allow(unused_variables))]
+    pub(crate) fn start(block_count: usize, label: &'static str) -> Self {
+        #[cfg(feature = "progress")]
+        {
+            let progress_bar = PROGRESS_BARS.load(Ordering::Relaxed).then(|| {
+                let progress_bar = indicatif::ProgressBar::new(block_count as u64);
+                progress_bar.set_style(
+                    indicatif::ProgressStyle::with_template(
+                        "{msg} [{bar:40}] {pos}/{len} blocks ({eta})",
+                    )
+                    .unwrap()
+                    .progress_chars("=> "),
+                );
+                progress_bar.set_message(label);
+                progress_bar
+            });
+            Self { progress_bar }
+        }
+        #[cfg(not(feature = "progress"))]
+        Self {}
+    }
+
+    /// Records one more finished block; safe to call from several threads at once
+    pub(crate) fn advance(&self) {
+        #[cfg(feature = "progress")]
+        if let Some(progress_bar) = &self.progress_bar {
+            progress_bar.inc(1);
+        }
+    }
+}
+
+#[cfg(feature = "progress")]
+impl Drop for BlockProgress {
+    fn drop(&mut self) {
+        if let Some(progress_bar) = &self.progress_bar {
+            progress_bar.finish_and_clear();
+        }
+    }
+}
+
+/// Text form `extract --raw` prints the decrypted bytes in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RawEncoding {
+    /// Offset, hex and printable ASCII columns, 16 bytes per line
+    #[default]
+    Hex,
+    /// Standard base64 on a single line
+    Base64,
+}
+
+impl RawEncoding {
+    /// Looks up an encoding by its command-line name, `hex` or `base64`
+    pub fn from_name(encoding_name: &str) -> Option<Self> {
+        match encoding_name {
+            "hex" => Some(Self::Hex),
+            "base64" => Some(Self::Base64),
+            _ => None,
+        }
+    }
+}
+
+/// Value of one field in a JSON result object
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Number(f64),
+    String(String),
+}
+
+impl From<usize> for JsonValue {
+    fn from(value: usize) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// Serializes fields as a single-line JSON object, in the order given
+///
+/// Numbers JSON can't represent, such as the infinite PSNR of an unchanged image, are
+/// written as `null`.
+pub fn json_object(fields: &[(&str, JsonValue)]) -> String {
+    let mut json_text = String::from("{");
+    for (field_index, (field_name, field_value)) in fields.iter().enumerate() {
+        if field_index > 0 {
+            json_text.push(',');
+        }
+        write_json_string(&mut json_text, field_name);
+        json_text.push(':');
+        match field_value {
+            JsonValue::Number(number) if number.is_finite() => {
+                write!(json_text, "{}", number).unwrap();
+            }
+            JsonValue::Number(_) | JsonValue::Null => json_text.push_str("null"),
+            JsonValue::String(text) => write_json_string(&mut json_text, text),
+        }
+    }
+    json_text.push('}');
+    json_text
+}
+
+/// Appends `text` as a quoted JSON string, escaping quotes, backslashes and control characters
+fn write_json_string(json_text: &mut String, text: &str) {
+    json_text.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => json_text.push_str("\\\""),
+            '\\' => json_text.push_str("\\\\"),
+            '\n' => json_text.push_str("\\n"),
+            '\r' => json_text.push_str("\\r"),
+            '\t' => json_text.push_str("\\t"),
+            control if control.is_control() => {
+                write!(json_text, "\\u{:04x}", control as u32).unwrap();
+            }
+            character => json_text.push(character),
+        }
+    }
+    json_text.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_object_escapes_strings_and_nulls_non_finite_numbers() {
+        let json_text = json_object(&[
+            ("message", "say \"hi\"\n\u{1}".into()),
+            ("bytes_extracted", 12usize.into()),
+            ("psnr", f64::INFINITY.into()),
+            ("key_path", None::<String>.into()),
+            ("ratio", 0.5.into()),
+        ]);
+        assert_eq!(
+            json_text,
+            r#"{"message":"say \"hi\"\n\u0001","bytes_extracted":12,"psnr":null,"key_path":null,"ratio":0.5}"#
+        );
+    }
+}
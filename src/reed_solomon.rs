@@ -0,0 +1,352 @@
+use crate::error::{Result, SteganographyError};
+
+/// Parity bytes appended to each block; up to half as many corrupted bytes are corrected
+pub const PARITY_BYTES: usize = 32;
+
+/// Longest codeword over GF(256), data and parity together
+const CODEWORD_LENGTH: usize = 255;
+
+/// Data bytes carried by each full block
+const BLOCK_DATA_LENGTH: usize = CODEWORD_LENGTH - PARITY_BYTES;
+
+/// Primitive polynomial x^8 + x^4 + x^3 + x^2 + 1 generating GF(256)
+const PRIMITIVE_POLYNOMIAL: u16 = 0x11d;
+
+/// Powers of the generator element, doubled so products of two logs need no reduction
+const GF_EXP: [u8; 512] = build_exp_table();
+
+/// Discrete logarithm of each nonzero field element
+const GF_LOG: [u8; 256] = build_log_table();
+
+/// Builds `GF_EXP` by repeatedly multiplying by the generator element
+const fn build_exp_table() -> [u8; 512] {
+    let mut exp_table = [0u8; 512];
+    let mut element: u16 = 1;
+    let mut power = 0;
+    while power < 255 {
+        exp_table[power] = element as u8;
+        exp_table[power + 255] = element as u8;
+        element <<= 1;
+        if element & 0x100 != 0 {
+            element ^= PRIMITIVE_POLYNOMIAL;
+        }
+        power += 1;
+    }
+    exp_table
+}
+
+/// Builds `GF_LOG` by inverting `GF_EXP`
+const fn build_log_table() -> [u8; 256] {
+    let mut log_table = [0u8; 256];
+    let mut power = 0;
+    while power < 255 {
+        log_table[GF_EXP[power] as usize] = power as u8;
+        power += 1;
+    }
+    log_table
+}
+
+/// Multiplies two field elements by adding their logarithms
+fn gf_multiply(left: u8, right: u8) -> u8 {
+    if left == 0 || right == 0 {
+        return 0;
+    }
+    GF_EXP[GF_LOG[left as usize] as usize + GF_LOG[right as usize] as usize]
+}
+
+/// Divides two field elements; `divisor` must be nonzero
+fn gf_divide(dividend: u8, divisor: u8) -> u8 {
+    if dividend == 0 {
+        return 0;
+    }
+    GF_EXP[GF_LOG[dividend as usize] as usize + 255 - GF_LOG[divisor as usize] as usize]
+}
+
+/// The generator element raised to `power`, which may be any non-negative integer
+fn gf_power(power: usize) -> u8 {
+    GF_EXP[power % 255]
+}
+
+/// Evaluates a polynomial stored lowest degree first at `point`
+fn evaluate_polynomial(coefficients: &[u8], point: u8) -> u8 {
+    coefficients.iter().rev().fold(0, |value, &coefficient| {
+        gf_multiply(value, point) ^ coefficient
+    })
+}
+
+/// Generator polynomial with roots α^0 to α^(PARITY_BYTES - 1), highest degree first
+fn generator_polynomial() -> Vec<u8> {
+    let mut generator = vec![1u8];
+    for root_power in 0..PARITY_BYTES {
+        let root = gf_power(root_power);
+        let mut product = vec![0u8; generator.len() + 1];
+        for (coefficient_index, &coefficient) in generator.iter().enumerate() {
+            product[coefficient_index] ^= coefficient;
+            product[coefficient_index + 1] ^= gf_multiply(coefficient, root);
+        }
+        generator = product;
+    }
+    generator
+}
+
+/// Length of `data_length` bytes once split into blocks and given parity
+///
+/// Returns `None` if the length doesn't fit in `usize`.
+pub fn encoded_length(data_length: usize) -> Option<usize> {
+    data_length
+        .div_ceil(BLOCK_DATA_LENGTH)
+        .checked_mul(PARITY_BYTES)
+        .and_then(|parity_length| parity_length.checked_add(data_length))
+}
+
+/// Most data bytes whose encoding fits in `available_bytes`
+pub fn max_data_length(available_bytes: usize) -> usize {
+    let full_blocks = available_bytes / CODEWORD_LENGTH;
+    let partial_block = available_bytes % CODEWORD_LENGTH;
+    full_blocks * BLOCK_DATA_LENGTH + partial_block.saturating_sub(PARITY_BYTES)
+}
+
+/// Splits data into blocks of up to 223 bytes and appends 32 parity bytes to each
+///
+/// The last block is shortened rather than padded, so short payloads pay only for their
+/// own length plus one block of parity.
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let generator = generator_polynomial();
+    let mut encoded_data = Vec::with_capacity(encoded_length(data.len()).unwrap_or_default());
+
+    for data_block in data.chunks(BLOCK_DATA_LENGTH) {
+        // Remainder of the block times x^PARITY_BYTES divided by the generator
+        let mut parity = [0u8; PARITY_BYTES];
+        for &data_byte in data_block {
+            let feedback = data_byte ^ parity[0];
+            parity.copy_within(1.., 0);
+            parity[PARITY_BYTES - 1] = 0;
+            for (parity_byte, &generator_coefficient) in parity.iter_mut().zip(&generator[1..]) {
+                *parity_byte ^= gf_multiply(generator_coefficient, feedback);
+            }
+        }
+        encoded_data.extend_from_slice(data_block);
+        encoded_data.extend_from_slice(&parity);
+    }
+
+    encoded_data
+}
+
+/// Corrects and strips the parity from data produced by `encode`
+///
+/// Each block tolerates up to 16 corrupted bytes. A block with more fails the whole decode
+/// rather than returning data that only looks repaired.
+pub fn decode(encoded_data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded_data = Vec::with_capacity(encoded_data.len());
+
+    for (block_index, codeword) in encoded_data.chunks(CODEWORD_LENGTH).enumerate() {
+        if codeword.len() <= PARITY_BYTES {
+            return Err(SteganographyError::InvalidInput(format!(
+                "Reed-Solomon block {} is only {} bytes long",
+                block_index,
+                codeword.len()
+            )));
+        }
+
+        let corrected_codeword = correct_codeword(codeword).ok_or_else(|| {
+            SteganographyError::InvalidInput(format!(
+                "Reed-Solomon block {} has more than {} corrupted bytes",
+                block_index,
+                PARITY_BYTES / 2
+            ))
+        })?;
+        decoded_data.extend_from_slice(&corrected_codeword[..codeword.len() - PARITY_BYTES]);
+    }
+
+    Ok(decoded_data)
+}
+
+/// Syndromes of a codeword stored highest degree first, one per generator root
+fn calculate_syndromes(codeword: &[u8]) -> Vec<u8> {
+    (0..PARITY_BYTES)
+        .map(|root_power| {
+            let root = gf_power(root_power);
+            codeword.iter().fold(0, |value, &coefficient| {
+                gf_multiply(value, root) ^ coefficient
+            })
+        })
+        .collect()
+}
+
+/// Error locator polynomial, lowest degree first, found with Berlekamp-Massey
+fn find_error_locator(syndromes: &[u8]) -> Vec<u8> {
+    let mut error_locator = vec![1u8];
+    let mut previous_locator = vec![1u8];
+    let mut locator_degree = 0;
+    let mut shift = 1;
+    let mut previous_discrepancy = 1u8;
+
+    for syndrome_index in 0..syndromes.len() {
+        let discrepancy = (1..=locator_degree)
+            .filter(|&coefficient_index| coefficient_index < error_locator.len())
+            .fold(
+                syndromes[syndrome_index],
+                |discrepancy, coefficient_index| {
+                    discrepancy
+                        ^ gf_multiply(
+                            error_locator[coefficient_index],
+                            syndromes[syndrome_index - coefficient_index],
+                        )
+                },
+            );
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let scale = gf_divide(discrepancy, previous_discrepancy);
+        let mut updated_locator = error_locator.clone();
+        updated_locator.resize(updated_locator.len().max(previous_locator.len() + shift), 0);
+        for (coefficient_index, &coefficient) in previous_locator.iter().enumerate() {
+            updated_locator[coefficient_index + shift] ^= gf_multiply(scale, coefficient);
+        }
+
+        if 2 * locator_degree <= syndrome_index {
+            locator_degree = syndrome_index + 1 - locator_degree;
+            previous_locator = std::mem::replace(&mut error_locator, updated_locator);
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            error_locator = updated_locator;
+            shift += 1;
+        }
+    }
+
+    error_locator.truncate(locator_degree + 1);
+    error_locator
+}
+
+/// Returns the codeword with its errors corrected, or `None` if there are too many
+fn correct_codeword(codeword: &[u8]) -> Option<Vec<u8>> {
+    let syndromes = calculate_syndromes(codeword);
+    if syndromes.iter().all(|&syndrome| syndrome == 0) {
+        return Some(codeword.to_vec());
+    }
+
+    let error_locator = find_error_locator(&syndromes);
+    let error_count = error_locator.len() - 1;
+    if error_count == 0 || error_count > PARITY_BYTES / 2 {
+        return None;
+    }
+
+    // Chien search: byte `index` holds the coefficient of x^(len - 1 - index)
+    let error_positions: Vec<usize> = (0..codeword.len())
+        .filter(|&byte_index| {
+            let error_power = codeword.len() - 1 - byte_index;
+            evaluate_polynomial(&error_locator, gf_power(255 - error_power)) == 0
+        })
+        .collect();
+    if error_positions.len() != error_count {
+        return None;
+    }
+
+    // Forney: the evaluator is syndromes times locator, truncated to the parity length
+    let mut error_evaluator = vec![0u8; PARITY_BYTES];
+    for (syndrome_index, &syndrome) in syndromes.iter().enumerate() {
+        for (locator_index, &locator_coefficient) in error_locator.iter().enumerate() {
+            if syndrome_index + locator_index < PARITY_BYTES {
+                error_evaluator[syndrome_index + locator_index] ^=
+                    gf_multiply(syndrome, locator_coefficient);
+            }
+        }
+    }
+    // Formal derivative: only odd-degree terms survive in characteristic 2
+    let locator_derivative: Vec<u8> = error_locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(degree, &coefficient)| if degree % 2 == 1 { coefficient } else { 0 })
+        .collect();
+
+    let mut corrected_codeword = codeword.to_vec();
+    for byte_index in error_positions {
+        let error_power = codeword.len() - 1 - byte_index;
+        let error_location = gf_power(error_power);
+        let inverse_location = gf_power(255 - error_power);
+        let denominator = evaluate_polynomial(&locator_derivative, inverse_location);
+        if denominator == 0 {
+            return None;
+        }
+        let error_magnitude = gf_multiply(
+            error_location,
+            gf_divide(
+                evaluate_polynomial(&error_evaluator, inverse_location),
+                denominator,
+            ),
+        );
+        corrected_codeword[byte_index] ^= error_magnitude;
+    }
+
+    calculate_syndromes(&corrected_codeword)
+        .iter()
+        .all(|&syndrome| syndrome == 0)
+        .then_some(corrected_codeword)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic bytes that aren't all alike
+    fn test_data(data_length: usize) -> Vec<u8> {
+        (0..data_length)
+            .map(|i| (i as u8).wrapping_mul(151).wrapping_add(7))
+            .collect()
+    }
+
+    #[test]
+    fn test_encoding_appends_parity_per_block() {
+        for data_length in [1, 40, 223, 224, 500] {
+            let encoded_data = encode(&test_data(data_length));
+            assert_eq!(Some(encoded_data.len()), encoded_length(data_length));
+            assert_eq!(decode(&encoded_data).unwrap(), test_data(data_length));
+        }
+        assert_eq!(encoded_length(0), Some(0));
+        assert_eq!(encoded_length(224), Some(224 + 2 * PARITY_BYTES));
+    }
+
+    #[test]
+    fn test_max_data_length_inverts_encoded_length() {
+        for available_bytes in [0, 32, 33, 100, 255, 256, 300, 1000] {
+            let data_length = max_data_length(available_bytes);
+            assert!(encoded_length(data_length).unwrap() <= available_bytes);
+            assert!(encoded_length(data_length + 1).unwrap() > available_bytes);
+        }
+    }
+
+    #[test]
+    fn test_corrects_up_to_half_the_parity_in_every_block() {
+        let original_data = test_data(400);
+        let mut encoded_data = encode(&original_data);
+
+        // Sixteen errors in the full block, including bursts and parity bytes, and in the
+        // shortened one
+        for byte_index in (0..12).chain(100..103).chain([254]) {
+            encoded_data[byte_index] ^= 0x5a ^ byte_index as u8 | 1;
+        }
+        for byte_index in (255..encoded_data.len()).step_by(13).take(16) {
+            encoded_data[byte_index] = !encoded_data[byte_index];
+        }
+
+        assert_eq!(decode(&encoded_data).unwrap(), original_data);
+    }
+
+    #[test]
+    fn test_too_many_errors_fail_instead_of_miscorrecting() {
+        let mut encoded_data = encode(&test_data(100));
+        for corrupted_byte in encoded_data.iter_mut().take(PARITY_BYTES / 2 + 1) {
+            *corrupted_byte ^= 0xff;
+        }
+
+        assert!(matches!(
+            decode(&encoded_data),
+            Err(SteganographyError::InvalidInput(message)) if message.contains("block 0")
+        ));
+        assert!(decode(&[0u8; PARITY_BYTES]).is_err());
+    }
+}
@@ -1,184 +1,733 @@
-use crate::dct::DctProcessor;
+use crate::embedding::{
+    ColorStandard, DctEmbeddingAlgorithm, EmbeddingAlgorithm, NoiseFloorEstimate, YCbCrComponent,
+};
 use crate::error::{Result, SteganographyError};
-use image::{GrayImage, Luma, Rgb, RgbImage};
-use jpeg_encoder::{ColorType, Encoder};
-
-/// Standard JPEG luminance quantization table
-const JPEG_LUMINANCE_QUANTIZATION_TABLE: [[f32; 8]; 8] = [
-    [16.0, 11.0, 10.0, 16.0, 24.0, 40.0, 51.0, 61.0],
-    [12.0, 12.0, 14.0, 19.0, 26.0, 58.0, 60.0, 55.0],
-    [14.0, 13.0, 16.0, 24.0, 40.0, 57.0, 69.0, 56.0],
-    [14.0, 17.0, 22.0, 29.0, 51.0, 87.0, 80.0, 62.0],
-    [18.0, 22.0, 37.0, 56.0, 68.0, 109.0, 103.0, 77.0],
-    [24.0, 35.0, 55.0, 64.0, 81.0, 104.0, 113.0, 92.0],
-    [49.0, 64.0, 78.0, 87.0, 103.0, 121.0, 120.0, 101.0],
-    [72.0, 92.0, 95.0, 98.0, 112.0, 100.0, 103.0, 99.0],
-];
-
-/// Configuration for steganography embedding parameters
+use crate::frame::{
+    BlockVisitation, FrameHeader, PartHeader, MAX_FRAME_HEADER_LENGTH, PART_HEADER_SIZE,
+};
+use crate::output::{diagnostic, BlockProgress};
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher},
+    ChaCha20,
+};
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, GrayAlphaImage, GrayImage, Luma, LumaA, Rgb, RgbImage, Rgba, RgbaImage};
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+pub use crate::embedding::{EmbeddingChannel, EmbeddingConfiguration};
+
+/// Number of header copies embedded when header protection is enabled
+pub const DEFAULT_PROTECTED_HEADER_COPIES: usize = 3;
+
+/// Pixels per block cell side in macro-block mode, giving 32x32 regions with 8x8 blocks
+pub const DEFAULT_MACRO_BLOCK_SCALE: usize = 4;
+
+/// Leading bits replicated by header protection, enough for the largest frame header
+const PROTECTED_HEADER_BITS: usize = MAX_FRAME_HEADER_LENGTH * 8;
+
+/// Leading blocks sampled when checking a carrier for a previous embedding
+const PRIOR_EMBEDDING_SAMPLE_BLOCKS: usize = 64;
+
+/// Share of sampled blocks that must show the embedded signature to report a prior embedding
+const PRIOR_EMBEDDING_BLOCK_FRACTION: f32 = 0.75;
+
+/// Nonce separating the block order keystream from the encryption and position keystreams
+const BLOCK_ORDER_NONCE: [u8; 12] = *b"steg-blkordr";
+
+/// Leading blocks scanned when estimating whether an image carries a payload
+const PAYLOAD_DETECTION_SAMPLE_BLOCKS: usize = 256;
+
+/// Lowest alpha value treated as fully opaque, so flipping its least significant bit is invisible
+const OPAQUE_ALPHA_THRESHOLD: u8 = u8::MAX - 1;
+
+/// Bytes recovered from a steganographic image along with per-bit confidence
 #[derive(Debug, Clone)]
-pub struct EmbeddingConfiguration {
-    pub block_size: usize,
-    pub embedding_positions: Vec<(usize, usize)>,
-    pub embedding_strength: f32,
-    pub minimum_quantization_step: f32,
+pub struct ExtractionResult {
+    pub data: Vec<u8>,
+    pub bit_confidences: Vec<f32>,
 }
 
-impl Default for EmbeddingConfiguration {
-    fn default() -> Self {
-        Self {
-            block_size: 8,
-            // Most robust DCT coefficient positions for JPEG compression survival
-            embedding_positions: vec![
-                (4, 1), // Primary robust position
-                (1, 4), // Secondary robust position
-                (3, 2), // Backup position
-                (2, 3), // Backup position
-                (5, 0),
-                (0, 5),
-                (3, 4),
-                (4, 3),
-            ],
-            embedding_strength: 25.0, // Strong enough to survive JPEG compression
-            minimum_quantization_step: 4.0,
+impl ExtractionResult {
+    /// Average confidence across every extracted bit
+    pub fn confidence(&self) -> f32 {
+        self.confidence_for_leading_bytes(self.data.len())
+    }
+
+    /// Average confidence across the bits of the first `byte_count` bytes
+    ///
+    /// Useful when only a prefix of the blob carries payload and the rest is unused cover.
+    pub fn confidence_for_leading_bytes(&self, byte_count: usize) -> f32 {
+        let bit_count = (byte_count * 8).min(self.bit_confidences.len());
+        if bit_count == 0 {
+            return 0.0;
         }
+        self.bit_confidences[..bit_count].iter().sum::<f32>() / bit_count as f32
     }
 }
 
 /// Main steganography engine for hiding and extracting data in images
-pub struct SteganographyEngine {
-    configuration: EmbeddingConfiguration,
-    dct_processor: DctProcessor,
+///
+/// The algorithm works on `N`x`N` blocks, 8x8 unless stated otherwise.
+pub struct SteganographyEngine<A: EmbeddingAlgorithm<N> = DctEmbeddingAlgorithm, const N: usize = 8>
+{
+    embedding_algorithm: A,
+    /// How many times the frame header is embedded at the start of the carrier
+    header_copies: usize,
+    /// Side length in pixels of each block cell; 1 embeds in plain pixel blocks
+    macro_block_scale: usize,
+    /// Order of the blocks after the header region
+    block_visitation: BlockVisitation,
+    /// Key the keyed block visitation order is derived from
+    block_order_seed: Option<[u8; 32]>,
 }
 
 impl SteganographyEngine {
     /// Creates a new steganography engine with default configuration
     pub fn new() -> Self {
-        Self {
-            configuration: EmbeddingConfiguration::default(),
-            dct_processor: DctProcessor::new(),
-        }
+        Self::with_algorithm(DctEmbeddingAlgorithm::new())
     }
 
     /// Creates a new steganography engine with custom configuration
     pub fn with_configuration(configuration: EmbeddingConfiguration) -> Self {
+        Self::with_algorithm(DctEmbeddingAlgorithm::with_configuration(configuration))
+    }
+}
+
+impl<const N: usize> SteganographyEngine<DctEmbeddingAlgorithm<N>, N> {
+    /// Estimates the image's embedded amplitude and noise level and votes with a threshold
+    /// between them from then on
+    ///
+    /// This is the first pass of a two-pass extraction. It samples the embedding-position
+    /// coefficients of every block, so the following extraction suits however this image was
+    /// recompressed or rescaled. Returns `None` and keeps the current threshold if the image
+    /// has too few blocks to estimate from.
+    pub fn calibrate_noise_floor(
+        &mut self,
+        steganographic_image: &RgbImage,
+    ) -> Result<Option<NoiseFloorEstimate>> {
+        let mut coefficient_magnitudes = Vec::new();
+        let block_locations = self
+            .channel_block_coordinates(steganographic_image.width(), steganographic_image.height());
+        for (block_index, (component, (block_x, block_y))) in block_locations.enumerate() {
+            let component_block = self.extract_component_block_from_rgb(
+                steganographic_image,
+                component,
+                block_x as usize,
+                block_y as usize,
+            );
+            coefficient_magnitudes.extend(
+                self.embedding_algorithm
+                    .embedded_coefficient_magnitudes(block_index, &component_block)?,
+            );
+        }
+
+        let noise_floor_estimate =
+            NoiseFloorEstimate::from_coefficient_magnitudes(&coefficient_magnitudes);
+        if let Some(noise_floor_estimate) = noise_floor_estimate {
+            self.embedding_algorithm
+                .set_vote_threshold(noise_floor_estimate.vote_threshold);
+        }
+        Ok(noise_floor_estimate)
+    }
+
+    /// Returns whether a carrier appears to already hold a payload embedded with this layout
+    ///
+    /// Samples the leading blocks, where every payload starts with its frame header, and
+    /// reports a prior embedding when most of them show the embedded coefficient signature.
+    /// Hiding again into such a carrier overwrites the old bits only partly, so a clean
+    /// carrier gives far more reliable extraction. Payloads hidden with keyed positions or a
+    /// different block layout are not detected.
+    pub fn detect_prior_embedding(&self, carrier_image: &RgbImage) -> Result<bool> {
+        Ok(self
+            .embedding_signature_fraction(carrier_image, PRIOR_EMBEDDING_SAMPLE_BLOCKS)?
+            .is_some_and(|signature_fraction| {
+                signature_fraction >= PRIOR_EMBEDDING_BLOCK_FRACTION as f64
+            }))
+    }
+
+    /// Estimates how likely an image is to carry a payload, from 0 (clean) to 1, without a key
+    ///
+    /// Scans the leading blocks for the embedded coefficient signature: carrying coefficients
+    /// that cluster at plus or minus the embedded amplitude with one shared sign. Natural
+    /// content shares a sign across them only by chance, so the share of matching blocks is
+    /// rescaled to run from that chance rate to every block. Payloads hidden with keyed
+    /// positions or a different block layout score as clean.
+    pub fn detect_payload_presence(&self, image: &RgbImage) -> Result<f64> {
+        let Some(signature_fraction) =
+            self.embedding_signature_fraction(image, PAYLOAD_DETECTION_SAMPLE_BLOCKS)?
+        else {
+            return Ok(0.0);
+        };
+
+        let carrying_coefficients = self
+            .embedding_algorithm
+            .configuration()
+            .redundant_position_count
+            .max(1);
+        let chance_fraction = 0.5f64.powi(carrying_coefficients as i32 - 1);
+        if chance_fraction >= 1.0 {
+            return Ok(signature_fraction);
+        }
+        Ok(((signature_fraction - chance_fraction) / (1.0 - chance_fraction)).clamp(0.0, 1.0))
+    }
+
+    /// Returns a 16-bit fingerprint of the parameters hide and extract must agree on
+    ///
+    /// Covers the block size in pixels, the embedding positions and whether they are keyed,
+    /// the embedding strength and any color standard other than BT.601. Never zero, which the
+    /// frame header reserves for no fingerprint.
+    pub fn configuration_fingerprint(&self) -> u16 {
+        let configuration = self.embedding_algorithm.configuration();
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&((configuration.block_size * self.macro_block_scale) as u32).to_le_bytes());
+        for &(coefficient_y, coefficient_x) in &configuration.embedding_positions {
+            hasher.update(&[coefficient_y as u8, coefficient_x as u8]);
+        }
+        hasher.update(&(configuration.redundant_position_count as u32).to_le_bytes());
+        hasher.update(&[u8::from(self.embedding_algorithm.has_keyed_positions())]);
+        hasher.update(&configuration.embedding_strength.to_le_bytes());
+        // Hashed only when changed, so default fingerprints match images embedded before it
+        if configuration.color_standard != ColorStandard::Bt601 {
+            hasher.update(configuration.color_standard.name().as_bytes());
+        }
+
+        let checksum = hasher.finalize();
+        ((checksum ^ (checksum >> 16)) as u16).max(1)
+    }
+
+    /// Returns the share of the leading blocks that show the embedded coefficient signature
+    ///
+    /// Returns `None` if the image has no blocks to sample.
+    fn embedding_signature_fraction(
+        &self,
+        image: &RgbImage,
+        sample_blocks: usize,
+    ) -> Result<Option<f64>> {
+        let mut sampled_blocks = 0usize;
+        let mut signature_blocks = 0usize;
+        let block_locations = self
+            .channel_block_coordinates(image.width(), image.height())
+            .take(sample_blocks);
+        for (block_index, (component, (block_x, block_y))) in block_locations.enumerate() {
+            let component_block = self.extract_component_block_from_rgb(
+                image,
+                component,
+                block_x as usize,
+                block_y as usize,
+            );
+            sampled_blocks += 1;
+            if self
+                .embedding_algorithm
+                .shows_embedding_signature(block_index, &component_block)?
+            {
+                signature_blocks += 1;
+            }
+        }
+
+        Ok((sampled_blocks > 0).then(|| signature_blocks as f64 / sampled_blocks as f64))
+    }
+}
+
+impl<A: EmbeddingAlgorithm<N>, const N: usize> SteganographyEngine<A, N> {
+    /// Creates a new steganography engine using a custom embedding algorithm
+    pub fn with_algorithm(embedding_algorithm: A) -> Self {
         Self {
-            configuration,
-            dct_processor: DctProcessor::new(),
+            embedding_algorithm,
+            header_copies: 1,
+            macro_block_scale: 1,
+            block_visitation: BlockVisitation::default(),
+            block_order_seed: None,
+        }
+    }
+
+    /// Swaps in a different embedding algorithm, keeping the header, macro-block and
+    /// visitation settings
+    pub fn with_embedding_algorithm<B: EmbeddingAlgorithm<N>>(
+        self,
+        embedding_algorithm: B,
+    ) -> SteganographyEngine<B, N> {
+        SteganographyEngine {
+            embedding_algorithm,
+            header_copies: self.header_copies,
+            macro_block_scale: self.macro_block_scale,
+            block_visitation: self.block_visitation,
+            block_order_seed: self.block_order_seed,
         }
     }
 
-    /// Converts data to bits with length header for reliable extraction
-    fn convert_data_to_bits_with_header(&self, data: &[u8]) -> Vec<u8> {
-        let mut bit_stream = Vec::new();
+    /// Embeds the frame header `header_copies` times before the body
+    ///
+    /// Losing the header loses the whole payload, while the body already tolerates bit
+    /// errors through repetition coding. Replicating only the header buys much more
+    /// reliable recovery for a fixed cost of a few hundred blocks. Extraction must use
+    /// the same number of copies.
+    pub fn with_header_copies(mut self, header_copies: usize) -> Self {
+        self.header_copies = header_copies.max(1);
+        self
+    }
+
+    /// Returns how many times the frame header is embedded
+    pub fn header_copies(&self) -> usize {
+        self.header_copies
+    }
+
+    /// Embeds each bit in a region `macro_block_scale` times the block size on each side
+    ///
+    /// Every cell of the block the algorithm sees is the mean luminance of a
+    /// `macro_block_scale`-sided square of pixels, and the change the algorithm makes to a
+    /// cell is spread evenly over its pixels. The embedded pattern is then coarse enough to
+    /// survive downscaling that averages away plain 8x8 blocks, at the cost of dividing
+    /// capacity by the square of the scale. Extraction must use the same scale.
+    pub fn with_macro_block_scale(mut self, macro_block_scale: usize) -> Self {
+        self.macro_block_scale = macro_block_scale.max(1);
+        self
+    }
+
+    /// Returns the side length in pixels of each block cell
+    pub fn macro_block_scale(&self) -> usize {
+        self.macro_block_scale
+    }
+
+    /// Visits the blocks after the header region in `block_visitation` order
+    ///
+    /// The header region is always visited row by row, so extraction can read the order
+    /// recorded in the frame header before it knows where the body is. Extraction must use
+    /// the same order.
+    pub fn with_block_visitation(mut self, block_visitation: BlockVisitation) -> Self {
+        self.block_visitation = block_visitation;
+        self
+    }
+
+    /// Returns the order of the blocks after the header region
+    pub fn block_visitation(&self) -> BlockVisitation {
+        self.block_visitation
+    }
+
+    /// Sets the key that [`BlockVisitation::Keyed`] shuffles the blocks with
+    ///
+    /// The permutation is drawn from a ChaCha20 keystream, so the body's bits are spread
+    /// evenly over the carrier and can't be read in sequence without the key. Until a seed
+    /// is set the keyed order uses an all-zero key, which still spreads the bits but hides
+    /// nothing. Extraction must use the same key.
+    pub fn with_block_order_seed(mut self, block_order_seed: [u8; 32]) -> Self {
+        self.block_order_seed = Some(block_order_seed);
+        self
+    }
+
+    /// Side length in pixels of the region holding one bit
+    fn region_size(&self) -> usize {
+        self.embedding_algorithm.block_size() * self.macro_block_scale
+    }
+
+    /// Rejects an image narrower or shorter than one region, naming the size the payload needs
+    ///
+    /// Such an image would still report one edge-clamped block of capacity, which leads to
+    /// confusing capacity errors. The suggested size is the smallest square whose regions
+    /// across `channel_count` channels hold `required_bits`.
+    fn check_minimum_dimensions(
+        &self,
+        image_width: u32,
+        image_height: u32,
+        required_bits: usize,
+        channel_count: usize,
+    ) -> Result<()> {
+        let region_size = self.region_size();
+        if image_width as usize >= region_size && image_height as usize >= region_size {
+            return Ok(());
+        }
 
-        // Add 32-bit length header for data size information
-        let data_length = data.len() as u32;
-        for bit_position in (0..32).rev() {
-            bit_stream.push(((data_length >> bit_position) & 1) as u8);
+        let required_regions = required_bits.div_ceil(channel_count.max(1));
+        let mut regions_per_side = required_regions.isqrt();
+        if regions_per_side * regions_per_side < required_regions {
+            regions_per_side += 1;
         }
+        let minimum_side = regions_per_side.max(1) * region_size;
+        Err(SteganographyError::InvalidInput(format!(
+            "image is {}x{} pixels, smaller than one {}x{} block; hiding {} bits needs at least {}x{} pixels",
+            image_width, image_height, region_size, region_size, required_bits, minimum_side, minimum_side
+        )))
+    }
+
+    /// Mean of `pixel_value` over the cell whose top-left pixel is `(cell_x, cell_y)`
+    ///
+    /// Coordinates past the right and bottom edges repeat the edge pixels, matching how
+    /// partial blocks have always been read.
+    fn cell_mean(
+        &self,
+        image_width: u32,
+        image_height: u32,
+        cell_x: usize,
+        cell_y: usize,
+        pixel_value: impl Fn(u32, u32) -> f32,
+    ) -> f32 {
+        let cell_pixel_sum: f32 = (0..self.macro_block_scale)
+            .flat_map(|offset_y| {
+                (0..self.macro_block_scale).map(move |offset_x| (offset_x, offset_y))
+            })
+            .map(|(offset_x, offset_y)| {
+                let pixel_x = ((cell_x + offset_x) as u32).min(image_width - 1);
+                let pixel_y = ((cell_y + offset_y) as u32).min(image_height - 1);
+                pixel_value(pixel_x, pixel_y)
+            })
+            .sum();
+        cell_pixel_sum / (self.macro_block_scale * self.macro_block_scale) as f32
+    }
+
+    /// In-bounds pixels of the cell whose top-left pixel is `(cell_x, cell_y)`
+    fn cell_pixels(
+        &self,
+        image_width: u32,
+        image_height: u32,
+        cell_x: usize,
+        cell_y: usize,
+    ) -> impl Iterator<Item = (u32, u32)> {
+        let macro_block_scale = self.macro_block_scale;
+        (0..macro_block_scale)
+            .flat_map(move |offset_y| {
+                (0..macro_block_scale)
+                    .map(move |offset_x| ((cell_x + offset_x) as u32, (cell_y + offset_y) as u32))
+            })
+            .filter(move |&(pixel_x, pixel_y)| pixel_x < image_width && pixel_y < image_height)
+    }
+
+    /// Returns the embedding algorithm in use
+    pub fn embedding_algorithm(&self) -> &A {
+        &self.embedding_algorithm
+    }
+
+    /// Converts data to its bit representation, most significant bit first
+    fn convert_data_to_bits(&self, data: &[u8]) -> Vec<u8> {
+        let mut bit_stream = Vec::with_capacity(data.len() * 8);
 
-        // Convert each byte to its bit representation
         for &data_byte in data {
             for bit_position in (0..8).rev() {
-                bit_stream.push(((data_byte >> bit_position) & 1) as u8);
+                bit_stream.push((data_byte >> bit_position) & 1);
             }
         }
 
         bit_stream
     }
 
-    /// Converts bits back to data using length header information
-    fn convert_bits_to_data_with_header(&self, bit_stream: &[u8]) -> Result<Vec<u8>> {
-        if bit_stream.len() < 32 {
-            return Err(SteganographyError::InvalidInput(
-                "Not enough bits for length header".to_string(),
-            ));
+    /// Converts bits back to bytes, discarding any trailing partial byte
+    fn convert_bits_to_data(&self, bit_stream: &[u8]) -> Vec<u8> {
+        bit_stream
+            .chunks_exact(8)
+            .map(|bit_chunk| {
+                bit_chunk
+                    .iter()
+                    .fold(0u8, |byte_value, &bit| (byte_value << 1) | bit)
+            })
+            .collect()
+    }
+
+    /// Packs extracted bits into bytes, keeping confidences for the whole bytes only
+    fn build_extraction_result(
+        &self,
+        extracted_bits: &[u8],
+        mut bit_confidences: Vec<f32>,
+    ) -> ExtractionResult {
+        let data = self.convert_bits_to_data(extracted_bits);
+        bit_confidences.truncate(data.len() * 8);
+
+        ExtractionResult {
+            data,
+            bit_confidences,
         }
+    }
 
-        // Extract data length from first 32 bits
-        let mut data_length = 0u32;
-        for bit_index in 0..32 {
-            data_length = (data_length << 1) | (bit_stream[bit_index] as u32);
+    /// Replicates the leading header bits ahead of the body when header protection is on
+    ///
+    /// Streams shorter than the protected region are zero-padded so its layout never
+    /// depends on the payload length.
+    fn replicate_protected_header(&self, mut bit_stream: Vec<u8>) -> Vec<u8> {
+        if self.header_copies <= 1 {
+            return bit_stream;
         }
 
-        let data_bits = &bit_stream[32..];
-        let expected_bit_count = data_length as usize * 8;
+        bit_stream.resize(bit_stream.len().max(PROTECTED_HEADER_BITS), 0);
+        let (header_bits, body_bits) = bit_stream.split_at(PROTECTED_HEADER_BITS);
+        let mut embedded_bits = header_bits.repeat(self.header_copies);
+        embedded_bits.extend_from_slice(body_bits);
+        embedded_bits
+    }
 
-        if data_bits.len() < expected_bit_count {
-            return Err(SteganographyError::InvalidInput(format!(
-                "Not enough data bits. Expected {}, got {}",
-                expected_bit_count,
-                data_bits.len()
-            )));
+    /// Merges the replicated header bits back into one copy by confidence-weighted voting
+    fn merge_protected_header(
+        &self,
+        embedded_bits: Vec<u8>,
+        embedded_confidences: Vec<f32>,
+    ) -> Result<(Vec<u8>, Vec<f32>)> {
+        if self.header_copies <= 1 {
+            return Ok((embedded_bits, embedded_confidences));
         }
 
-        // Convert bits back to bytes
-        let mut recovered_data = Vec::new();
-        for bit_chunk in data_bits[..expected_bit_count].chunks(8) {
-            let mut byte_value = 0u8;
-            for &bit in bit_chunk {
-                byte_value = (byte_value << 1) | bit;
-            }
-            recovered_data.push(byte_value);
+        let replicated_bits = PROTECTED_HEADER_BITS * self.header_copies;
+        if embedded_bits.len() < replicated_bits {
+            return Err(SteganographyError::CapacityError {
+                required: replicated_bits,
+                available: embedded_bits.len(),
+            });
+        }
+
+        let mut merged_bits = Vec::with_capacity(embedded_bits.len() - replicated_bits);
+        let mut merged_confidences = Vec::with_capacity(merged_bits.capacity());
+        for header_bit_index in 0..PROTECTED_HEADER_BITS {
+            // Each copy pulls toward its bit value in proportion to how confidently it was read
+            let weighted_vote: f32 = (0..self.header_copies)
+                .map(|copy_index| copy_index * PROTECTED_HEADER_BITS + header_bit_index)
+                .map(|bit_index| {
+                    if embedded_bits[bit_index] == 1 {
+                        embedded_confidences[bit_index]
+                    } else {
+                        -embedded_confidences[bit_index]
+                    }
+                })
+                .sum();
+
+            merged_bits.push(match weighted_vote.partial_cmp(&0.0) {
+                Some(std::cmp::Ordering::Greater) => 1,
+                Some(std::cmp::Ordering::Less) => 0,
+                _ => embedded_bits[header_bit_index],
+            });
+            merged_confidences.push((weighted_vote.abs() / self.header_copies as f32).min(1.0));
         }
+        merged_bits.extend_from_slice(&embedded_bits[replicated_bits..]);
+        merged_confidences.extend_from_slice(&embedded_confidences[replicated_bits..]);
 
-        Ok(recovered_data)
+        Ok((merged_bits, merged_confidences))
     }
 
-    /// Calculates quantization table based on JPEG quality factor
-    fn calculate_quantization_table(&self, jpeg_quality: u8) -> [[f32; 8]; 8] {
-        let quality_factor = jpeg_quality.clamp(1, 100) as f32;
-        let scaling_factor = if quality_factor < 50.0 {
-            5000.0 / quality_factor
-        } else {
-            200.0 - 2.0 * quality_factor
-        };
+    /// Blocks spent on header copies beyond the first
+    fn protected_header_overhead_bits(&self) -> usize {
+        PROTECTED_HEADER_BITS * (self.header_copies - 1)
+    }
+
+    /// Number of leading blocks holding the frame header and its copies
+    fn header_region_blocks(&self) -> usize {
+        PROTECTED_HEADER_BITS * self.header_copies
+    }
+
+    /// Number of embedded bits that must be read to recover `payload_bits` bits
+    fn embedded_bits_for_payload(&self, payload_bits: usize) -> usize {
+        if self.header_copies <= 1 {
+            return payload_bits;
+        }
+        payload_bits.max(PROTECTED_HEADER_BITS) + self.protected_header_overhead_bits()
+    }
+
+    /// Reads `bits_to_extract` payload bits via `read_embedded_bits`, undoing header replication
+    fn extract_payload_bits(
+        &self,
+        total_capacity: usize,
+        expected_data_length: Option<usize>,
+        read_embedded_bits: impl FnOnce(usize) -> Result<(Vec<u8>, Vec<f32>)>,
+    ) -> Result<(Vec<u8>, Vec<f32>)> {
+        let payload_capacity = total_capacity.saturating_sub(self.protected_header_overhead_bits());
+        let bits_to_extract =
+            self.calculate_bits_to_extract(payload_capacity, expected_data_length)?;
+        let (embedded_bits, embedded_confidences) =
+            read_embedded_bits(self.embedded_bits_for_payload(bits_to_extract))?;
+        let (mut payload_bits, mut payload_confidences) =
+            self.merge_protected_header(embedded_bits, embedded_confidences)?;
+
+        payload_bits.truncate(bits_to_extract);
+        payload_confidences.truncate(bits_to_extract);
+        Ok((payload_bits, payload_confidences))
+    }
 
-        let mut quantization_table = [[0.0f32; 8]; 8];
-        for row_index in 0..8 {
-            for column_index in 0..8 {
-                let quantized_value =
-                    ((JPEG_LUMINANCE_QUANTIZATION_TABLE[row_index][column_index] * scaling_factor
-                        + 50.0)
-                        / 100.0)
-                        .floor()
-                        .clamp(1.0, 255.0);
-                quantization_table[row_index][column_index] = quantized_value;
+    /// Determines how many bits to read back, defaulting to the full framed blob
+    fn calculate_bits_to_extract(
+        &self,
+        total_capacity: usize,
+        expected_data_length: Option<usize>,
+    ) -> Result<usize> {
+        match expected_data_length {
+            Some(expected_length) => {
+                let required_bits = expected_length.checked_mul(8).ok_or_else(|| {
+                    SteganographyError::InvalidInput(format!(
+                        "expected length of {} bytes is too large",
+                        expected_length
+                    ))
+                })?;
+                if required_bits > total_capacity {
+                    return Err(SteganographyError::CapacityError {
+                        required: required_bits,
+                        available: total_capacity,
+                    });
+                }
+                Ok(required_bits)
             }
+            // The crypto layer owns the authoritative length, so read every whole byte
+            None => Ok(total_capacity - total_capacity % 8),
         }
-        quantization_table
     }
 
     /// Calculates maximum data capacity for an RGB image in bits
-    pub fn calculate_capacity_bits(&self, rgb_image: &RgbImage) -> usize {
-        let horizontal_blocks = (rgb_image.width() as usize + self.configuration.block_size - 1)
-            / self.configuration.block_size;
-        let vertical_blocks = (rgb_image.height() as usize + self.configuration.block_size - 1)
-            / self.configuration.block_size;
-        horizontal_blocks * vertical_blocks // One bit per block for robustness
+    ///
+    /// Each channel selected by the embedding algorithm holds one bit per block.
+    pub fn calculate_capacity_bits(&self, rgb_image: &RgbImage) -> Result<usize> {
+        let channel_count = self
+            .embedding_algorithm
+            .embedding_channel()
+            .components()
+            .len();
+        self.capacity_for_dimensions(rgb_image.width(), rgb_image.height())?
+            .checked_mul(channel_count)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "a {}x{} image has more capacity than this platform can count",
+                    rgb_image.width(),
+                    rgb_image.height()
+                ))
+            })
     }
 
     /// Calculates maximum data capacity for a grayscale image in bits (legacy support)
-    pub fn calculate_grayscale_capacity_bits(&self, grayscale_image: &GrayImage) -> usize {
-        let horizontal_blocks = (grayscale_image.width() as usize + self.configuration.block_size
-            - 1)
-            / self.configuration.block_size;
-        let vertical_blocks = (grayscale_image.height() as usize + self.configuration.block_size
-            - 1)
-            / self.configuration.block_size;
-        horizontal_blocks * vertical_blocks // One bit per block for robustness
+    pub fn calculate_grayscale_capacity_bits(&self, grayscale_image: &GrayImage) -> Result<usize> {
+        self.capacity_for_dimensions(grayscale_image.width(), grayscale_image.height())
+    }
+
+    /// Asks the algorithm for capacity in cells, which are single pixels outside macro-block mode
+    fn capacity_for_dimensions(&self, image_width: u32, image_height: u32) -> Result<usize> {
+        let macro_block_scale = self.macro_block_scale as u32;
+        self.embedding_algorithm.capacity(
+            image_width.div_ceil(macro_block_scale),
+            image_height.div_ceil(macro_block_scale),
+        )
+    }
+
+    /// Top-left corners of every block in embedding order
+    ///
+    /// The header region comes first, row by row, followed by the remaining blocks in the
+    /// configured visitation order. Partial blocks along the right and bottom edges are
+    /// included. Every embed and extract path walks the carrier through this iterator so
+    /// they always agree on which block holds which bit.
+    pub fn block_coordinates(&self, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+        let region_size = self.region_size() as u32;
+        let grid_columns = width.div_ceil(region_size) as usize;
+        let grid_rows = height.div_ceil(region_size) as usize;
+        let header_blocks = self.header_region_blocks();
+        let row_major_index =
+            move |(grid_x, grid_y): (usize, usize)| grid_y * grid_columns + grid_x;
+
+        let header_region = (0..header_blocks.min(grid_columns * grid_rows))
+            .map(move |block_index| (block_index % grid_columns, block_index / grid_columns));
+        let block_order_seed = self.block_order_seed.unwrap_or_default();
+        let body_region = grid_visitation_order(
+            self.block_visitation,
+            &block_order_seed,
+            grid_columns,
+            grid_rows,
+        )
+        .into_iter()
+        .filter(move |&grid_position| row_major_index(grid_position) >= header_blocks);
+
+        header_region
+            .chain(body_region)
+            .map(move |(grid_x, grid_y)| (grid_x as u32 * region_size, grid_y as u32 * region_size))
+    }
+
+    /// Every block of every embedding channel in embedding order, tagged with its component
+    ///
+    /// Each channel is walked in full with [`Self::block_coordinates`] before the next, so
+    /// the frame header always lands in the first channel selected.
+    fn channel_block_coordinates(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = (YCbCrComponent, (u32, u32))> + '_ {
+        self.embedding_algorithm
+            .embedding_channel()
+            .components()
+            .iter()
+            .flat_map(move |&component| {
+                self.block_coordinates(width, height)
+                    .map(move |block_coordinates| (component, block_coordinates))
+            })
+    }
+
+    /// Pads an RGB image up to a multiple of the block size by replicating edge pixels
+    ///
+    /// Every block is then fully inside the carrier, so edge blocks embed as reliably as
+    /// interior ones at the cost of changing the output dimensions. In macro-block mode the
+    /// image is padded to a multiple of the whole region.
+    pub fn pad_to_block_multiple(&self, rgb_image: &RgbImage) -> RgbImage {
+        let block_size = self.region_size() as u32;
+        let padded_width = rgb_image.width().div_ceil(block_size) * block_size;
+        let padded_height = rgb_image.height().div_ceil(block_size) * block_size;
+
+        RgbImage::from_fn(padded_width, padded_height, |pixel_x, pixel_y| {
+            *rgb_image.get_pixel(
+                pixel_x.min(rgb_image.width() - 1),
+                pixel_y.min(rgb_image.height() - 1),
+            )
+        })
+    }
+
+    /// Crops a padded RGB image back to its original dimensions
+    pub fn crop_to_dimensions(&self, rgb_image: &RgbImage, width: u32, height: u32) -> RgbImage {
+        image::imageops::crop_imm(
+            rgb_image,
+            0,
+            0,
+            width.min(rgb_image.width()),
+            height.min(rgb_image.height()),
+        )
+        .to_image()
+    }
+
+    /// Peak signal-to-noise ratio of `modified` against `original` in decibels
+    ///
+    /// The mean squared error is taken over every RGB sample of the overlapping area.
+    /// Identical images have an infinite PSNR; around 40 dB and above is usually invisible.
+    pub fn compute_psnr(&self, original: &RgbImage, modified: &RgbImage) -> f64 {
+        let compared_width = original.width().min(modified.width());
+        let compared_height = original.height().min(modified.height());
+        let sample_count = compared_width as f64 * compared_height as f64 * 3.0;
+        if sample_count == 0.0 {
+            return f64::INFINITY;
+        }
+
+        let mut squared_error_sum = 0.0;
+        for y in 0..compared_height {
+            for x in 0..compared_width {
+                let original_pixel = original.get_pixel(x, y);
+                let modified_pixel = modified.get_pixel(x, y);
+                for channel in 0..3 {
+                    let sample_difference =
+                        original_pixel[channel] as f64 - modified_pixel[channel] as f64;
+                    squared_error_sum += sample_difference * sample_difference;
+                }
+            }
+        }
+
+        let mean_squared_error = squared_error_sum / sample_count;
+        if mean_squared_error == 0.0 {
+            return f64::INFINITY;
+        }
+        10.0 * (255.0 * 255.0 / mean_squared_error).log10()
     }
 
     /// Hides encrypted data in RGB image using JPEG-robust DCT steganography
     pub fn hide_data_in_rgb_image(
-        &mut self,
+        &self,
         source_image: &RgbImage,
         encrypted_data: &[u8],
         jpeg_quality: u8,
     ) -> Result<RgbImage> {
-        let bit_stream = self.convert_data_to_bits_with_header(encrypted_data);
-        let available_capacity = self.calculate_capacity_bits(source_image);
+        let bit_stream = self.replicate_protected_header(self.convert_data_to_bits(encrypted_data));
+        let channel_count = self
+            .embedding_algorithm
+            .embedding_channel()
+            .components()
+            .len();
+        self.check_minimum_dimensions(
+            source_image.width(),
+            source_image.height(),
+            bit_stream.len(),
+            channel_count,
+        )?;
+        let available_capacity = self.calculate_capacity_bits(source_image)?;
 
         if bit_stream.len() > available_capacity {
             return Err(SteganographyError::CapacityError {
@@ -187,7 +736,7 @@ impl SteganographyEngine {
             });
         }
 
-        println!(
+        diagnostic!(
             "Hiding {} bytes ({} bits) in RGB image with capacity {} bits (JPEG quality: {})",
             encrypted_data.len(),
             bit_stream.len(),
@@ -195,47 +744,149 @@ impl SteganographyEngine {
             jpeg_quality
         );
 
-        let quantization_table = self.calculate_quantization_table(jpeg_quality);
         let mut steganographic_image = source_image.clone();
-        let mut current_bit_index = 0;
 
-        // Embed data in luminance channel only to preserve color information
-        for block_y in (0..source_image.height()).step_by(self.configuration.block_size) {
-            for block_x in (0..source_image.width()).step_by(self.configuration.block_size) {
-                if current_bit_index >= bit_stream.len() {
-                    return Ok(steganographic_image);
-                }
+        // Assign every bit to its block up front so the blocks can be embedded in any order
+        let assigned_blocks: Vec<_> = self
+            .channel_block_coordinates(source_image.width(), source_image.height())
+            .take(bit_stream.len())
+            .enumerate()
+            .collect();
 
-                // Extract luminance values from RGB block
-                let mut luminance_block = self.extract_luminance_block_from_rgb(
-                    source_image,
+        let progress = BlockProgress::start(assigned_blocks.len(), "Embedding");
+
+        // Channels are filled one after another, so each block is read back from the image
+        // being built to keep the changes already made to its other channels. Blocks of one
+        // channel never overlap, so they are embedded together and written back afterwards.
+        for channel_blocks in
+            assigned_blocks.chunk_by(|(_, (first_component, _)), (_, (second_component, _))| {
+                first_component == second_component
+            })
+        {
+            let embedded_blocks = process_blocks(
+                channel_blocks.to_vec(),
+                |(bit_index, (component, (block_x, block_y)))| {
+                    // Extract the channel's values from the RGB block
+                    let mut component_block = self.extract_component_block_from_rgb(
+                        &steganographic_image,
+                        component,
+                        block_x as usize,
+                        block_y as usize,
+                    );
+
+                    // Embed bit using the configured embedding algorithm
+                    self.embedding_algorithm.embed_bit(
+                        bit_index,
+                        &mut component_block,
+                        bit_stream[bit_index],
+                        jpeg_quality,
+                    )?;
+                    progress.advance();
+                    Ok((component, (block_x, block_y), component_block))
+                },
+            )
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+            // Write the modified channel back to the RGB image
+            for (component, (block_x, block_y), component_block) in embedded_blocks {
+                self.write_component_block_to_rgb(
+                    &mut steganographic_image,
+                    component,
                     block_x as usize,
                     block_y as usize,
+                    &component_block,
                 );
+            }
+        }
 
-                // Apply DCT transformation
-                self.dct_processor.apply_forward_dct(&mut luminance_block)?;
+        Ok(steganographic_image)
+    }
 
-                // Embed bit using quantization-aware robust method
-                let bit_to_embed = bit_stream[current_bit_index];
-                self.embed_bit_robustly(&mut luminance_block, bit_to_embed, &quantization_table);
+    /// Extracts one YCbCr component of an RGB block for DCT processing
+    fn extract_component_block_from_rgb(
+        &self,
+        rgb_image: &RgbImage,
+        component: YCbCrComponent,
+        block_x: usize,
+        block_y: usize,
+    ) -> [[f32; N]; N] {
+        let component_index = match component {
+            YCbCrComponent::Luma => {
+                return self.extract_luminance_block_from_rgb(rgb_image, block_x, block_y)
+            }
+            YCbCrComponent::BlueChroma => 1,
+            YCbCrComponent::RedChroma => 2,
+        };
 
-                current_bit_index += 1;
+        let mut component_block = [[0f32; N]; N];
+        for (y, component_row) in component_block.iter_mut().enumerate() {
+            for (x, component_value) in component_row.iter_mut().enumerate() {
+                *component_value = self.cell_mean(
+                    rgb_image.width(),
+                    rgb_image.height(),
+                    block_x + x * self.macro_block_scale,
+                    block_y + y * self.macro_block_scale,
+                    |pixel_x, pixel_y| {
+                        rgb_to_ycbcr(rgb_image.get_pixel(pixel_x, pixel_y))[component_index]
+                    },
+                );
+            }
+        }
+        component_block
+    }
 
-                // Apply inverse DCT transformation
-                self.dct_processor.apply_inverse_dct(&mut luminance_block)?;
+    /// Writes one modified YCbCr component back to an RGB block
+    ///
+    /// Luminance keeps its original write-back. A chroma change goes through the full
+    /// YCbCr conversion, so the pixel's luminance and other chroma channel are untouched
+    /// apart from rounding.
+    fn write_component_block_to_rgb(
+        &self,
+        rgb_image: &mut RgbImage,
+        component: YCbCrComponent,
+        block_x: usize,
+        block_y: usize,
+        component_block: &[[f32; N]; N],
+    ) {
+        let component_index = match component {
+            YCbCrComponent::Luma => {
+                return self.write_luminance_block_to_rgb(
+                    rgb_image,
+                    block_x,
+                    block_y,
+                    component_block,
+                )
+            }
+            YCbCrComponent::BlueChroma => 1,
+            YCbCrComponent::RedChroma => 2,
+        };
 
-                // Write modified luminance back to RGB image
-                self.write_luminance_block_to_rgb(
-                    &mut steganographic_image,
-                    block_x as usize,
-                    block_y as usize,
-                    &luminance_block,
+        let (image_width, image_height) = rgb_image.dimensions();
+        for (y, component_row) in component_block.iter().enumerate() {
+            for (x, component_value) in component_row.iter().enumerate() {
+                let cell_x = block_x + x * self.macro_block_scale;
+                let cell_y = block_y + y * self.macro_block_scale;
+                let cell_component = self.cell_mean(
+                    image_width,
+                    image_height,
+                    cell_x,
+                    cell_y,
+                    |pixel_x, pixel_y| {
+                        rgb_to_ycbcr(rgb_image.get_pixel(pixel_x, pixel_y))[component_index]
+                    },
                 );
+                let cell_delta = component_value - cell_component;
+
+                for (pixel_x, pixel_y) in
+                    self.cell_pixels(image_width, image_height, cell_x, cell_y)
+                {
+                    let mut ycbcr_pixel = rgb_to_ycbcr(rgb_image.get_pixel(pixel_x, pixel_y));
+                    ycbcr_pixel[component_index] += cell_delta;
+                    rgb_image.put_pixel(pixel_x, pixel_y, ycbcr_to_rgb(ycbcr_pixel));
+                }
             }
         }
-
-        Ok(steganographic_image)
     }
 
     /// Extracts luminance values from RGB block for DCT processing
@@ -244,24 +895,21 @@ impl SteganographyEngine {
         rgb_image: &RgbImage,
         block_x: usize,
         block_y: usize,
-    ) -> [[f32; 8]; 8] {
-        let mut luminance_block = [[0f32; 8]; 8];
-
-        for y in 0..self.configuration.block_size {
-            for x in 0..self.configuration.block_size {
-                let pixel_x = (block_x + x) as u32;
-                let pixel_y = (block_y + y) as u32;
-
-                // Handle boundary conditions by using edge pixels
-                let actual_x = pixel_x.min(rgb_image.width() - 1);
-                let actual_y = pixel_y.min(rgb_image.height() - 1);
-
-                let rgb_pixel = rgb_image.get_pixel(actual_x, actual_y);
-                // Convert RGB to luminance using ITU-R BT.709 standard
-                let luminance_value = 0.299 * rgb_pixel[0] as f32
-                    + 0.587 * rgb_pixel[1] as f32
-                    + 0.114 * rgb_pixel[2] as f32;
-                luminance_block[y][x] = luminance_value;
+    ) -> [[f32; N]; N] {
+        let color_standard = self.embedding_algorithm.color_standard();
+        let mut luminance_block = [[0f32; N]; N];
+
+        for (y, luminance_row) in luminance_block.iter_mut().enumerate() {
+            for (x, luminance_value) in luminance_row.iter_mut().enumerate() {
+                *luminance_value = self.cell_mean(
+                    rgb_image.width(),
+                    rgb_image.height(),
+                    block_x + x * self.macro_block_scale,
+                    block_y + y * self.macro_block_scale,
+                    |pixel_x, pixel_y| {
+                        rgb_luminance(rgb_image.get_pixel(pixel_x, pixel_y), color_standard)
+                    },
+                );
             }
         }
         luminance_block
@@ -273,162 +921,406 @@ impl SteganographyEngine {
         rgb_image: &mut RgbImage,
         block_x: usize,
         block_y: usize,
-        luminance_block: &[[f32; 8]; 8],
+        luminance_block: &[[f32; N]; N],
     ) {
-        for y in 0..self.configuration.block_size {
-            for x in 0..self.configuration.block_size {
-                let pixel_x = (block_x + x) as u32;
-                let pixel_y = (block_y + y) as u32;
+        let (image_width, image_height) = rgb_image.dimensions();
+        let color_standard = self.embedding_algorithm.color_standard();
+        for (y, luminance_row) in luminance_block.iter().enumerate() {
+            for (x, luminance_value) in luminance_row.iter().enumerate() {
+                let cell_x = block_x + x * self.macro_block_scale;
+                let cell_y = block_y + y * self.macro_block_scale;
+                let cell_luminance = self.cell_mean(
+                    image_width,
+                    image_height,
+                    cell_x,
+                    cell_y,
+                    |pixel_x, pixel_y| {
+                        rgb_luminance(rgb_image.get_pixel(pixel_x, pixel_y), color_standard)
+                    },
+                );
+                let cell_delta = luminance_value - cell_luminance;
 
                 // Only modify pixels within image bounds
-                if pixel_x < rgb_image.width() && pixel_y < rgb_image.height() {
+                for (pixel_x, pixel_y) in
+                    self.cell_pixels(image_width, image_height, cell_x, cell_y)
+                {
                     let original_rgb = rgb_image.get_pixel(pixel_x, pixel_y);
-                    let original_luminance = 0.299 * original_rgb[0] as f32
-                        + 0.587 * original_rgb[1] as f32
-                        + 0.114 * original_rgb[2] as f32;
-                    let new_luminance = luminance_block[y][x].round().clamp(0.0, 255.0);
+                    let original_luminance = rgb_luminance(original_rgb, color_standard);
+                    let new_luminance = (original_luminance + cell_delta).round().clamp(0.0, 255.0);
 
                     // Calculate luminance change
                     let luminance_delta = new_luminance - original_luminance;
 
-                    // Distribute luminance change across RGB channels to maintain color balance
-                    let new_red = (original_rgb[0] as f32 + luminance_delta * 0.2)
-                        .round()
-                        .clamp(0.0, 255.0) as u8;
-                    let new_green = (original_rgb[1] as f32 + luminance_delta * 0.6)
-                        .round()
-                        .clamp(0.0, 255.0) as u8;
-                    let new_blue = (original_rgb[2] as f32 + luminance_delta * 0.2)
-                        .round()
-                        .clamp(0.0, 255.0) as u8;
-
-                    rgb_image.put_pixel(pixel_x, pixel_y, Rgb([new_red, new_green, new_blue]));
-                }
-            }
-        }
-    }
+                    // Luminance weights sum to one in either standard, so adding the change to
+                    // every channel moves luminance by exactly that change and leaves
+                    // chrominance as it was
+                    let new_rgb = original_rgb.0.map(|channel| {
+                        (channel as f32 + luminance_delta).round().clamp(0.0, 255.0) as u8
+                    });
 
-    /// Embeds a bit robustly using multiple DCT coefficients for redundancy
-    fn embed_bit_robustly(
-        &self,
-        dct_block: &mut [[f32; 8]; 8],
-        bit_value: u8,
-        quantization_table: &[[f32; 8]; 8],
-    ) {
-        // Use multiple positions for redundancy (first 4 positions)
-        let positions_to_use = &self.configuration.embedding_positions
-            [..4.min(self.configuration.embedding_positions.len())];
-
-        for &(coefficient_y, coefficient_x) in positions_to_use {
-            let coefficient = &mut dct_block[coefficient_y][coefficient_x];
-            let quantization_step = quantization_table[coefficient_y][coefficient_x]
-                .max(self.configuration.minimum_quantization_step);
-            let embedding_strength = self
-                .configuration
-                .embedding_strength
-                .max(quantization_step * 3.0);
-
-            // Use strong coefficient modification for JPEG compression survival
-            if bit_value == 1 {
-                *coefficient = embedding_strength; // Strongly positive for bit 1
-            } else {
-                *coefficient = -embedding_strength; // Strongly negative for bit 0
+                    rgb_image.put_pixel(pixel_x, pixel_y, Rgb(new_rgb));
+                }
             }
         }
     }
 
     /// Extracts encrypted data from RGB steganographic image
     pub fn extract_data_from_rgb_image(
-        &mut self,
+        &self,
         steganographic_image: &RgbImage,
         expected_data_length: Option<usize>,
-    ) -> Result<Vec<u8>> {
-        let mut extracted_bits = Vec::new();
-        let total_capacity = self.calculate_capacity_bits(steganographic_image);
-
-        // Extract bits from all blocks
-        for block_y in (0..steganographic_image.height()).step_by(self.configuration.block_size) {
-            for block_x in (0..steganographic_image.width()).step_by(self.configuration.block_size)
-            {
-                let mut luminance_block = self.extract_luminance_block_from_rgb(
-                    steganographic_image,
-                    block_x as usize,
-                    block_y as usize,
-                );
-                self.dct_processor.apply_forward_dct(&mut luminance_block)?;
-
-                // Extract bit using robust method
-                let extracted_bit = self.extract_bit_robustly(&luminance_block);
-                extracted_bits.push(extracted_bit);
+    ) -> Result<ExtractionResult> {
+        let total_capacity = self.calculate_capacity_bits(steganographic_image)?;
+        let (extracted_bits, bit_confidences) =
+            self.extract_payload_bits(total_capacity, expected_data_length, |bits_to_extract| {
+                self.extract_bits_from_rgb_image(steganographic_image, bits_to_extract)
+            })?;
 
-                // Early termination if we have expected length
-                if let Some(expected_length) = expected_data_length {
-                    if extracted_bits.len() >= 32 + expected_length * 8 {
-                        break;
-                    }
-                }
+        diagnostic!("Extracted {} bits total", extracted_bits.len());
 
-                // Try to determine actual length from header
-                if extracted_bits.len() >= 32 && expected_data_length.is_none() {
-                    let mut header_length = 0u32;
-                    for bit_index in 0..32 {
-                        header_length = (header_length << 1) | (extracted_bits[bit_index] as u32);
-                    }
+        Ok(self.build_extraction_result(&extracted_bits, bit_confidences))
+    }
 
-                    let total_bits_needed = 32 + (header_length as usize * 8);
-                    if header_length > 0
-                        && header_length < (total_capacity / 8) as u32
-                        && extracted_bits.len() >= total_bits_needed
-                    {
-                        extracted_bits.truncate(total_bits_needed);
-                        break;
-                    }
-                }
-            }
+    /// Hides encrypted data in a decoded image of any pixel format
+    ///
+    /// Bits go into the color channels as [`Self::hide_data_in_rgb_image`] puts them. An
+    /// alpha channel is copied through untouched, and a grayscale carrier stays grayscale
+    /// unless chroma channels carry bits. Images with 16-bit or floating-point channels are
+    /// converted to 8 bits per channel.
+    pub fn hide_data_in_dynamic_image(
+        &self,
+        source_image: &DynamicImage,
+        encrypted_data: &[u8],
+        jpeg_quality: u8,
+    ) -> Result<DynamicImage> {
+        let steganographic_image =
+            self.hide_data_in_rgb_image(&source_image.to_rgb8(), encrypted_data, jpeg_quality)?;
+        let color_type = source_image.color();
+        // Luminance changes are written equally to every channel, so gray pixels stay gray
+        let stays_grayscale = !color_type.has_color()
+            && !self
+                .embedding_algorithm
+                .embedding_channel()
+                .includes_chroma();
+        if !color_type.has_alpha() {
+            return Ok(if stays_grayscale {
+                DynamicImage::ImageLuma8(GrayImage::from_fn(
+                    steganographic_image.width(),
+                    steganographic_image.height(),
+                    |x, y| Luma([steganographic_image.get_pixel(x, y)[0]]),
+                ))
+            } else {
+                DynamicImage::ImageRgb8(steganographic_image)
+            });
         }
 
-        println!("Extracted {} bits total", extracted_bits.len());
-
-        self.convert_bits_to_data_with_header(&extracted_bits)
+        let alpha_source = source_image.to_rgba8();
+        let alpha_at = |x, y| alpha_source.get_pixel(x, y)[3];
+        Ok(if stays_grayscale {
+            DynamicImage::ImageLumaA8(GrayAlphaImage::from_fn(
+                steganographic_image.width(),
+                steganographic_image.height(),
+                |x, y| LumaA([steganographic_image.get_pixel(x, y)[0], alpha_at(x, y)]),
+            ))
+        } else {
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(
+                steganographic_image.width(),
+                steganographic_image.height(),
+                |x, y| {
+                    let Rgb([red, green, blue]) = *steganographic_image.get_pixel(x, y);
+                    Rgba([red, green, blue, alpha_at(x, y)])
+                },
+            ))
+        })
     }
 
-    /// Extracts a bit robustly using majority voting from multiple coefficients
-    fn extract_bit_robustly(&self, dct_block: &[[f32; 8]; 8]) -> u8 {
-        // Use multiple positions for majority voting to improve reliability
-        let positions_to_check = &self.configuration.embedding_positions
-            [..4.min(self.configuration.embedding_positions.len())];
+    /// Extracts encrypted data from a decoded image of any pixel format
+    ///
+    /// Reads the color channels written by [`Self::hide_data_in_dynamic_image`]; alpha is
+    /// ignored, so use [`Self::extract_data_from_rgba_alpha`] for the alpha channel mode.
+    pub fn extract_data_from_dynamic_image(
+        &self,
+        steganographic_image: &DynamicImage,
+        expected_data_length: Option<usize>,
+    ) -> Result<ExtractionResult> {
+        self.extract_data_from_rgb_image(&steganographic_image.to_rgb8(), expected_data_length)
+    }
 
-        let mut votes_for_1 = 0;
-        let mut votes_for_0 = 0;
+    /// Extracts a length-header frame, reading only the blocks it occupies
+    ///
+    /// The frame header is decoded once from the leading blocks, which every visitation order
+    /// reads first, and the frame length it claims with `repetition_factor` decides how many
+    /// blocks are read in total. If the header doesn't parse or claims more than the image
+    /// holds, the whole image is read so the crypto layer can report what is wrong.
+    pub fn extract_frame_from_rgb_image(
+        &self,
+        steganographic_image: &RgbImage,
+        repetition_factor: usize,
+    ) -> Result<ExtractionResult> {
+        let total_capacity = self.calculate_capacity_bits(steganographic_image)?;
+        let payload_capacity_bytes =
+            total_capacity.saturating_sub(self.protected_header_overhead_bits()) / 8;
+        let (header_bits, _) = self.extract_payload_bits(
+            total_capacity,
+            Some(MAX_FRAME_HEADER_LENGTH.min(payload_capacity_bytes)),
+            |bits_to_extract| {
+                self.extract_bits_from_rgb_image(steganographic_image, bits_to_extract)
+            },
+        )?;
+
+        let frame_length = FrameHeader::from_bytes(&self.convert_bits_to_data(&header_bits))
+            .ok()
+            .and_then(|frame_header| frame_header.frame_length(repetition_factor))
+            .filter(|&frame_length| frame_length <= payload_capacity_bytes);
+        if let Some(frame_length) = frame_length {
+            diagnostic!(
+                "Frame header claims {} bytes; reading {} of {} blocks",
+                frame_length,
+                self.embedded_bits_for_payload(frame_length * 8),
+                total_capacity
+            );
+        }
+        self.extract_data_from_rgb_image(steganographic_image, frame_length)
+    }
 
-        for &(coefficient_y, coefficient_x) in positions_to_check {
-            let coefficient_value = dct_block[coefficient_y][coefficient_x];
+    /// Hides encrypted data split across several carriers, one part per carrier
+    ///
+    /// Each carrier gets a share of the stream in proportion to its capacity, behind a
+    /// [`PartHeader`] recording where the share belongs, so the parts can be reassembled by
+    /// [`Self::extract_data_from_rgb_images`] whatever order the images come back in.
+    pub fn hide_data_across_rgb_images(
+        &self,
+        source_images: &[RgbImage],
+        encrypted_data: &[u8],
+        jpeg_quality: u8,
+    ) -> Result<Vec<RgbImage>> {
+        let part_count = u16::try_from(source_images.len())
+            .ok()
+            .filter(|&part_count| part_count > 0)
+            .ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "a split payload needs between 1 and {} carriers",
+                    u16::MAX
+                ))
+            })?;
+
+        // Every part carries its own header, which comes out of that carrier's capacity
+        let carrier_capacities = source_images
+            .iter()
+            .map(|source_image| self.calculate_capacity_bits(source_image))
+            .collect::<Result<Vec<usize>>>()?;
+        let chunk_capacities: Vec<usize> = carrier_capacities
+            .iter()
+            .map(|carrier_capacity| (carrier_capacity / 8).saturating_sub(PART_HEADER_SIZE))
+            .collect();
+        let total_chunk_capacity: usize = chunk_capacities.iter().sum();
+        if encrypted_data.len() > total_chunk_capacity {
+            return Err(SteganographyError::CapacityError {
+                required: (encrypted_data.len() + source_images.len() * PART_HEADER_SIZE) * 8,
+                available: carrier_capacities.iter().sum(),
+            });
+        }
 
-            // Use a more conservative threshold
-            if coefficient_value > 10.0 {
-                votes_for_1 += 1;
-            } else if coefficient_value < -10.0 {
-                votes_for_0 += 1;
-            }
-            // Values between -10 and 10 are considered neutral (no vote)
+        // Proportional shares round down, so hand the leftover bytes to carriers with room
+        let mut chunk_lengths: Vec<usize> = chunk_capacities
+            .iter()
+            .map(|&chunk_capacity| {
+                (encrypted_data.len() as u128 * chunk_capacity as u128
+                    / total_chunk_capacity.max(1) as u128) as usize
+            })
+            .collect();
+        let mut leftover_bytes = encrypted_data.len() - chunk_lengths.iter().sum::<usize>();
+        for (chunk_length, &chunk_capacity) in chunk_lengths.iter_mut().zip(&chunk_capacities) {
+            let extra_bytes = leftover_bytes.min(chunk_capacity - *chunk_length);
+            *chunk_length += extra_bytes;
+            leftover_bytes -= extra_bytes;
         }
 
-        // If we have votes, use majority decision
-        if votes_for_1 > votes_for_0 {
-            1
-        } else if votes_for_0 > votes_for_1 {
-            0
-        } else {
-            // If tied or no clear votes, check the primary coefficient with lower threshold
-            let (primary_y, primary_x) = self.configuration.embedding_positions[0];
-            let primary_value = dct_block[primary_y][primary_x];
+        let mut remaining_data = encrypted_data;
+        source_images
+            .iter()
+            .zip(chunk_lengths)
+            .enumerate()
+            .map(|(part_index, (source_image, chunk_length))| {
+                let (chunk, rest) = remaining_data.split_at(chunk_length);
+                remaining_data = rest;
+                let part_header = PartHeader {
+                    part_index: part_index as u16,
+                    part_count,
+                    chunk_length: u32::try_from(chunk_length).map_err(|_| {
+                        SteganographyError::InvalidInput(
+                            "split payload part is too large for its header".to_string(),
+                        )
+                    })?,
+                };
+
+                let mut part_data = part_header.to_bytes().to_vec();
+                part_data.extend_from_slice(chunk);
+                diagnostic!(
+                    "Part {} of {}: {} bytes of the encrypted stream",
+                    part_index + 1,
+                    part_count,
+                    chunk_length
+                );
+                self.hide_data_in_rgb_image(source_image, &part_data, jpeg_quality)
+            })
+            .collect()
+    }
 
-            if primary_value > 0.0 {
-                1
-            } else {
-                0
+    /// Reassembles encrypted data hidden by [`Self::hide_data_across_rgb_images`]
+    ///
+    /// The images may be given in any order. Fails naming the first missing part index if
+    /// any part of the split is absent.
+    pub fn extract_data_from_rgb_images(
+        &self,
+        steganographic_images: &[RgbImage],
+    ) -> Result<ExtractionResult> {
+        let mut parts: Vec<Option<ExtractionResult>> = Vec::new();
+        for steganographic_image in steganographic_images {
+            let extraction_result = self.extract_data_from_rgb_image(steganographic_image, None)?;
+            let part_header = PartHeader::from_bytes(&extraction_result.data)?;
+            if parts.is_empty() {
+                parts = vec![None; part_header.part_count as usize];
+            } else if parts.len() != part_header.part_count as usize {
+                return Err(SteganographyError::InvalidInput(format!(
+                    "the images come from different splits: one has {} parts, another {}",
+                    parts.len(),
+                    part_header.part_count
+                )));
+            }
+
+            let chunk_end = PART_HEADER_SIZE + part_header.chunk_length as usize;
+            if chunk_end > extraction_result.data.len() {
+                return Err(SteganographyError::InvalidInput(format!(
+                    "part index {} claims {} bytes but only {} are available",
+                    part_header.part_index,
+                    part_header.chunk_length,
+                    extraction_result.data.len() - PART_HEADER_SIZE
+                )));
+            }
+            let part = &mut parts[part_header.part_index as usize];
+            if part.is_some() {
+                return Err(SteganographyError::InvalidInput(format!(
+                    "part index {} was given more than once",
+                    part_header.part_index
+                )));
             }
+            *part = Some(ExtractionResult {
+                data: extraction_result.data[PART_HEADER_SIZE..chunk_end].to_vec(),
+                bit_confidences: extraction_result.bit_confidences
+                    [PART_HEADER_SIZE * 8..chunk_end * 8]
+                    .to_vec(),
+            });
+        }
+
+        if parts.is_empty() {
+            return Err(SteganographyError::InvalidInput(
+                "no images given to reassemble a split payload from".to_string(),
+            ));
+        }
+        let part_count = parts.len();
+        let mut joined_result = ExtractionResult {
+            data: Vec::new(),
+            bit_confidences: Vec::new(),
+        };
+        for (part_index, part) in parts.into_iter().enumerate() {
+            let part = part.ok_or_else(|| {
+                SteganographyError::InvalidInput(format!(
+                    "part index {} of {} is missing; pass every image of the split",
+                    part_index, part_count
+                ))
+            })?;
+            joined_result.data.extend(part.data);
+            joined_result.bit_confidences.extend(part.bit_confidences);
         }
+        Ok(joined_result)
+    }
+
+    /// Reads the block visitation order recorded in the frame header
+    ///
+    /// The header region is visited in the same order whatever the body uses, so this works
+    /// before the engine is set to the recorded order.
+    pub fn read_block_visitation(
+        &self,
+        steganographic_image: &RgbImage,
+    ) -> Result<BlockVisitation> {
+        let total_capacity = self.calculate_capacity_bits(steganographic_image)?;
+        let (header_bits, _) = self.extract_payload_bits(
+            total_capacity,
+            Some(MAX_FRAME_HEADER_LENGTH),
+            |bits_to_extract| {
+                self.extract_bits_from_rgb_image(steganographic_image, bits_to_extract)
+            },
+        )?;
+
+        let frame_header = FrameHeader::from_bytes(&self.convert_bits_to_data(&header_bits))?;
+        Ok(frame_header.block_visitation)
+    }
+
+    /// Extracts every embedded bit without packing, decoding or decryption
+    ///
+    /// Replicated header copies are returned as embedded, not merged. Comparing the bits read
+    /// from a pristine and a recompressed copy with [`bit_error_rate`] measures robustness
+    /// directly.
+    pub fn extract_raw_bits(&self, steganographic_image: &RgbImage) -> Result<Vec<u8>> {
+        let total_capacity = self.calculate_capacity_bits(steganographic_image)?;
+        let (extracted_bits, _) =
+            self.extract_bits_from_rgb_image(steganographic_image, total_capacity)?;
+        Ok(extracted_bits)
+    }
+
+    /// Reads bits and their confidences from the first `bits_to_extract` blocks
+    fn extract_bits_from_rgb_image(
+        &self,
+        steganographic_image: &RgbImage,
+        bits_to_extract: usize,
+    ) -> Result<(Vec<u8>, Vec<f32>)> {
+        self.extract_indexed_bits(
+            self.channel_block_coordinates(
+                steganographic_image.width(),
+                steganographic_image.height(),
+            ),
+            bits_to_extract,
+            |(component, (block_x, block_y))| {
+                self.extract_component_block_from_rgb(
+                    steganographic_image,
+                    component,
+                    block_x as usize,
+                    block_y as usize,
+                )
+            },
+        )
+    }
+
+    /// Decodes each block independently and reassembles the bits in block index order
+    ///
+    /// Every result is tagged with the index of the block it came from and sorted by that
+    /// index before use, so the bit stream (and the header parsed from it) is identical
+    /// however the blocks end up being visited.
+    fn extract_indexed_bits<L: Send>(
+        &self,
+        block_locations: impl Iterator<Item = L>,
+        bits_to_extract: usize,
+        read_block: impl Fn(L) -> [[f32; N]; N] + Sync + Send,
+    ) -> Result<(Vec<u8>, Vec<f32>)> {
+        let indexed_locations: Vec<_> = block_locations.take(bits_to_extract).enumerate().collect();
+        let progress = BlockProgress::start(indexed_locations.len(), "Extracting");
+        let mut indexed_bits =
+            process_blocks(indexed_locations, |(block_index, block_location)| {
+                let block = read_block(block_location);
+                progress.advance();
+                self.embedding_algorithm
+                    .extract_bit(block_index, &block)
+                    .map(|extracted_bit| (block_index, extracted_bit))
+            })
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+        indexed_bits.sort_unstable_by_key(|&(block_index, _)| block_index);
+
+        Ok(indexed_bits
+            .into_iter()
+            .map(|(_, extracted_bit)| (extracted_bit.bit_value, extracted_bit.confidence))
+            .unzip())
     }
 
     /// Saves RGB image as JPEG with specified quality
@@ -438,13 +1330,55 @@ impl SteganographyEngine {
         output_path: &str,
         jpeg_quality: u8,
     ) -> Result<()> {
-        let mut jpeg_buffer = Vec::new();
-        let jpeg_encoder = Encoder::new(&mut jpeg_buffer, jpeg_quality);
+        let jpeg_buffer = self.encode_rgb_image_as_jpeg(rgb_image, jpeg_quality)?;
+        std::fs::write(output_path, jpeg_buffer)?;
+        Ok(())
+    }
 
-        // Convert RGB image to byte array
-        let rgb_data: Vec<u8> = rgb_image
-            .pixels()
-            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+    /// Saves RGB image as a lossless PNG, so the embedded coefficients are kept exactly
+    pub fn save_rgb_image_as_png(&self, rgb_image: &RgbImage, output_path: &str) -> Result<()> {
+        rgb_image
+            .save_with_format(output_path, image::ImageFormat::Png)
+            .map_err(|error| SteganographyError::ImageError(error.to_string()))
+    }
+
+    /// Saves RGB image as a lossless WebP
+    pub fn save_rgb_image_as_webp(&self, rgb_image: &RgbImage, output_path: &str) -> Result<()> {
+        let mut webp_buffer = Vec::new();
+        WebPEncoder::new_lossless(&mut webp_buffer)
+            .encode(
+                rgb_image.as_raw(),
+                rgb_image.width(),
+                rgb_image.height(),
+                image::ColorType::Rgb8,
+            )
+            .map_err(|error| SteganographyError::ImageError(error.to_string()))?;
+        std::fs::write(output_path, webp_buffer)?;
+        Ok(())
+    }
+
+    /// Encodes RGB image as JPEG in memory with specified quality
+    pub fn encode_rgb_image_as_jpeg(
+        &self,
+        rgb_image: &RgbImage,
+        jpeg_quality: u8,
+    ) -> Result<Vec<u8>> {
+        let mut jpeg_buffer = Vec::new();
+        let mut jpeg_encoder = Encoder::new(&mut jpeg_buffer, jpeg_quality);
+
+        // Subsampled chroma would average away the bits embedded in it
+        if self
+            .embedding_algorithm
+            .embedding_channel()
+            .includes_chroma()
+        {
+            jpeg_encoder.set_sampling_factor(SamplingFactor::F_1_1);
+        }
+
+        // Convert RGB image to byte array
+        let rgb_data: Vec<u8> = rgb_image
+            .pixels()
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
             .collect();
 
         jpeg_encoder
@@ -456,21 +1390,31 @@ impl SteganographyEngine {
             )
             .map_err(|error| SteganographyError::ImageError(error.to_string()))?;
 
-        std::fs::write(output_path, jpeg_buffer)?;
-        Ok(())
+        Ok(jpeg_buffer)
     }
 
-    // Legacy methods for grayscale image support
+    /// Calculates how many bits fit in the alpha channel of an RGBA image
+    ///
+    /// Only fully opaque pixels carry a bit; changing the alpha of partially transparent
+    /// pixels would be visible where the image is composited.
+    pub fn calculate_alpha_capacity_bits(&self, rgba_image: &RgbaImage) -> usize {
+        rgba_image
+            .pixels()
+            .filter(|pixel| pixel[3] >= OPAQUE_ALPHA_THRESHOLD)
+            .count()
+    }
 
-    /// Hides data in grayscale image (legacy method)
-    pub fn hide_data_in_grayscale_image(
-        &mut self,
-        source_image: &GrayImage,
+    /// Hides data in the alpha channel least significant bits of an RGBA image
+    ///
+    /// Bits are written to opaque pixels in raster order. This is a lossless mode: the result
+    /// must be saved in a lossless format such as PNG and does not survive recompression.
+    pub fn hide_data_in_rgba_alpha(
+        &self,
+        source_image: &RgbaImage,
         encrypted_data: &[u8],
-        jpeg_quality: u8,
-    ) -> Result<GrayImage> {
-        let bit_stream = self.convert_data_to_bits_with_header(encrypted_data);
-        let available_capacity = self.calculate_grayscale_capacity_bits(source_image);
+    ) -> Result<RgbaImage> {
+        let bit_stream = self.convert_data_to_bits(encrypted_data);
+        let available_capacity = self.calculate_alpha_capacity_bits(source_image);
 
         if bit_stream.len() > available_capacity {
             return Err(SteganographyError::CapacityError {
@@ -479,78 +1423,141 @@ impl SteganographyEngine {
             });
         }
 
-        let quantization_table = self.calculate_quantization_table(jpeg_quality);
         let mut steganographic_image = source_image.clone();
-        let mut current_bit_index = 0;
+        let opaque_pixels = steganographic_image
+            .pixels_mut()
+            .filter(|pixel| pixel[3] >= OPAQUE_ALPHA_THRESHOLD);
+        for (pixel, &bit_value) in opaque_pixels.zip(&bit_stream) {
+            pixel[3] = OPAQUE_ALPHA_THRESHOLD | bit_value;
+        }
 
-        for block_y in (0..source_image.height()).step_by(self.configuration.block_size) {
-            for block_x in (0..source_image.width()).step_by(self.configuration.block_size) {
-                if current_bit_index >= bit_stream.len() {
-                    return Ok(steganographic_image);
-                }
+        Ok(steganographic_image)
+    }
 
-                let mut grayscale_block =
-                    self.extract_grayscale_block(source_image, block_x as usize, block_y as usize);
+    /// Extracts data from the alpha channel least significant bits of an RGBA image
+    ///
+    /// The channel is read losslessly, so every bit is reported with full confidence.
+    pub fn extract_data_from_rgba_alpha(
+        &self,
+        steganographic_image: &RgbaImage,
+        expected_data_length: Option<usize>,
+    ) -> Result<ExtractionResult> {
+        let total_capacity = self.calculate_alpha_capacity_bits(steganographic_image);
+        let bits_to_extract =
+            self.calculate_bits_to_extract(total_capacity, expected_data_length)?;
+        let extracted_bits: Vec<u8> = steganographic_image
+            .pixels()
+            .filter(|pixel| pixel[3] >= OPAQUE_ALPHA_THRESHOLD)
+            .take(bits_to_extract)
+            .map(|pixel| pixel[3] & 1)
+            .collect();
+        let bit_confidences = vec![1.0; extracted_bits.len()];
 
-                self.dct_processor.apply_forward_dct(&mut grayscale_block)?;
+        Ok(self.build_extraction_result(&extracted_bits, bit_confidences))
+    }
 
-                let bit_to_embed = bit_stream[current_bit_index];
-                self.embed_bit_robustly(&mut grayscale_block, bit_to_embed, &quantization_table);
+    // Legacy methods for grayscale image support
 
-                current_bit_index += 1;
+    /// Hides data in grayscale image (legacy method)
+    pub fn hide_data_in_grayscale_image(
+        &self,
+        source_image: &GrayImage,
+        encrypted_data: &[u8],
+        jpeg_quality: u8,
+    ) -> Result<GrayImage> {
+        let bit_stream = self.replicate_protected_header(self.convert_data_to_bits(encrypted_data));
+        self.check_minimum_dimensions(
+            source_image.width(),
+            source_image.height(),
+            bit_stream.len(),
+            1,
+        )?;
+        let available_capacity = self.calculate_grayscale_capacity_bits(source_image)?;
 
-                self.dct_processor.apply_inverse_dct(&mut grayscale_block)?;
+        if bit_stream.len() > available_capacity {
+            return Err(SteganographyError::CapacityError {
+                required: bit_stream.len(),
+                available: available_capacity,
+            });
+        }
 
-                self.write_grayscale_block(
-                    &mut steganographic_image,
-                    block_x as usize,
-                    block_y as usize,
-                    &grayscale_block,
-                );
-            }
+        let mut steganographic_image = source_image.clone();
+
+        let block_coordinates = self.block_coordinates(source_image.width(), source_image.height());
+        let progress = BlockProgress::start(bit_stream.len(), "Embedding");
+        for (bit_index, (block_x, block_y)) in block_coordinates.take(bit_stream.len()).enumerate()
+        {
+            let mut grayscale_block =
+                self.extract_grayscale_block(source_image, block_x as usize, block_y as usize);
+
+            self.embedding_algorithm.embed_bit(
+                bit_index,
+                &mut grayscale_block,
+                bit_stream[bit_index],
+                jpeg_quality,
+            )?;
+
+            self.write_grayscale_block(
+                &mut steganographic_image,
+                block_x as usize,
+                block_y as usize,
+                &grayscale_block,
+            );
+            progress.advance();
         }
 
         Ok(steganographic_image)
     }
 
-    /// Extracts 8x8 grayscale block from image
+    /// Extracts an NxN grayscale block from image
     fn extract_grayscale_block(
         &self,
         grayscale_image: &GrayImage,
         block_x: usize,
         block_y: usize,
-    ) -> [[f32; 8]; 8] {
-        let mut grayscale_block = [[0f32; 8]; 8];
-        for y in 0..self.configuration.block_size {
-            for x in 0..self.configuration.block_size {
-                let pixel_x = (block_x + x) as u32;
-                let pixel_y = (block_y + y) as u32;
-
-                let actual_x = pixel_x.min(grayscale_image.width() - 1);
-                let actual_y = pixel_y.min(grayscale_image.height() - 1);
-
-                let pixel_value = grayscale_image.get_pixel(actual_x, actual_y)[0];
-                grayscale_block[y][x] = pixel_value as f32;
+    ) -> [[f32; N]; N] {
+        let mut grayscale_block = [[0f32; N]; N];
+        for (y, grayscale_row) in grayscale_block.iter_mut().enumerate() {
+            for (x, grayscale_value) in grayscale_row.iter_mut().enumerate() {
+                *grayscale_value = self.cell_mean(
+                    grayscale_image.width(),
+                    grayscale_image.height(),
+                    block_x + x * self.macro_block_scale,
+                    block_y + y * self.macro_block_scale,
+                    |pixel_x, pixel_y| grayscale_image.get_pixel(pixel_x, pixel_y)[0] as f32,
+                );
             }
         }
         grayscale_block
     }
 
-    /// Writes 8x8 grayscale block back to image
+    /// Writes an NxN grayscale block back to image
     fn write_grayscale_block(
         &self,
         grayscale_image: &mut GrayImage,
         block_x: usize,
         block_y: usize,
-        grayscale_block: &[[f32; 8]; 8],
+        grayscale_block: &[[f32; N]; N],
     ) {
-        for y in 0..self.configuration.block_size {
-            for x in 0..self.configuration.block_size {
-                let pixel_x = (block_x + x) as u32;
-                let pixel_y = (block_y + y) as u32;
-
-                if pixel_x < grayscale_image.width() && pixel_y < grayscale_image.height() {
-                    let pixel_value = grayscale_block[y][x].round().clamp(0.0, 255.0) as u8;
+        let (image_width, image_height) = grayscale_image.dimensions();
+        for (y, grayscale_row) in grayscale_block.iter().enumerate() {
+            for (x, grayscale_value) in grayscale_row.iter().enumerate() {
+                let cell_x = block_x + x * self.macro_block_scale;
+                let cell_y = block_y + y * self.macro_block_scale;
+                let cell_delta = grayscale_value
+                    - self.cell_mean(
+                        image_width,
+                        image_height,
+                        cell_x,
+                        cell_y,
+                        |pixel_x, pixel_y| grayscale_image.get_pixel(pixel_x, pixel_y)[0] as f32,
+                    );
+
+                for (pixel_x, pixel_y) in
+                    self.cell_pixels(image_width, image_height, cell_x, cell_y)
+                {
+                    let original_value = grayscale_image.get_pixel(pixel_x, pixel_y)[0] as f32;
+                    let pixel_value = (original_value + cell_delta).round().clamp(0.0, 255.0) as u8;
                     grayscale_image.put_pixel(pixel_x, pixel_y, Luma([pixel_value]));
                 }
             }
@@ -559,102 +1566,383 @@ impl SteganographyEngine {
 
     /// Extracts data from grayscale steganographic image (legacy)
     pub fn extract_data_from_grayscale_image(
-        &mut self,
+        &self,
         steganographic_image: &GrayImage,
         expected_data_length: Option<usize>,
-    ) -> Result<Vec<u8>> {
-        let mut extracted_bits = Vec::new();
-        let total_capacity = self.calculate_grayscale_capacity_bits(steganographic_image);
+    ) -> Result<ExtractionResult> {
+        let total_capacity = self.calculate_grayscale_capacity_bits(steganographic_image)?;
+        let (extracted_bits, bit_confidences) =
+            self.extract_payload_bits(total_capacity, expected_data_length, |bits_to_extract| {
+                self.extract_bits_from_grayscale_image(steganographic_image, bits_to_extract)
+            })?;
+
+        Ok(self.build_extraction_result(&extracted_bits, bit_confidences))
+    }
 
-        for block_y in (0..steganographic_image.height()).step_by(self.configuration.block_size) {
-            for block_x in (0..steganographic_image.width()).step_by(self.configuration.block_size)
-            {
-                let mut grayscale_block = self.extract_grayscale_block(
+    /// Reads bits and their confidences from the first `bits_to_extract` grayscale blocks
+    fn extract_bits_from_grayscale_image(
+        &self,
+        steganographic_image: &GrayImage,
+        bits_to_extract: usize,
+    ) -> Result<(Vec<u8>, Vec<f32>)> {
+        self.extract_indexed_bits(
+            self.block_coordinates(steganographic_image.width(), steganographic_image.height()),
+            bits_to_extract,
+            |(block_x, block_y)| {
+                self.extract_grayscale_block(
                     steganographic_image,
                     block_x as usize,
                     block_y as usize,
-                );
-                self.dct_processor.apply_forward_dct(&mut grayscale_block)?;
-
-                let extracted_bit = self.extract_bit_robustly(&grayscale_block);
-                extracted_bits.push(extracted_bit);
-
-                if let Some(expected_length) = expected_data_length {
-                    if extracted_bits.len() >= 32 + expected_length * 8 {
-                        break;
-                    }
-                }
-
-                if extracted_bits.len() >= 32 && expected_data_length.is_none() {
-                    let mut header_length = 0u32;
-                    for bit_index in 0..32 {
-                        header_length = (header_length << 1) | (extracted_bits[bit_index] as u32);
-                    }
-
-                    let total_bits_needed = 32 + (header_length as usize * 8);
-                    if header_length > 0
-                        && header_length < (total_capacity / 8) as u32
-                        && extracted_bits.len() >= total_bits_needed
-                    {
-                        extracted_bits.truncate(total_bits_needed);
-                        break;
-                    }
-                }
-            }
-        }
-
-        self.convert_bits_to_data_with_header(&extracted_bits)
+                )
+            },
+        )
     }
 
-    /// Saves grayscale image as JPEG (legacy method)
+    /// Saves grayscale image as a single-channel JPEG (legacy method)
     pub fn save_grayscale_image_as_jpeg(
         &self,
         grayscale_image: &GrayImage,
         output_path: &str,
         jpeg_quality: u8,
     ) -> Result<()> {
+        let jpeg_buffer = self.encode_grayscale_image_as_jpeg(grayscale_image, jpeg_quality)?;
+        std::fs::write(output_path, jpeg_buffer)?;
+        Ok(())
+    }
+
+    /// Encodes grayscale image as a genuine single-channel JPEG in memory
+    fn encode_grayscale_image_as_jpeg(
+        &self,
+        grayscale_image: &GrayImage,
+        jpeg_quality: u8,
+    ) -> Result<Vec<u8>> {
         let mut jpeg_buffer = Vec::new();
         let jpeg_encoder = Encoder::new(&mut jpeg_buffer, jpeg_quality);
 
-        // Convert grayscale to RGB for JPEG encoding
-        let rgb_data: Vec<u8> = grayscale_image
-            .pixels()
-            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0]])
-            .collect();
-
         jpeg_encoder
             .encode(
-                &rgb_data,
+                grayscale_image.as_raw(),
                 grayscale_image.width() as u16,
                 grayscale_image.height() as u16,
-                ColorType::Rgb,
+                ColorType::Luma,
             )
             .map_err(|error| SteganographyError::ImageError(error.to_string()))?;
 
-        std::fs::write(output_path, jpeg_buffer)?;
-        Ok(())
+        Ok(jpeg_buffer)
+    }
+}
+
+/// Grid positions of a `grid_columns` by `grid_rows` block grid in visitation order
+///
+/// `block_order_seed` only affects the keyed order.
+fn grid_visitation_order(
+    block_visitation: BlockVisitation,
+    block_order_seed: &[u8; 32],
+    grid_columns: usize,
+    grid_rows: usize,
+) -> Vec<(usize, usize)> {
+    match block_visitation {
+        BlockVisitation::RowMajor => (0..grid_rows)
+            .flat_map(|grid_y| (0..grid_columns).map(move |grid_x| (grid_x, grid_y)))
+            .collect(),
+        BlockVisitation::ColumnMajor => (0..grid_columns)
+            .flat_map(|grid_x| (0..grid_rows).map(move |grid_y| (grid_x, grid_y)))
+            .collect(),
+        BlockVisitation::Zigzag => {
+            let diagonal_count = (grid_columns + grid_rows).saturating_sub(1);
+            (0..diagonal_count)
+                .flat_map(|diagonal| {
+                    // Positions on this anti-diagonal, from its top-right end to its bottom-left
+                    let first_row = diagonal.saturating_sub(grid_columns - 1);
+                    let last_row = diagonal.min(grid_rows - 1);
+                    let diagonal_positions =
+                        (first_row..=last_row).map(move |grid_y| (diagonal - grid_y, grid_y));
+                    // Even diagonals run upwards, odd ones downwards, like the JPEG scan
+                    if diagonal % 2 == 0 {
+                        diagonal_positions.rev().collect::<Vec<_>>()
+                    } else {
+                        diagonal_positions.collect()
+                    }
+                })
+                .collect()
+        }
+        BlockVisitation::Keyed => {
+            let mut grid_positions = grid_visitation_order(
+                BlockVisitation::RowMajor,
+                block_order_seed,
+                grid_columns,
+                grid_rows,
+            );
+            shuffle_with_keystream(&mut grid_positions, block_order_seed);
+            grid_positions
+        }
+    }
+}
+
+/// Applies a Fisher-Yates shuffle driven by the ChaCha20 keystream of `block_order_seed`
+fn shuffle_with_keystream<T>(items: &mut [T], block_order_seed: &[u8; 32]) {
+    let mut cipher = ChaCha20::new(block_order_seed.into(), &BLOCK_ORDER_NONCE.into());
+    for item_index in (1..items.len()).rev() {
+        let mut random_bytes = [0u8; 8];
+        cipher.apply_keystream(&mut random_bytes);
+        let swap_index = (u64::from_le_bytes(random_bytes) % (item_index as u64 + 1)) as usize;
+        items.swap(item_index, swap_index);
     }
 }
 
-impl Default for SteganographyEngine {
+/// Converts an RGB pixel to luminance using the weights of `color_standard`
+fn rgb_luminance(rgb_pixel: &Rgb<u8>, color_standard: ColorStandard) -> f32 {
+    let [red_weight, green_weight, blue_weight] = color_standard.luminance_weights();
+    red_weight * rgb_pixel[0] as f32
+        + green_weight * rgb_pixel[1] as f32
+        + blue_weight * rgb_pixel[2] as f32
+}
+
+/// Full-range JPEG (JFIF) YCbCr values of an RGB pixel, chroma centred on 128
+fn rgb_to_ycbcr(rgb_pixel: &Rgb<u8>) -> [f32; 3] {
+    let [red, green, blue] = rgb_pixel.0.map(f32::from);
+    [
+        rgb_luminance(rgb_pixel, ColorStandard::Bt601),
+        128.0 - 0.168736 * red - 0.331264 * green + 0.5 * blue,
+        128.0 + 0.5 * red - 0.418688 * green - 0.081312 * blue,
+    ]
+}
+
+/// Converts full-range YCbCr values back to an RGB pixel, rounding and clamping each channel
+fn ycbcr_to_rgb([luminance, blue_chroma, red_chroma]: [f32; 3]) -> Rgb<u8> {
+    let blue_difference = blue_chroma - 128.0;
+    let red_difference = red_chroma - 128.0;
+    Rgb([
+        luminance + 1.402 * red_difference,
+        luminance - 0.344136 * blue_difference - 0.714136 * red_difference,
+        luminance + 1.772 * blue_difference,
+    ]
+    .map(|channel_value| channel_value.round().clamp(0.0, 255.0) as u8))
+}
+
+/// Applies `process_block` to every block, on all cores when built with the `parallel` feature
+///
+/// Results are returned in the order of `blocks` either way.
+#[cfg(feature = "parallel")]
+fn process_blocks<B: Send, R: Send>(
+    blocks: Vec<B>,
+    process_block: impl Fn(B) -> R + Sync + Send,
+) -> Vec<R> {
+    use rayon::prelude::*;
+    blocks.into_par_iter().map(process_block).collect()
+}
+
+/// Applies `process_block` to every block in order
+#[cfg(not(feature = "parallel"))]
+fn process_blocks<B, R>(blocks: Vec<B>, process_block: impl Fn(B) -> R) -> Vec<R> {
+    blocks.into_iter().map(process_block).collect()
+}
+
+/// Fraction of positions at which two bit streams disagree
+///
+/// Only the overlapping prefix is compared; two empty streams have a rate of zero.
+pub fn bit_error_rate(reference_bits: &[u8], received_bits: &[u8]) -> f64 {
+    let compared_bits = reference_bits.len().min(received_bits.len());
+    if compared_bits == 0 {
+        return 0.0;
+    }
+
+    let bit_errors = reference_bits
+        .iter()
+        .zip(received_bits)
+        .filter(|(reference_bit, received_bit)| reference_bit != received_bit)
+        .count();
+    bit_errors as f64 / compared_bits as f64
+}
+
+/// Number of bytes of `reference_data` that were not received intact
+///
+/// Bytes missing from the end of `received_data` count as damaged.
+pub fn byte_error_count(reference_data: &[u8], received_data: &[u8]) -> usize {
+    let differing_bytes = reference_data
+        .iter()
+        .zip(received_data)
+        .filter(|(reference_byte, received_byte)| reference_byte != received_byte)
+        .count();
+    differing_bytes + reference_data.len().saturating_sub(received_data.len())
+}
+
+impl<const N: usize> Default for SteganographyEngine<DctEmbeddingAlgorithm<N>, N> {
     fn default() -> Self {
-        Self::new()
+        Self::with_algorithm(DctEmbeddingAlgorithm::default())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::{CryptographicEngine, FrameOptions};
+    use crate::embedding::{AdaptiveStrength, ExtractedBit};
     use image::{ImageBuffer, Rgb};
 
+    /// JPEG qualities the robustness harness recompresses at, from heavy to light
+    const ROBUSTNESS_SWEEP_QUALITIES: [u8; 10] = [50, 55, 60, 65, 70, 75, 80, 85, 90, 95];
+
+    /// Carrier with red and green ramps and a multiplicative blue pattern, for round-trip tests
+    fn gradient_carrier(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x * 2) as u8, (y * 2) as u8, ((x * y) % 256) as u8])
+        })
+    }
+
+    /// Photo-like carrier for the robustness harness: smooth gradients under mild texture
+    fn robustness_carrier(width: u32, height: u32) -> RgbImage {
+        let mut noise_state = 0x9e37_79b9u32;
+        RgbImage::from_fn(width, height, |x, y| {
+            noise_state = noise_state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let texture = (noise_state >> 28) as u8;
+            Rgb([
+                (60 + x * 120 / width) as u8 + texture,
+                (70 + y * 110 / height) as u8 + texture,
+                (90 + (x + y) * 40 / (width + height)) as u8 + texture,
+            ])
+        })
+    }
+
+    /// Corrupts an image the way a sharing platform does, by re-encoding it as a JPEG
+    fn recompress_as_jpeg(
+        stego_engine: &SteganographyEngine,
+        steganographic_image: &RgbImage,
+        jpeg_quality: u8,
+    ) -> RgbImage {
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(steganographic_image, jpeg_quality)
+            .unwrap();
+        image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8()
+    }
+
+    /// Hides `payload` tuned for `embedding_quality`, recompresses the result at every sweep
+    /// quality and returns the raw bit error rate of the payload bits at each one
+    fn recompression_bit_error_rates(
+        stego_engine: &SteganographyEngine,
+        carrier_image: &RgbImage,
+        payload: &[u8],
+        embedding_quality: u8,
+    ) -> Vec<(u8, f64)> {
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(carrier_image, payload, embedding_quality)
+            .unwrap();
+        let embedded_bits = stego_engine.convert_data_to_bits(payload);
+
+        ROBUSTNESS_SWEEP_QUALITIES
+            .iter()
+            .map(|&jpeg_quality| {
+                let recompressed_image =
+                    recompress_as_jpeg(stego_engine, &steganographic_image, jpeg_quality);
+                let extracted_bits = stego_engine.extract_raw_bits(&recompressed_image).unwrap();
+                (
+                    jpeg_quality,
+                    bit_error_rate(&embedded_bits, &extracted_bits[..embedded_bits.len()]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_robustness_sweep_from_quality_50_to_95() {
+        let stego_engine = SteganographyEngine::new();
+        let carrier_image = robustness_carrier(384, 384);
+        let payload: Vec<u8> = (0..240u8).map(|i| i.wrapping_mul(151) ^ 0x5a).collect();
+
+        // Tuned for quality 75, the payload survives the whole sweep untouched
+        let tuned_for_75 =
+            recompression_bit_error_rates(&stego_engine, &carrier_image, &payload, 75);
+        for (jpeg_quality, bit_error_rate) in tuned_for_75 {
+            assert_eq!(bit_error_rate, 0.0, "quality {}", jpeg_quality);
+        }
+
+        // Tuned for the default 85, only recompression well below it costs bits
+        let tuned_for_85 =
+            recompression_bit_error_rates(&stego_engine, &carrier_image, &payload, 85);
+        for (jpeg_quality, bit_error_rate) in tuned_for_85 {
+            let error_budget = if jpeg_quality >= 65 { 0.0 } else { 0.1 };
+            assert!(
+                bit_error_rate <= error_budget,
+                "quality {}: bit error rate {}",
+                jpeg_quality,
+                bit_error_rate
+            );
+        }
+
+        // Repetition coding absorbs the scattered errors down to quality 55, so the full
+        // pipeline recovers the message; the few percent of bits flipped at 50 can outvote
+        // five copies
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = [0x5a; 32];
+        let encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, b"robust at every quality")
+            .unwrap();
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&carrier_image, &encrypted_data, 85)
+            .unwrap();
+        for jpeg_quality in ROBUSTNESS_SWEEP_QUALITIES
+            .into_iter()
+            .filter(|&jpeg_quality| jpeg_quality >= 55)
+        {
+            let recompressed_image =
+                recompress_as_jpeg(&stego_engine, &steganographic_image, jpeg_quality);
+            let extraction_result = stego_engine
+                .extract_data_from_rgb_image(&recompressed_image, None)
+                .unwrap();
+            let decrypted_data = crypto_engine
+                .decrypt_with_bit_reliability(
+                    &encryption_key,
+                    &extraction_result.data,
+                    &extraction_result.bit_confidences,
+                )
+                .unwrap_or_else(|error| panic!("quality {}: {}", jpeg_quality, error));
+            assert_eq!(decrypted_data, b"robust at every quality");
+        }
+    }
+
+    /// Trivial algorithm that encodes each bit as a saturated block, bypassing the DCT
+    struct IdentityEmbeddingAlgorithm;
+
+    impl EmbeddingAlgorithm for IdentityEmbeddingAlgorithm {
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn embed_bit(
+            &self,
+            _block_index: usize,
+            luminance_block: &mut [[f32; 8]; 8],
+            bit_value: u8,
+            _jpeg_quality: u8,
+        ) -> Result<()> {
+            let luminance_value = if bit_value == 1 { 255.0 } else { 0.0 };
+            *luminance_block = [[luminance_value; 8]; 8];
+            Ok(())
+        }
+
+        fn extract_bit(
+            &self,
+            _block_index: usize,
+            luminance_block: &[[f32; 8]; 8],
+        ) -> Result<ExtractedBit> {
+            let mean_luminance = luminance_block.iter().flatten().sum::<f32>() / 64.0;
+            Ok(ExtractedBit {
+                bit_value: if mean_luminance > 128.0 { 1 } else { 0 },
+                confidence: ((mean_luminance - 128.0).abs() / 128.0).min(1.0),
+            })
+        }
+
+        fn capacity(&self, image_width: u32, image_height: u32) -> Result<usize> {
+            Ok((image_width as usize).div_ceil(8) * (image_height as usize).div_ceil(8))
+        }
+    }
+
     #[test]
     fn test_data_bit_conversion_roundtrip() {
         let stego_engine = SteganographyEngine::new();
         let test_data = b"Hello, World! This is a test message.";
-        let bit_stream = stego_engine.convert_data_to_bits_with_header(test_data);
-        let recovered_data = stego_engine
-            .convert_bits_to_data_with_header(&bit_stream)
-            .unwrap();
+        let bit_stream = stego_engine.convert_data_to_bits(test_data);
+        let recovered_data = stego_engine.convert_bits_to_data(&bit_stream);
         assert_eq!(test_data.to_vec(), recovered_data);
     }
 
@@ -662,7 +1950,1233 @@ mod tests {
     fn test_capacity_calculation() {
         let stego_engine = SteganographyEngine::new();
         let test_image: RgbImage = ImageBuffer::from_fn(64, 64, |_, _| Rgb([128, 128, 128]));
-        let capacity = stego_engine.calculate_capacity_bits(&test_image);
+        let capacity = stego_engine.calculate_capacity_bits(&test_image).unwrap();
         assert_eq!(capacity, 64); // 8x8 blocks = 64 bits capacity
     }
+
+    #[test]
+    fn test_expected_length_overflowing_the_bit_count_is_rejected() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage = ImageBuffer::from_fn(64, 64, |_, _| Rgb([128, 128, 128]));
+
+        assert!(matches!(
+            stego_engine.extract_data_from_rgb_image(&test_image, Some(usize::MAX)),
+            Err(SteganographyError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_block_coordinates_cover_partial_edge_blocks_in_raster_order() {
+        let stego_engine = SteganographyEngine::new();
+        let block_coordinates: Vec<_> = stego_engine.block_coordinates(20, 9).collect();
+
+        assert_eq!(
+            block_coordinates,
+            vec![(0, 0), (8, 0), (16, 0), (0, 8), (8, 8), (16, 8)]
+        );
+        assert_eq!(
+            block_coordinates.len(),
+            stego_engine
+                .calculate_capacity_bits(&RgbImage::new(20, 9))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_grid_visitation_orders() {
+        assert_eq!(
+            grid_visitation_order(BlockVisitation::RowMajor, &[0; 32], 3, 2),
+            vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+        );
+        assert_eq!(
+            grid_visitation_order(BlockVisitation::ColumnMajor, &[0; 32], 3, 2),
+            vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)]
+        );
+        assert_eq!(
+            grid_visitation_order(BlockVisitation::Zigzag, &[0; 32], 3, 2),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1), (2, 0), (2, 1)]
+        );
+        assert_eq!(
+            grid_visitation_order(BlockVisitation::Zigzag, &[0; 32], 3, 3)[..6],
+            [(0, 0), (1, 0), (0, 1), (0, 2), (1, 1), (2, 0)]
+        );
+    }
+
+    #[test]
+    fn test_block_visitation_keeps_header_region_row_major() {
+        let row_major_coordinates: Vec<_> = SteganographyEngine::new()
+            .block_coordinates(256, 200)
+            .collect();
+
+        for block_visitation in [
+            BlockVisitation::ColumnMajor,
+            BlockVisitation::Zigzag,
+            BlockVisitation::Keyed,
+        ] {
+            let stego_engine = SteganographyEngine::new()
+                .with_block_visitation(block_visitation)
+                .with_block_order_seed([5; 32]);
+            let block_coordinates: Vec<_> = stego_engine.block_coordinates(256, 200).collect();
+            let header_blocks = stego_engine.header_region_blocks();
+
+            assert_eq!(
+                block_coordinates[..header_blocks],
+                row_major_coordinates[..header_blocks]
+            );
+            assert_ne!(block_coordinates, row_major_coordinates);
+
+            let mut sorted_coordinates = block_coordinates.clone();
+            sorted_coordinates.sort_unstable_by_key(|&(block_x, block_y)| (block_y, block_x));
+            assert_eq!(sorted_coordinates, row_major_coordinates);
+        }
+    }
+
+    #[test]
+    fn test_configuration_fingerprint_tells_embedding_parameters_apart() {
+        let default_fingerprint = SteganographyEngine::new().configuration_fingerprint();
+        assert_eq!(
+            SteganographyEngine::new().configuration_fingerprint(),
+            default_fingerprint
+        );
+
+        let other_fingerprints = [
+            SteganographyEngine::new()
+                .with_macro_block_scale(2)
+                .configuration_fingerprint(),
+            SteganographyEngine::with_configuration(EmbeddingConfiguration {
+                embedding_positions: vec![(3, 2), (2, 3), (5, 1)],
+                ..EmbeddingConfiguration::default()
+            })
+            .configuration_fingerprint(),
+            SteganographyEngine::with_configuration(EmbeddingConfiguration {
+                embedding_strength: 40.0,
+                ..EmbeddingConfiguration::default()
+            })
+            .configuration_fingerprint(),
+            SteganographyEngine::with_algorithm(DctEmbeddingAlgorithm::with_keyed_positions(
+                EmbeddingConfiguration::default(),
+                [7; 32],
+            ))
+            .configuration_fingerprint(),
+            SteganographyEngine::with_configuration(EmbeddingConfiguration {
+                color_standard: ColorStandard::Bt709,
+                ..EmbeddingConfiguration::default()
+            })
+            .configuration_fingerprint(),
+        ];
+        for fingerprint in other_fingerprints {
+            assert_ne!(fingerprint, default_fingerprint);
+            assert_ne!(fingerprint, 0);
+        }
+
+        // Extraction with other parameters stops at the fingerprint instead of decoding garbage
+        let crypto_engine = CryptographicEngine::new();
+        let encrypted_data = crypto_engine
+            .encrypt_with_frame_options(
+                &[0x42; 32],
+                b"fingerprinted",
+                &FrameOptions {
+                    configuration_fingerprint: Some(default_fingerprint),
+                    ..FrameOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(crypto_engine
+            .require_configuration_fingerprint(&encrypted_data, default_fingerprint)
+            .is_ok());
+        assert!(matches!(
+            crypto_engine.require_configuration_fingerprint(&encrypted_data, other_fingerprints[0]),
+            Err(SteganographyError::InvalidInput(message)) if message.starts_with("configuration mismatch")
+        ));
+    }
+
+    #[test]
+    fn test_keyed_block_order_is_deterministic_and_spreads_the_body() {
+        let keyed_coordinates = |block_order_seed: [u8; 32]| -> Vec<(u32, u32)> {
+            SteganographyEngine::new()
+                .with_block_visitation(BlockVisitation::Keyed)
+                .with_block_order_seed(block_order_seed)
+                .block_coordinates(256, 200)
+                .collect()
+        };
+        let header_blocks = SteganographyEngine::new().header_region_blocks();
+
+        assert_eq!(keyed_coordinates([5; 32]), keyed_coordinates([5; 32]));
+        assert_ne!(
+            keyed_coordinates([5; 32])[header_blocks..],
+            keyed_coordinates([6; 32])[header_blocks..]
+        );
+
+        // Row-major order would fill the next rows down; the keyed order reaches the bottom half
+        let first_body_blocks = &keyed_coordinates([5; 32])[header_blocks..header_blocks + 32];
+        assert!(first_body_blocks.iter().any(|&(_, block_y)| block_y >= 100));
+    }
+
+    #[test]
+    fn test_recorded_block_visitation_drives_extraction() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        let test_image: RgbImage = ImageBuffer::from_fn(256, 256, |x, y| {
+            Rgb([(x / 2) as u8 + 40, (y / 2) as u8 + 40, 120])
+        });
+        let test_message = b"zigzag";
+
+        let encrypted_data = crypto_engine
+            .encrypt_with_frame_options(
+                &encryption_key,
+                test_message,
+                &FrameOptions {
+                    block_visitation: BlockVisitation::Zigzag,
+                    ..FrameOptions::default()
+                },
+            )
+            .unwrap();
+        let steganographic_image = SteganographyEngine::new()
+            .with_block_visitation(BlockVisitation::Zigzag)
+            .hide_data_in_rgb_image(&test_image, &encrypted_data, 85)
+            .unwrap();
+
+        let stego_engine = SteganographyEngine::new();
+        let block_visitation = stego_engine
+            .read_block_visitation(&steganographic_image)
+            .unwrap();
+        assert_eq!(block_visitation, BlockVisitation::Zigzag);
+
+        let extraction_result = stego_engine
+            .with_block_visitation(block_visitation)
+            .extract_data_from_rgb_image(&steganographic_image, None)
+            .unwrap();
+        let decrypted_data = crypto_engine
+            .decrypt_with_bit_reliability(
+                &encryption_key,
+                &extraction_result.data,
+                &extraction_result.bit_confidences,
+            )
+            .unwrap();
+        assert_eq!(decrypted_data, test_message);
+    }
+
+    #[test]
+    fn test_custom_algorithm_roundtrip() {
+        let stego_engine = SteganographyEngine::with_algorithm(IdentityEmbeddingAlgorithm);
+        let test_image: RgbImage = ImageBuffer::from_fn(128, 128, |_, _| Rgb([128, 128, 128]));
+        let test_data = b"Layered payload";
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, test_data, 85)
+            .unwrap();
+        let recovered_data = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+
+        assert_eq!(test_data.to_vec(), recovered_data);
+    }
+
+    #[test]
+    fn test_extraction_without_length_reads_full_blob() {
+        let stego_engine = SteganographyEngine::with_algorithm(IdentityEmbeddingAlgorithm);
+        let test_image: RgbImage = ImageBuffer::from_fn(128, 128, |_, _| Rgb([128, 128, 128]));
+        let test_data = b"Framed";
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, test_data, 85)
+            .unwrap();
+        let recovered_data = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, None)
+            .unwrap()
+            .data;
+
+        // 16x16 blocks hold 256 bits, all of which are returned for the crypto layer to frame
+        assert_eq!(recovered_data.len(), 32);
+        assert_eq!(test_data.to_vec(), recovered_data[..test_data.len()]);
+    }
+
+    #[test]
+    fn test_split_payload_reassembles_in_any_order_and_names_a_missing_part() {
+        let stego_engine = SteganographyEngine::with_algorithm(IdentityEmbeddingAlgorithm);
+        let cover_images: Vec<RgbImage> = (0..3)
+            .map(|_| ImageBuffer::from_fn(128, 128, |_, _| Rgb([128, 128, 128])))
+            .collect();
+        let test_data: Vec<u8> = (0..60).collect();
+
+        let steganographic_images = stego_engine
+            .hide_data_across_rgb_images(&cover_images, &test_data, 85)
+            .unwrap();
+        let shuffled_images = [
+            steganographic_images[2].clone(),
+            steganographic_images[0].clone(),
+            steganographic_images[1].clone(),
+        ];
+        let extraction_result = stego_engine
+            .extract_data_from_rgb_images(&shuffled_images)
+            .unwrap();
+        assert_eq!(extraction_result.data, test_data);
+        assert_eq!(extraction_result.bit_confidences.len(), test_data.len() * 8);
+
+        let missing_part_error = stego_engine
+            .extract_data_from_rgb_images(&shuffled_images[..2])
+            .unwrap_err();
+        assert!(missing_part_error
+            .to_string()
+            .contains("part index 1 of 3 is missing"));
+
+        // Three 32-byte carriers hold 24 bytes each once their part headers are written
+        assert!(matches!(
+            stego_engine.hide_data_across_rgb_images(&cover_images, &[0u8; 73], 85),
+            Err(SteganographyError::CapacityError { .. })
+        ));
+    }
+
+    /// Identity embedding that counts the blocks extraction reads
+    #[derive(Default)]
+    struct BlockCountingAlgorithm {
+        extracted_blocks: std::sync::atomic::AtomicUsize,
+    }
+
+    impl EmbeddingAlgorithm for BlockCountingAlgorithm {
+        fn block_size(&self) -> usize {
+            IdentityEmbeddingAlgorithm.block_size()
+        }
+
+        fn embed_bit(
+            &self,
+            block_index: usize,
+            luminance_block: &mut [[f32; 8]; 8],
+            bit_value: u8,
+            jpeg_quality: u8,
+        ) -> Result<()> {
+            IdentityEmbeddingAlgorithm.embed_bit(
+                block_index,
+                luminance_block,
+                bit_value,
+                jpeg_quality,
+            )
+        }
+
+        fn extract_bit(
+            &self,
+            block_index: usize,
+            luminance_block: &[[f32; 8]; 8],
+        ) -> Result<ExtractedBit> {
+            self.extracted_blocks
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            IdentityEmbeddingAlgorithm.extract_bit(block_index, luminance_block)
+        }
+
+        fn capacity(&self, image_width: u32, image_height: u32) -> Result<usize> {
+            IdentityEmbeddingAlgorithm.capacity(image_width, image_height)
+        }
+    }
+
+    #[test]
+    fn test_frame_extraction_reads_only_the_blocks_the_header_claims() {
+        let stego_engine = SteganographyEngine::with_algorithm(BlockCountingAlgorithm::default())
+            .with_block_visitation(BlockVisitation::ColumnMajor);
+        let test_image: RgbImage = ImageBuffer::from_fn(512, 512, |_, _| Rgb([128, 128, 128]));
+        let crypto_engine = CryptographicEngine::new();
+        let key = [0x42; 32];
+        let encrypted_data = crypto_engine
+            .encrypt_with_frame_options(
+                &key,
+                b"short",
+                &FrameOptions {
+                    block_visitation: BlockVisitation::ColumnMajor,
+                    ..FrameOptions::default()
+                },
+            )
+            .unwrap();
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &encrypted_data, 85)
+            .unwrap();
+        let extraction_result = stego_engine
+            .extract_frame_from_rgb_image(&steganographic_image, crypto_engine.repetition_factor())
+            .unwrap();
+
+        // The header is read once, then exactly the frame, out of 4096 blocks
+        let extracted_blocks = stego_engine
+            .embedding_algorithm
+            .extracted_blocks
+            .load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(extraction_result.data.len(), encrypted_data.len());
+        assert_eq!(
+            extracted_blocks,
+            (MAX_FRAME_HEADER_LENGTH + encrypted_data.len()) * 8
+        );
+        assert!(extracted_blocks < stego_engine.calculate_capacity_bits(&test_image).unwrap());
+        assert_eq!(
+            crypto_engine
+                .decrypt_with_bit_reliability(
+                    &key,
+                    &extraction_result.data,
+                    &extraction_result.bit_confidences
+                )
+                .unwrap(),
+            b"short"
+        );
+    }
+
+    #[test]
+    fn test_extraction_confidence_reflects_vote_margin() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage = ImageBuffer::from_fn(64, 64, |x, y| {
+            Rgb([(x * 3) as u8 + 40, (y * 3) as u8 + 40, 120])
+        });
+        let test_data = b"conf";
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, test_data, 85)
+            .unwrap();
+        let extraction_result = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap();
+
+        assert_eq!(test_data.to_vec(), extraction_result.data);
+        assert_eq!(extraction_result.bit_confidences.len(), test_data.len() * 8);
+        assert!(extraction_result.confidence() > 0.9);
+    }
+
+    #[test]
+    fn test_padding_to_block_multiple_roundtrip() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage =
+            ImageBuffer::from_fn(61, 43, |x, y| Rgb([(x * 4) as u8, (y * 5) as u8, 90]));
+        let test_data = b"edge";
+
+        let padded_image = stego_engine.pad_to_block_multiple(&test_image);
+        assert_eq!(padded_image.dimensions(), (64, 48));
+        assert_eq!(padded_image.get_pixel(63, 47), test_image.get_pixel(60, 42));
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&padded_image, test_data, 85)
+            .unwrap();
+        let recovered_data = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        let cropped_image = stego_engine.crop_to_dimensions(&steganographic_image, 61, 43);
+
+        assert_eq!(test_data.to_vec(), recovered_data);
+        assert_eq!(cropped_image.dimensions(), (61, 43));
+    }
+
+    #[test]
+    fn test_grayscale_jpeg_is_single_channel() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: GrayImage = ImageBuffer::from_fn(40, 24, |x, y| Luma([(x * 6 + y) as u8]));
+
+        let jpeg_buffer = stego_engine
+            .encode_grayscale_image_as_jpeg(&test_image, 90)
+            .unwrap();
+        let decoded_image = image::load_from_memory(&jpeg_buffer).unwrap();
+
+        assert_eq!(decoded_image.color(), image::ColorType::L8);
+        assert_eq!((decoded_image.width(), decoded_image.height()), (40, 24));
+    }
+
+    #[test]
+    fn test_keyed_positions_roundtrip() {
+        let position_seed = [42u8; 32];
+        let stego_engine =
+            SteganographyEngine::with_algorithm(DctEmbeddingAlgorithm::with_keyed_positions(
+                EmbeddingConfiguration::default(),
+                position_seed,
+            ));
+        let test_image: RgbImage =
+            ImageBuffer::from_fn(128, 128, |x, y| Rgb([x as u8 + 60, y as u8 + 60, 100]));
+        let test_data = b"keyed positions";
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, test_data, 85)
+            .unwrap();
+        let recovered_data = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        assert_eq!(test_data.to_vec(), recovered_data);
+
+        // Reading the fixed default positions does not recover the payload
+        let unkeyed_data = SteganographyEngine::new()
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        assert_ne!(test_data.to_vec(), unkeyed_data);
+    }
+
+    #[test]
+    fn test_bit_error_rate_after_recompression() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage =
+            ImageBuffer::from_fn(64, 64, |x, y| Rgb([x as u8 + 90, y as u8 + 90, 110]));
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, b"raw bits", 75)
+            .unwrap();
+        let pristine_bits = stego_engine
+            .extract_raw_bits(&steganographic_image)
+            .unwrap();
+        assert_eq!(pristine_bits.len(), 64);
+        assert_eq!(
+            stego_engine.convert_bits_to_data(&pristine_bits[..64]),
+            b"raw bits"
+        );
+
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 75)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+        let recompressed_bits = stego_engine.extract_raw_bits(&recompressed_image).unwrap();
+
+        assert!(bit_error_rate(&pristine_bits, &recompressed_bits) < 0.1);
+        assert_eq!(bit_error_rate(&[0, 1, 1, 0], &[0, 1, 0, 1]), 0.5);
+        assert_eq!(bit_error_rate(&[], &[1]), 0.0);
+    }
+
+    #[test]
+    fn test_psnr_measures_embedding_distortion() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage =
+            ImageBuffer::from_fn(64, 64, |x, y| Rgb([x as u8 + 90, y as u8 + 90, 110]));
+        assert_eq!(
+            stego_engine.compute_psnr(&test_image, &test_image),
+            f64::INFINITY
+        );
+
+        // Every sample off by 16 gives 20 * log10(255 / 16)
+        let shifted_image: RgbImage =
+            ImageBuffer::from_fn(64, 64, |x, y| Rgb([x as u8 + 106, y as u8 + 74, 126]));
+        let shifted_psnr = stego_engine.compute_psnr(&test_image, &shifted_image);
+        assert!((shifted_psnr - 24.05).abs() < 0.01);
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, b"psnr", 75)
+            .unwrap();
+        let embedding_psnr = stego_engine.compute_psnr(&test_image, &steganographic_image);
+        assert!(embedding_psnr > shifted_psnr && embedding_psnr.is_finite());
+    }
+
+    #[test]
+    fn test_byte_error_count_includes_missing_bytes() {
+        assert_eq!(byte_error_count(b"frame", b"frame"), 0);
+        assert_eq!(byte_error_count(b"frame", b"frAmE"), 2);
+        assert_eq!(byte_error_count(b"frame", b"fra"), 2);
+        assert_eq!(byte_error_count(b"fra", b"frame"), 0);
+    }
+
+    #[test]
+    fn test_process_blocks_keeps_block_order() {
+        let block_indices: Vec<usize> = (0..1000).collect();
+        let doubled_indices = process_blocks(block_indices, |block_index| block_index * 2);
+        assert!(doubled_indices
+            .iter()
+            .enumerate()
+            .all(|(block_index, &doubled_index)| doubled_index == block_index * 2));
+    }
+
+    #[test]
+    fn test_protected_header_survives_corrupted_header_copy_and_body() {
+        let crypto_engine = CryptographicEngine::new();
+        let encryption_key = CryptographicEngine::generate_encryption_key();
+        // Short enough for the frame and two extra header copies to fit in 1024 blocks
+        let test_message = b"ok";
+        let encrypted_data = crypto_engine
+            .encrypt_with_error_correction(&encryption_key, test_message)
+            .unwrap();
+        let test_image: RgbImage = ImageBuffer::from_fn(256, 256, |_, _| Rgb([128, 128, 128]));
+
+        // Invert blocks so they read back as the opposite bit with the same confidence
+        let corrupt_blocks = |image: &mut RgbImage, block_indices: &[usize]| {
+            for &block_index in block_indices {
+                let block_x = (block_index % 32) as u32 * 8;
+                let block_y = (block_index / 32) as u32 * 8;
+                for pixel_y in block_y..block_y + 8 {
+                    for pixel_x in block_x..block_x + 8 {
+                        image
+                            .get_pixel_mut(pixel_x, pixel_y)
+                            .0
+                            .iter_mut()
+                            .for_each(|channel_value| *channel_value = 255 - *channel_value);
+                    }
+                }
+            }
+        };
+        let header_length = crypto_engine
+            .read_frame_header(&encrypted_data)
+            .unwrap()
+            .encoded_length();
+
+        let decrypt_corrupted = |header_copies: usize| {
+            let stego_engine = SteganographyEngine::with_algorithm(IdentityEmbeddingAlgorithm)
+                .with_header_copies(header_copies);
+            let mut steganographic_image = stego_engine
+                .hide_data_in_rgb_image(&test_image, &encrypted_data, 85)
+                .unwrap();
+
+            // Flip the first embedded copy of the header and one repetition of each body
+            // byte stored past the replicated region
+            let header_blocks: Vec<usize> = (0..header_length * 8).collect();
+            let body_blocks: Vec<usize> = (MAX_FRAME_HEADER_LENGTH..encrypted_data.len())
+                .filter(|byte_index| (byte_index - header_length).is_multiple_of(5))
+                .flat_map(|byte_index| {
+                    let first_block =
+                        byte_index * 8 + stego_engine.protected_header_overhead_bits();
+                    first_block..first_block + 8
+                })
+                .collect();
+            corrupt_blocks(&mut steganographic_image, &header_blocks);
+            corrupt_blocks(&mut steganographic_image, &body_blocks);
+
+            let extraction_result = stego_engine
+                .extract_data_from_rgb_image(&steganographic_image, None)
+                .unwrap();
+            crypto_engine.decrypt_with_bit_reliability(
+                &encryption_key,
+                &extraction_result.data,
+                &extraction_result.bit_confidences,
+            )
+        };
+
+        assert_eq!(
+            decrypt_corrupted(DEFAULT_PROTECTED_HEADER_COPIES).unwrap(),
+            test_message.to_vec()
+        );
+        assert!(decrypt_corrupted(1)
+            .map(|decrypted_data| decrypted_data != test_message.to_vec())
+            .unwrap_or(true));
+    }
+
+    #[test]
+    fn test_repeated_extraction_is_bit_identical() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image = gradient_carrier(128, 128);
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(37)).collect();
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 85)
+            .unwrap();
+        let extract = || {
+            let extraction_result = stego_engine
+                .extract_data_from_rgb_image(&steganographic_image, None)
+                .unwrap();
+            let confidence_bits: Vec<u32> = extraction_result
+                .bit_confidences
+                .iter()
+                .map(|confidence| confidence.to_bits())
+                .collect();
+            (extraction_result.data, confidence_bits)
+        };
+
+        let first_extraction = extract();
+        assert_eq!(first_extraction.0, test_data);
+        for _ in 0..100 {
+            assert_eq!(extract(), first_extraction);
+        }
+    }
+
+    #[test]
+    fn test_engine_is_shared_across_threads_without_locking() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SteganographyEngine>();
+
+        let stego_engine = SteganographyEngine::new();
+        let test_image = gradient_carrier(128, 128);
+
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(37)).collect();
+        let roundtrip = || {
+            let steganographic_image = stego_engine
+                .hide_data_in_rgb_image(&test_image, &test_data, 85)
+                .unwrap();
+            stego_engine
+                .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+                .unwrap()
+                .data
+        };
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| scope.spawn(roundtrip)).collect();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), test_data);
+            }
+        });
+    }
+
+    #[test]
+    fn test_macro_blocks_survive_downscale_that_destroys_plain_blocks() {
+        use image::imageops::{resize, FilterType};
+
+        let test_image: RgbImage = ImageBuffer::from_fn(512, 512, |x, y| {
+            Rgb([(x / 2) as u8, (y / 2) as u8, ((x + y) / 4) as u8])
+        });
+        let test_data: Vec<u8> = (0..24u8).map(|i| i.wrapping_mul(53) ^ 0x5a).collect();
+        let halve_and_restore = |steganographic_image: &RgbImage| {
+            let downscaled_image = resize(steganographic_image, 256, 256, FilterType::Triangle);
+            resize(&downscaled_image, 512, 512, FilterType::Triangle)
+        };
+        let bit_error_rate_after_rescale = |stego_engine: &SteganographyEngine| {
+            let steganographic_image = stego_engine
+                .hide_data_in_rgb_image(&test_image, &test_data, 85)
+                .unwrap();
+            let rescaled_image = halve_and_restore(&steganographic_image);
+            let embedded_bits = stego_engine.convert_data_to_bits(&test_data);
+            let extracted_bits = stego_engine.extract_raw_bits(&rescaled_image).unwrap();
+            bit_error_rate(&embedded_bits, &extracted_bits[..embedded_bits.len()])
+        };
+
+        let macro_block_engine =
+            SteganographyEngine::new().with_macro_block_scale(DEFAULT_MACRO_BLOCK_SCALE);
+        assert_eq!(
+            macro_block_engine
+                .calculate_capacity_bits(&test_image)
+                .unwrap(),
+            256
+        );
+        assert_eq!(bit_error_rate_after_rescale(&macro_block_engine), 0.0);
+        assert!(bit_error_rate_after_rescale(&SteganographyEngine::new()) > 0.2);
+    }
+
+    #[test]
+    fn test_sixteen_pixel_dct_blocks_roundtrip_through_jpeg() {
+        use image::imageops::{resize, FilterType};
+
+        let stego_engine = SteganographyEngine::<DctEmbeddingAlgorithm<16>, 16>::default();
+        let test_image = gradient_carrier(256, 256);
+        let test_data = b"16x16 DCT blocks";
+        assert_eq!(
+            stego_engine.calculate_capacity_bits(&test_image).unwrap(),
+            256
+        );
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, test_data, 85)
+            .unwrap();
+        let recovered_data = stego_engine
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        assert_eq!(test_data.to_vec(), recovered_data);
+
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 85)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+        let recovered_data = stego_engine
+            .extract_data_from_rgb_image(&recompressed_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        assert_eq!(test_data.to_vec(), recovered_data);
+
+        // The coefficients sit at half the spatial frequency of 8x8 blocks, so halving the
+        // image and scaling it back up doesn't erase them
+        let downscaled_image = resize(&steganographic_image, 128, 128, FilterType::Triangle);
+        let rescaled_image = resize(&downscaled_image, 256, 256, FilterType::Triangle);
+        let embedded_bits = stego_engine.convert_data_to_bits(test_data);
+        let extracted_bits = stego_engine.extract_raw_bits(&rescaled_image).unwrap();
+        assert_eq!(
+            bit_error_rate(&embedded_bits, &extracted_bits[..embedded_bits.len()]),
+            0.0
+        );
+
+        // An 8x8 transform over 2x2-pixel cells covers the same squares, so only the
+        // fingerprint tells the two apart
+        assert_ne!(
+            SteganographyEngine::new()
+                .with_macro_block_scale(2)
+                .configuration_fingerprint(),
+            stego_engine.configuration_fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_noise_floor_calibration_beats_fixed_threshold_on_attenuated_image() {
+        let mut stego_engine = SteganographyEngine::new();
+        let test_image = gradient_carrier(128, 128);
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(73)).collect();
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 85)
+            .unwrap();
+
+        // Flatten contrast so embedded coefficients fall under the fixed threshold, then add
+        // deterministic pixel noise
+        const ATTENUATION: f32 = 0.3;
+        const NOISE_RANGE: u32 = 10;
+        let mut noise_state = 0x2545_f491u32;
+        let attenuated_image = RgbImage::from_fn(128, 128, |x, y| {
+            let Rgb(channels) = *steganographic_image.get_pixel(x, y);
+            Rgb(channels.map(|channel_value| {
+                noise_state = noise_state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let noise = ((noise_state >> 16) % NOISE_RANGE) as f32 - (NOISE_RANGE / 2) as f32;
+                (128.0 + (channel_value as f32 - 128.0) * ATTENUATION + noise)
+                    .round()
+                    .clamp(0.0, 255.0) as u8
+            }))
+        });
+
+        let embedded_bits = stego_engine.convert_data_to_bits(&test_data);
+        let fixed_threshold_bits = stego_engine.extract_raw_bits(&attenuated_image).unwrap();
+        let noise_floor_estimate = stego_engine
+            .calibrate_noise_floor(&attenuated_image)
+            .unwrap()
+            .unwrap();
+        let calibrated_bits = stego_engine.extract_raw_bits(&attenuated_image).unwrap();
+
+        let fixed_error_rate = bit_error_rate(&embedded_bits, &fixed_threshold_bits);
+        let calibrated_error_rate = bit_error_rate(&embedded_bits, &calibrated_bits);
+        assert!(noise_floor_estimate.vote_threshold < noise_floor_estimate.embedded_amplitude);
+        assert!(calibrated_error_rate < fixed_error_rate);
+
+        // Calibrating on an untouched image still recovers every bit
+        stego_engine
+            .calibrate_noise_floor(&steganographic_image)
+            .unwrap();
+        let pristine_bits = stego_engine
+            .extract_raw_bits(&steganographic_image)
+            .unwrap();
+        assert_eq!(bit_error_rate(&embedded_bits, &pristine_bits), 0.0);
+    }
+
+    #[test]
+    fn test_prior_embedding_is_detected_in_reused_carrier() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image = gradient_carrier(128, 128);
+        let mut noise_state = 0x9e37_79b9u32;
+        let noise_image = RgbImage::from_fn(128, 128, |_, _| {
+            noise_state = noise_state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            Rgb([(noise_state >> 24) as u8; 3])
+        });
+        assert!(!stego_engine.detect_prior_embedding(&test_image).unwrap());
+        assert!(!stego_engine.detect_prior_embedding(&noise_image).unwrap());
+
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(73)).collect();
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 75)
+            .unwrap();
+        assert!(stego_engine
+            .detect_prior_embedding(&steganographic_image)
+            .unwrap());
+
+        // The signature survives the recompression a shared image goes through
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 75)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+        assert!(stego_engine
+            .detect_prior_embedding(&recompressed_image)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_payload_presence_separates_clean_and_embedded_images() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage = ImageBuffer::from_fn(256, 256, |x, y| {
+            Rgb([x as u8, y as u8, ((x * y) % 256) as u8])
+        });
+        let mut noise_state = 0x2545_f491u32;
+        let noise_image = RgbImage::from_fn(256, 256, |_, _| {
+            noise_state = noise_state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            Rgb([(noise_state >> 24) as u8; 3])
+        });
+        assert!(stego_engine.detect_payload_presence(&test_image).unwrap() < 0.2);
+        assert!(stego_engine.detect_payload_presence(&noise_image).unwrap() < 0.2);
+
+        // Half the blocks carry bits, and the signature survives recompression
+        let test_data: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(37)).collect();
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 75)
+            .unwrap();
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 75)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+        assert!(
+            stego_engine
+                .detect_payload_presence(&recompressed_image)
+                .unwrap()
+                > 0.8
+        );
+    }
+
+    #[test]
+    fn test_chroma_channels_triple_capacity_and_survive_recompression() {
+        let test_image: RgbImage =
+            ImageBuffer::from_fn(128, 128, |x, y| Rgb([x as u8 + 60, y as u8 + 60, 110]));
+        let luma_capacity = SteganographyEngine::new()
+            .calculate_capacity_bits(&test_image)
+            .unwrap();
+
+        let stego_engine = SteganographyEngine::with_configuration(EmbeddingConfiguration {
+            embedding_channel: EmbeddingChannel::All,
+            ..EmbeddingConfiguration::default()
+        });
+        let capacity = stego_engine.calculate_capacity_bits(&test_image).unwrap();
+        assert_eq!(capacity, luma_capacity * 3);
+
+        // Fill every channel so the Cb and Cr blocks carry bits too
+        let test_data: Vec<u8> = (0..capacity / 8)
+            .map(|i| (i as u8).wrapping_mul(73))
+            .collect();
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 85)
+            .unwrap();
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 85)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+
+        let embedded_bits = stego_engine.convert_data_to_bits(&test_data);
+        let recovered_bits = stego_engine.extract_raw_bits(&recompressed_image).unwrap();
+        assert_eq!(recovered_bits.len(), capacity);
+        assert!(bit_error_rate(&embedded_bits, &recovered_bits) < 0.01);
+    }
+
+    #[test]
+    fn test_adaptive_strength_is_gentler_on_flat_areas_and_still_survives_jpeg() {
+        // A smooth sky-like gradient above a noisy textured band
+        let mut noise_state = 0x1234_5678u32;
+        let test_image = RgbImage::from_fn(128, 128, |x, y| {
+            noise_state = noise_state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            if y < 64 {
+                Rgb([100, 150 + (x / 8) as u8, 220])
+            } else {
+                Rgb([88 + (noise_state >> 24) as u8 / 3; 3])
+            }
+        });
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(73)).collect();
+
+        let fixed_engine = SteganographyEngine::new();
+        let adaptive_engine = SteganographyEngine::with_configuration(EmbeddingConfiguration {
+            adaptive_strength: AdaptiveStrength::PHOTOGRAPHIC,
+            ..EmbeddingConfiguration::default()
+        });
+        let fixed_image = fixed_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 95)
+            .unwrap();
+        let adaptive_image = adaptive_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 95)
+            .unwrap();
+        let flat_area_psnr = |steganographic_image: &RgbImage| {
+            let flat_area =
+                |image: &RgbImage| image::imageops::crop_imm(image, 0, 0, 128, 64).to_image();
+            fixed_engine.compute_psnr(&flat_area(&test_image), &flat_area(steganographic_image))
+        };
+        assert!(flat_area_psnr(&adaptive_image) > flat_area_psnr(&fixed_image) + 1.0);
+
+        let jpeg_buffer = adaptive_engine
+            .encode_rgb_image_as_jpeg(&adaptive_image, 95)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+        let embedded_bits = adaptive_engine.convert_data_to_bits(&test_data);
+        let recovered_bits = adaptive_engine
+            .extract_raw_bits(&recompressed_image)
+            .unwrap();
+        assert_eq!(
+            bit_error_rate(&embedded_bits, &recovered_bits[..embedded_bits.len()]),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_vote_threshold_scales_with_a_stronger_embedding() {
+        let configuration = EmbeddingConfiguration {
+            embedding_strength: 60.0,
+            ..EmbeddingConfiguration::default()
+        };
+        assert!((EmbeddingConfiguration::default().vote_threshold() - 6.4).abs() < 1e-5);
+        assert_eq!(configuration.vote_threshold(), 24.0);
+
+        let test_image = RgbImage::from_fn(128, 128, |x, y| {
+            Rgb([
+                ((x + y) / 2) as u8 + 40,
+                120 + (x / 4) as u8,
+                90 + (y / 4) as u8,
+            ])
+        });
+        let test_data: Vec<u8> = (0..24u8).map(|i| i.wrapping_mul(37)).collect();
+        let stego_engine = SteganographyEngine::with_configuration(configuration);
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 75)
+            .unwrap();
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 75)
+            .unwrap();
+        let recompressed_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+
+        let embedded_bits = stego_engine.convert_data_to_bits(&test_data);
+        let recovered_bits = stego_engine.extract_raw_bits(&recompressed_image).unwrap();
+        assert_eq!(
+            bit_error_rate(&embedded_bits, &recovered_bits[..embedded_bits.len()]),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_image_smaller_than_a_block_is_rejected_with_minimum_size() {
+        let stego_engine = SteganographyEngine::new();
+        let tiny_image = RgbImage::from_pixel(4, 4, Rgb([120, 130, 140]));
+
+        // 4 bytes are 32 bits, which need 6x6 blocks
+        let hide_error = stego_engine
+            .hide_data_in_rgb_image(&tiny_image, &[1, 2, 3, 4], 85)
+            .unwrap_err();
+        assert!(matches!(hide_error, SteganographyError::InvalidInput(_)));
+        assert!(hide_error
+            .to_string()
+            .contains("image is 4x4 pixels, smaller than one 8x8 block; hiding 32 bits needs at least 48x48 pixels"));
+
+        let narrow_image = GrayImage::from_pixel(64, 7, Luma([128]));
+        assert!(matches!(
+            stego_engine.hide_data_in_grayscale_image(&narrow_image, &[1], 85),
+            Err(SteganographyError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_embedding_changes_luminance_without_a_color_cast() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image: RgbImage = ImageBuffer::from_pixel(128, 128, Rgb([128, 128, 128]));
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(89) ^ 0x3c).collect();
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 85)
+            .unwrap();
+        // Changes to AC coefficients average out over a block, so compare pixel by pixel
+        let chroma_shift_sums = test_image.pixels().zip(steganographic_image.pixels()).fold(
+            [0.0f64; 2],
+            |shift_sums, (original_pixel, embedded_pixel)| {
+                let [_, original_blue, original_red] = rgb_to_ycbcr(original_pixel);
+                let [_, embedded_blue, embedded_red] = rgb_to_ycbcr(embedded_pixel);
+                [
+                    shift_sums[0] + (embedded_blue - original_blue).abs() as f64,
+                    shift_sums[1] + (embedded_red - original_red).abs() as f64,
+                ]
+            },
+        );
+        let pixel_count = test_image.pixels().len() as f64;
+        assert!(chroma_shift_sums[0] / pixel_count < 0.01);
+        assert!(chroma_shift_sums[1] / pixel_count < 0.01);
+        assert!(stego_engine.compute_psnr(&test_image, &steganographic_image) < f64::INFINITY);
+        assert_eq!(
+            stego_engine
+                .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+                .unwrap()
+                .data,
+            test_data
+        );
+    }
+
+    #[test]
+    fn test_bt709_luminance_roundtrips_and_weighs_green_more() {
+        let bt709_engine = SteganographyEngine::with_configuration(EmbeddingConfiguration {
+            color_standard: ColorStandard::Bt709,
+            ..EmbeddingConfiguration::default()
+        });
+        let test_image = robustness_carrier(128, 128);
+        let test_data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(41) ^ 0x99).collect();
+
+        let steganographic_image = bt709_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 85)
+            .unwrap();
+        let recovered_data = bt709_engine
+            .extract_data_from_rgb_image(&steganographic_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        assert_eq!(recovered_data, test_data);
+
+        for color_standard in [ColorStandard::Bt601, ColorStandard::Bt709] {
+            let weight_sum: f32 = color_standard.luminance_weights().iter().sum();
+            assert!((weight_sum - 1.0).abs() < 1e-6);
+        }
+        let green_pixel = Rgb([0, 255, 0]);
+        assert!(
+            rgb_luminance(&green_pixel, ColorStandard::Bt709)
+                > rgb_luminance(&green_pixel, ColorStandard::Bt601)
+        );
+    }
+
+    #[test]
+    fn test_dynamic_image_keeps_alpha_and_grayscale() {
+        let stego_engine = SteganographyEngine::new();
+        let test_data: Vec<u8> = (0..24u8).map(|i| i.wrapping_mul(97) ^ 0x21).collect();
+        let color_image = robustness_carrier(128, 128);
+        let rgba_image = RgbaImage::from_fn(128, 128, |x, y| {
+            let Rgb([red, green, blue]) = *color_image.get_pixel(x, y);
+            Rgba([red, green, blue, (x + y) as u8])
+        });
+        let gray_image =
+            GrayImage::from_fn(128, 128, |x, y| Luma([color_image.get_pixel(x, y)[1]]));
+
+        let rgba_stego = stego_engine
+            .hide_data_in_dynamic_image(
+                &DynamicImage::ImageRgba8(rgba_image.clone()),
+                &test_data,
+                85,
+            )
+            .unwrap();
+        let embedded_rgba = rgba_stego.as_rgba8().unwrap();
+        assert!(embedded_rgba
+            .pixels()
+            .zip(rgba_image.pixels())
+            .all(|(embedded_pixel, source_pixel)| embedded_pixel[3] == source_pixel[3]));
+
+        let gray_stego = stego_engine
+            .hide_data_in_dynamic_image(
+                &DynamicImage::ImageLuma8(gray_image.clone()),
+                &test_data,
+                85,
+            )
+            .unwrap();
+        assert!(gray_stego.as_luma8().is_some());
+        let gray_as_rgb = DynamicImage::ImageLuma8(gray_image).to_rgb8();
+        assert!(stego_engine
+            .hide_data_in_rgb_image(&gray_as_rgb, &test_data, 85)
+            .unwrap()
+            .pixels()
+            .all(|rgb_pixel| rgb_pixel[0] == rgb_pixel[1] && rgb_pixel[1] == rgb_pixel[2]));
+
+        let wide_stego = stego_engine
+            .hide_data_in_dynamic_image(
+                &DynamicImage::ImageRgb16(DynamicImage::ImageRgb8(color_image).to_rgb16()),
+                &test_data,
+                85,
+            )
+            .unwrap();
+        assert!(wide_stego.as_rgb8().is_some());
+
+        for steganographic_image in [rgba_stego, gray_stego, wide_stego] {
+            let recovered_data = stego_engine
+                .extract_data_from_dynamic_image(&steganographic_image, Some(test_data.len()))
+                .unwrap()
+                .data;
+            assert_eq!(recovered_data, test_data);
+        }
+    }
+
+    #[test]
+    fn test_ycbcr_conversion_roundtrips_rgb() {
+        for rgb_pixel in [
+            Rgb([0, 0, 0]),
+            Rgb([255, 255, 255]),
+            Rgb([255, 0, 0]),
+            Rgb([12, 200, 87]),
+        ] {
+            assert_eq!(ycbcr_to_rgb(rgb_to_ycbcr(&rgb_pixel)), rgb_pixel);
+        }
+    }
+
+    #[test]
+    fn test_alpha_channel_roundtrip_through_png() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image = RgbaImage::from_fn(32, 24, |x, y| {
+            image::Rgba([(x * 8) as u8, (y * 10) as u8, 77, u8::MAX])
+        });
+        let test_data = b"opaque alpha carrier";
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgba_alpha(&test_image, test_data)
+            .unwrap();
+        assert!(steganographic_image.pixels().zip(test_image.pixels()).all(
+            |(stego_pixel, cover_pixel)| stego_pixel.0[..3] == cover_pixel.0[..3]
+                && stego_pixel[3] >= OPAQUE_ALPHA_THRESHOLD
+        ));
+
+        let mut png_buffer = std::io::Cursor::new(Vec::new());
+        steganographic_image
+            .write_to(&mut png_buffer, image::ImageOutputFormat::Png)
+            .unwrap();
+        let decoded_image = image::load_from_memory(png_buffer.get_ref()).unwrap();
+        assert!(decoded_image.color().has_alpha());
+
+        let extraction_result = stego_engine
+            .extract_data_from_rgba_alpha(&decoded_image.to_rgba8(), None)
+            .unwrap();
+        assert_eq!(extraction_result.data.len(), 32 * 24 / 8);
+        assert_eq!(extraction_result.data[..test_data.len()], test_data[..]);
+    }
+
+    #[test]
+    fn test_alpha_channel_skips_translucent_pixels() {
+        let stego_engine = SteganographyEngine::new();
+        let test_image = RgbaImage::from_fn(16, 16, |x, _| {
+            image::Rgba([90, 90, 90, if x < 8 { u8::MAX } else { 128 }])
+        });
+        assert_eq!(stego_engine.calculate_alpha_capacity_bits(&test_image), 128);
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgba_alpha(&test_image, &[0xA5; 16])
+            .unwrap();
+        for (x, _, pixel) in steganographic_image.enumerate_pixels() {
+            if x >= 8 {
+                assert_eq!(pixel[3], 128);
+            }
+        }
+        assert!(matches!(
+            stego_engine.hide_data_in_rgba_alpha(&test_image, &[0; 17]),
+            Err(SteganographyError::CapacityError {
+                required: 136,
+                available: 128
+            })
+        ));
+    }
+
+    /// Committed output of the golden pipeline; regenerate with `STEG_UPDATE_GOLDEN=1`
+    const GOLDEN_JPEG_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/golden_keyed_q85.jpg"
+    );
+
+    #[test]
+    fn test_golden_jpeg_output_is_unchanged() {
+        let stego_engine =
+            SteganographyEngine::with_algorithm(DctEmbeddingAlgorithm::with_keyed_positions(
+                EmbeddingConfiguration::default(),
+                [0x5e; 32],
+            ));
+        let test_image = RgbImage::from_fn(96, 64, |x, y| {
+            Rgb([
+                (x * 2 + y) as u8,
+                (y * 3 + 20) as u8,
+                ((x ^ y) * 4 % 256) as u8,
+            ])
+        });
+        // One byte per row of blocks, filling all 96 blocks
+        let test_data: Vec<u8> = (0..12u8)
+            .map(|i| i.wrapping_mul(151).wrapping_add(7))
+            .collect();
+
+        let steganographic_image = stego_engine
+            .hide_data_in_rgb_image(&test_image, &test_data, 85)
+            .unwrap();
+        let jpeg_buffer = stego_engine
+            .encode_rgb_image_as_jpeg(&steganographic_image, 85)
+            .unwrap();
+        if std::env::var_os("STEG_UPDATE_GOLDEN").is_some() {
+            std::fs::write(GOLDEN_JPEG_PATH, &jpeg_buffer).unwrap();
+        }
+
+        let golden_jpeg = std::fs::read(GOLDEN_JPEG_PATH).unwrap();
+        let golden_image = image::load_from_memory(&golden_jpeg).unwrap().to_rgb8();
+        let produced_image = image::load_from_memory(&jpeg_buffer).unwrap().to_rgb8();
+        assert_eq!(produced_image.dimensions(), golden_image.dimensions());
+
+        // Compare decoded pixels with a small tolerance so encoder or float rounding
+        // differences across platforms don't fail the test, while real changes do
+        let total_difference: u64 = produced_image
+            .as_raw()
+            .iter()
+            .zip(golden_image.as_raw())
+            .map(|(&produced, &golden)| produced.abs_diff(golden) as u64)
+            .sum();
+        let mean_difference = total_difference as f64 / golden_image.as_raw().len() as f64;
+        assert!(
+            mean_difference < 0.1,
+            "output drifted from the golden JPEG by {:.2} per channel",
+            mean_difference
+        );
+
+        let golden_data = stego_engine
+            .extract_data_from_rgb_image(&golden_image, Some(test_data.len()))
+            .unwrap()
+            .data;
+        assert_eq!(golden_data, test_data);
+    }
 }
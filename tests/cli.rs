@@ -0,0 +1,1303 @@
+//! End-to-end tests that run the compiled `steg` binary against temporary files
+
+use assert_cmd::Command;
+use image::{Rgb, RgbImage};
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+use steg::crypto::{CryptographicEngine, KeyEncoding};
+use tempfile::TempDir;
+
+const SECRET_MESSAGE: &str = "meet at the old bridge";
+
+/// Saves a textured PNG carrier of the given size and returns its path
+fn write_carrier(directory: &Path, width: u32, height: u32) -> PathBuf {
+    let carrier_path = directory.join("carrier.png");
+    RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 3 % 256) as u8,
+            (y * 2 % 256) as u8,
+            ((x + y) % 256) as u8,
+        ])
+    })
+    .save(&carrier_path)
+    .unwrap();
+    carrier_path
+}
+
+/// Saves a key file holding `key_byte` repeated and returns its path
+fn write_key_file(directory: &Path, file_name: &str, key_byte: u8) -> PathBuf {
+    let key_path = directory.join(file_name);
+    CryptographicEngine::new()
+        .save_key_to_file(
+            &[key_byte; 32],
+            key_path.to_str().unwrap(),
+            KeyEncoding::Base64,
+        )
+        .unwrap();
+    key_path
+}
+
+/// Builds a command running the `steg` binary built for this test run
+fn steg() -> Command {
+    Command::cargo_bin("steg").unwrap()
+}
+
+/// Hides `SECRET_MESSAGE` in a 512x512 carrier with the given key, returning the output JPEG
+fn hide_secret_message(directory: &TempDir, key_path: &Path) -> PathBuf {
+    let carrier_path = write_carrier(directory.path(), 512, 512);
+    let output_path = directory.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Steganographic image saved to"));
+
+    output_path.with_extension("jpg")
+}
+
+#[test]
+fn hide_then_extract_recovers_message() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+    assert!(hidden_path.exists());
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+}
+
+#[test]
+fn hide_generates_key_file_next_to_output() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .assert()
+        .success();
+
+    let key_path = output_path.with_extension("key");
+    assert!(key_path.exists());
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(SECRET_MESSAGE));
+}
+
+#[test]
+fn extract_with_wrong_key_does_not_reveal_message() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let wrong_key_path = write_key_file(temp_dir.path(), "wrong.key", 0x17);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+
+    // Without authentication a wrong key still fails the plaintext checksum
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&wrong_key_path)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(SECRET_MESSAGE).not())
+        .stderr(predicate::str::contains("integrity check failed"));
+
+    // and --authenticated can't be demanded of a payload without a tag
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .arg("--authenticated")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not authenticated"));
+}
+
+#[test]
+fn authenticated_payload_fails_clearly_with_wrong_key() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let wrong_key_path = write_key_file(temp_dir.path(), "wrong.key", 0x17);
+    let output_path = temp_dir.path().join("hidden");
+    let hidden_path = output_path.with_extension("jpg");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .arg("--authenticated")
+        .assert()
+        .success();
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .arg("--authenticated")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&wrong_key_path)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("authentication failed"));
+}
+
+#[test]
+fn hide_with_missing_input_file_fails() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(temp_dir.path().join("missing.png"))
+        .arg("--output")
+        .arg(temp_dir.path().join("hidden"))
+        .args(["--data", SECRET_MESSAGE])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::starts_with("Error:"));
+}
+
+#[test]
+fn extract_with_missing_key_file_fails() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(temp_dir.path().join("missing.key"))
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::starts_with("Error:"));
+}
+
+#[test]
+fn hide_with_oversized_payload_reports_capacity() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 64, 64);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", &SECRET_MESSAGE.repeat(20)])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Insufficient capacity"));
+
+    assert!(!output_path.with_extension("jpg").exists());
+}
+
+#[test]
+fn hide_without_data_is_a_usage_error() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 64, 64);
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(temp_dir.path().join("hidden"))
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--data"));
+}
+
+#[test]
+fn binary_file_roundtrips_through_file_and_output_aliases() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let payload_path = temp_dir.path().join("payload.bin");
+    let recovered_path = temp_dir.path().join("recovered.bin");
+    let output_path = temp_dir.path().join("hidden");
+    let payload: Vec<u8> = (0..48u8).map(|i| i.wrapping_mul(151) ^ 0x80).collect();
+    std::fs::write(&payload_path, &payload).unwrap();
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--file")
+        .arg(&payload_path)
+        .arg("--key-file")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Payload content type: binary"));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .arg("--output")
+        .arg(&recovered_path)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read(&recovered_path).unwrap(), payload);
+
+    // A message and a file can't both be hidden
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--file")
+        .arg(&payload_path)
+        .assert()
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn capacity_reports_raw_bits_and_largest_message() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let max_message_length = CryptographicEngine::new().calculate_max_plaintext_length(512);
+
+    steg()
+        .arg("capacity")
+        .arg("--input")
+        .arg(&carrier_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Raw capacity: 4096 bits (512 bytes)",
+        ))
+        .stdout(predicate::str::contains("5x repetition coding"))
+        .stdout(predicate::str::contains(format!(
+            "Maximum message size: {} bytes",
+            max_message_length
+        )));
+}
+
+#[test]
+fn passphrase_roundtrips_without_key_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let output_path = temp_dir.path().join("hidden");
+    let hidden_path = output_path.with_extension("jpg");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .args(["--passphrase", "correct horse battery staple"])
+        .assert()
+        .success();
+    assert!(!output_path.with_extension("key").exists());
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .args(["--passphrase", "correct horse battery staple"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .args(["--passphrase", "wrong horse battery staple"])
+        .assert()
+        .stdout(predicate::str::contains(SECRET_MESSAGE).not());
+
+    // Extraction needs either a key or a passphrase
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .assert()
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn chroma_channels_fit_a_message_too_large_for_luma() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 256, 256);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+    let long_message = SECRET_MESSAGE.repeat(2);
+
+    let hide_in_channels = |channels: &str| {
+        steg()
+            .arg("hide")
+            .arg("--input")
+            .arg(&carrier_path)
+            .arg("--output")
+            .arg(&output_path)
+            .args(["--data", &long_message])
+            .arg("--key-file")
+            .arg(&key_path)
+            .args(["--channels", channels])
+            .assert()
+    };
+    hide_in_channels("luma")
+        .failure()
+        .stdout(predicate::str::contains("--channels all"));
+    hide_in_channels("all").success();
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .args(["--channels", "all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", long_message)));
+
+    steg()
+        .arg("capacity")
+        .arg("--input")
+        .arg(&carrier_path)
+        .args(["--channels", "all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Raw capacity: 3072 bits (384 bytes) across the Y, Cb, Cr channels, 1024 bits each",
+        ));
+}
+
+#[test]
+fn reed_solomon_payload_fits_where_repetition_does_not() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 256, 256);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+    let long_message = SECRET_MESSAGE.repeat(2);
+
+    let hide_with_ecc = |ecc: &str| {
+        steg()
+            .arg("hide")
+            .arg("--input")
+            .arg(&carrier_path)
+            .arg("--output")
+            .arg(&output_path)
+            .args(["--data", &long_message])
+            .arg("--key-file")
+            .arg(&key_path)
+            .args(["--ecc", ecc])
+            .assert()
+    };
+    hide_with_ecc("repetition")
+        .failure()
+        .stdout(predicate::str::contains("--ecc rs"));
+    hide_with_ecc("rs").success();
+
+    // The coding scheme is read from the frame header, so extract needs no flag
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", long_message)));
+}
+
+#[test]
+fn lower_repetition_factor_roundtrips_when_both_sides_agree() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 256, 256);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    let hide_with_repetition = |repetition: &str| {
+        steg()
+            .arg("hide")
+            .arg("--input")
+            .arg(&carrier_path)
+            .arg("--output")
+            .arg(&output_path)
+            .args(["--data", SECRET_MESSAGE])
+            .arg("--key-file")
+            .arg(&key_path)
+            .args(["--repetition", repetition, "--quality", "95"])
+            .assert()
+    };
+    hide_with_repetition("5").failure();
+    hide_with_repetition("3").success();
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .args(["--repetition", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .args(["--repetition", "4"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be odd"));
+}
+
+#[test]
+fn verify_reports_pass_without_writing_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+
+    steg()
+        .current_dir(temp_dir.path())
+        .arg("verify")
+        .arg("--input")
+        .arg(&carrier_path)
+        .args(["--data", SECRET_MESSAGE, "--quality", "90"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("PASS"));
+
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn report_quality_prints_psnr_and_warns_below_threshold() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(temp_dir.path().join("hidden"))
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .args(["--report-quality", "--min-psnr", "99"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Embedding PSNR:"))
+        .stdout(predicate::str::contains("Warning: PSNR is below 99.0 dB"));
+}
+
+#[test]
+fn sentinel_framing_roundtrips_without_a_length_header() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .args(["--framing", "sentinel"])
+        .assert()
+        .success();
+
+    let extract_with_framing = |framing: &str| {
+        steg()
+            .arg("extract")
+            .arg("--input")
+            .arg(output_path.with_extension("jpg"))
+            .arg("--key")
+            .arg(&key_path)
+            .args(["--framing", framing])
+            .assert()
+    };
+    extract_with_framing("sentinel")
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+    extract_with_framing("header").failure();
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .args(["--framing", "sentinel", "--compress"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn lossless_formats_keep_the_requested_extension() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+
+    let hide_as = |output_name: &str, extra_arguments: &[&str]| {
+        steg()
+            .arg("hide")
+            .arg("--input")
+            .arg(&carrier_path)
+            .arg("--output")
+            .arg(temp_dir.path().join(output_name))
+            .args(["--data", SECRET_MESSAGE])
+            .arg("--key-file")
+            .arg(&key_path)
+            .args(extra_arguments)
+            .assert()
+            .success()
+    };
+    hide_as("hidden.png", &["--quality", "100"])
+        .stdout(predicate::str::contains("ignored for png"));
+    hide_as("hidden", &["--format", "webp"]);
+
+    for (hidden_name, expected_format) in [
+        ("hidden.png", image::ImageFormat::Png),
+        ("hidden.webp", image::ImageFormat::WebP),
+    ] {
+        let hidden_path = temp_dir.path().join(hidden_name);
+        let hidden_bytes = std::fs::read(&hidden_path).unwrap();
+        assert_eq!(image::guess_format(&hidden_bytes).unwrap(), expected_format);
+
+        steg()
+            .arg("extract")
+            .arg("--input")
+            .arg(&hidden_path)
+            .arg("--key")
+            .arg(&key_path)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+    }
+    assert!(!temp_dir.path().join("hidden.png.jpg").exists());
+}
+
+#[test]
+fn detect_tells_clean_and_hidden_images_apart_without_a_key() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+
+    let detect = |image_path: &Path| steg().arg("detect").arg("--input").arg(image_path).assert();
+    detect(&temp_dir.path().join("carrier.png"))
+        .success()
+        .stdout(predicate::str::contains("no hidden data detected"));
+    detect(&hidden_path)
+        .success()
+        .stdout(predicate::str::contains("likely contains hidden data"));
+}
+
+#[test]
+fn adaptive_strength_roundtrips_without_extract_options() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .arg("--adaptive-strength")
+        .assert()
+        .success();
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+}
+
+#[test]
+fn custom_positions_must_match_between_hide_and_extract() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+    let custom_positions = "3,2;2,3;5,1";
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .args(["--positions", custom_positions])
+        .assert()
+        .success();
+
+    let extract_with_arguments = |extra_arguments: &[&str]| {
+        steg()
+            .arg("extract")
+            .arg("--input")
+            .arg(output_path.with_extension("jpg"))
+            .arg("--key")
+            .arg(&key_path)
+            .args(extra_arguments)
+            .assert()
+    };
+    extract_with_arguments(&["--positions", custom_positions])
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+    extract_with_arguments(&[])
+        .failure()
+        .stderr(predicate::str::contains("configuration mismatch"));
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .args(["--positions", "4,1;0,0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("DC coefficient"));
+}
+
+#[test]
+fn batch_hides_in_every_large_enough_image_with_one_key() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let input_dir = temp_dir.path().join("covers");
+    let output_dir = temp_dir.path().join("hidden");
+    std::fs::create_dir(&input_dir).unwrap();
+    std::fs::rename(
+        write_carrier(temp_dir.path(), 512, 512),
+        input_dir.join("large.png"),
+    )
+    .unwrap();
+    std::fs::rename(
+        write_carrier(temp_dir.path(), 32, 32),
+        input_dir.join("tiny.png"),
+    )
+    .unwrap();
+    std::fs::write(input_dir.join("notes.txt"), "not an image").unwrap();
+
+    steg()
+        .arg("batch")
+        .arg("--input-dir")
+        .arg(&input_dir)
+        .arg("--output-dir")
+        .arg(&output_dir)
+        .args(["--data", SECRET_MESSAGE])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("skipping"))
+        .stdout(predicate::str::contains(
+            "1 of 2 images hidden, 1 skipped as too small, 0 failed",
+        ));
+    assert!(!output_dir.join("tiny.jpg").exists());
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_dir.join("large.jpg"))
+        .arg("--key")
+        .arg(output_dir.join("batch.key"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+}
+
+#[test]
+fn json_mode_prints_only_a_json_object_on_stdout() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+    let hidden_path = output_path.with_extension("jpg");
+
+    let hide_output = steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .arg("--json")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Steganographic image saved to"))
+        .get_output()
+        .stdout
+        .clone();
+    let hide_json = String::from_utf8(hide_output).unwrap();
+    assert_eq!(hide_json.lines().count(), 1);
+    assert!(hide_json.starts_with(&format!(
+        "{{\"output_path\":\"{}\",\"key_path\":\"{}\",\"capacity_bits\":4096,",
+        hidden_path.display(),
+        key_path.display()
+    )));
+    assert!(hide_json.contains("\"psnr\":"));
+
+    steg()
+        .arg("--json")
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(format!(
+            "{{\"message\":\"{}\",\"bytes_extracted\":{},\"content_type\":\"text\",\"output_file\":null}}\n",
+            SECRET_MESSAGE,
+            SECRET_MESSAGE.len()
+        ));
+
+    steg()
+        .arg("capacity")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "only supported by hide and extract",
+        ));
+}
+
+#[test]
+fn larger_block_size_must_match_between_hide_and_extract() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 1024, 1024);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .args(["--block-size", "16"])
+        .assert()
+        .success();
+
+    let extract_with_arguments = |extra_arguments: &[&str]| {
+        steg()
+            .arg("extract")
+            .arg("--input")
+            .arg(output_path.with_extension("jpg"))
+            .arg("--key")
+            .arg(&key_path)
+            .args(extra_arguments)
+            .assert()
+    };
+    extract_with_arguments(&["--block-size", "16"])
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+    extract_with_arguments(&[])
+        .failure()
+        .stderr(predicate::str::contains(
+            "configuration mismatch: image was embedded with different parameters",
+        ));
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .args(["--block-size", "12"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected 8, 16 or 32"));
+
+    // Positions name coefficients of the larger block, so they can reach past the 8x8 range
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .args(["--positions", "10,2;2,10"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("outside the 8x8 block"));
+}
+
+#[test]
+fn keyed_block_visitation_roundtrips_with_the_key_only() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .args(["--block-visitation", "keyed"])
+        .assert()
+        .success();
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Block visitation: keyed"))
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(temp_dir.path().join("passphrase"))
+        .args(["--data", SECRET_MESSAGE])
+        .args(["--passphrase", "correct horse"])
+        .args(["--block-visitation", "keyed"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs a key file"));
+}
+
+#[test]
+fn dry_run_hide_checks_everything_but_writes_nothing() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let output_path = temp_dir.path().join("hidden");
+    let list_directory = || {
+        let mut file_names: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        file_names.sort();
+        file_names
+    };
+    let files_before = list_directory();
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Dry run: would generate a new encryption key",
+        ))
+        .stdout(predicate::str::contains(
+            "Dry run: would save steganographic image",
+        ));
+    assert_eq!(list_directory(), files_before);
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", &"x".repeat(4096)])
+        .arg("--dry-run")
+        .assert()
+        .failure();
+    assert_eq!(list_directory(), files_before);
+}
+
+#[test]
+fn progress_flag_keeps_piped_output_clean() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("--progress")
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+
+    // The bar only draws on a terminal, so piped stderr stays empty
+    steg()
+        .arg("extract")
+        .arg("--progress")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)))
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn rotate_key_re_encrypts_so_only_the_new_key_extracts() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+    let rotated_path = temp_dir.path().join("rotated.jpg");
+
+    steg()
+        .arg("rotate-key")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--old-key")
+        .arg(&key_path)
+        .arg("--output")
+        .arg(&rotated_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Generated new encryption key"))
+        .stdout(predicate::str::contains(
+            "Verified that the re-encrypted image extracts under the new key",
+        ))
+        .stdout(predicate::str::contains("Re-encrypted image saved to"));
+
+    let new_key_path = temp_dir.path().join("rotated.jpg.key");
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&rotated_path)
+        .arg("--key")
+        .arg(&new_key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&rotated_path)
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(SECRET_MESSAGE).not());
+
+    // Rotating to the key already in use would change nothing
+    steg()
+        .arg("rotate-key")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--old-key")
+        .arg(&key_path)
+        .arg("--new-key-file")
+        .arg(&key_path)
+        .arg("--output")
+        .arg(temp_dir.path().join("same.jpg"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("same as the old key"));
+}
+
+#[test]
+fn verify_key_reports_a_match_without_revealing_the_payload() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let wrong_key_path = write_key_file(temp_dir.path(), "wrong.key", 0x17);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+
+    steg()
+        .arg("verify-key")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Key matches: the payload's checksum verified",
+        ))
+        .stdout(predicate::str::contains(SECRET_MESSAGE).not());
+
+    steg()
+        .arg("verify-key")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&wrong_key_path)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Key matches").not())
+        .stderr(predicate::str::contains("integrity check failed"));
+}
+
+#[test]
+fn auto_quality_picks_a_quality_the_message_survives() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 512, 512);
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .arg("--auto-quality")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Auto quality: quality 100 survives",
+        ))
+        .stdout(predicate::str::contains(
+            "Auto quality: selected JPEG quality",
+        ));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(output_path.with_extension("jpg"))
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)));
+
+    steg()
+        .arg("hide")
+        .arg("--input")
+        .arg(&carrier_path)
+        .arg("--output")
+        .arg(temp_dir.path().join("lossless.png"))
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--auto-quality")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only applies to JPEG output"));
+}
+
+#[test]
+fn split_hide_spreads_a_message_that_only_every_part_recovers() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let cover_paths: Vec<PathBuf> = (1..=3)
+        .map(|cover_number| {
+            let cover_path = temp_dir.path().join(format!("cover{}.png", cover_number));
+            std::fs::rename(write_carrier(temp_dir.path(), 256, 256), &cover_path).unwrap();
+            cover_path
+        })
+        .collect();
+    let joined_paths = |paths: &[&PathBuf]| {
+        paths
+            .iter()
+            .map(|path| path.to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let output_path = temp_dir.path().join("hidden");
+
+    steg()
+        .arg("hide")
+        .args([
+            "--split",
+            &joined_paths(&cover_paths.iter().collect::<Vec<_>>()),
+        ])
+        .arg("--output")
+        .arg(&output_path)
+        .args(["--data", SECRET_MESSAGE])
+        .arg("--key-file")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Part 3 of 3 saved to"));
+
+    let part_paths: Vec<PathBuf> = (1..=3)
+        .map(|part_number| temp_dir.path().join(format!("hidden-{}.jpg", part_number)))
+        .collect();
+    steg()
+        .arg("extract")
+        .args([
+            "--split",
+            &joined_paths(&[&part_paths[2], &part_paths[0], &part_paths[1]]),
+        ])
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(SECRET_MESSAGE));
+
+    steg()
+        .arg("extract")
+        .args(["--split", &joined_paths(&[&part_paths[0], &part_paths[2]])])
+        .arg("--key")
+        .arg(&key_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("part index 1 of 3 is missing"));
+}
+
+#[test]
+fn raw_extract_dumps_decrypted_bytes_as_hex_or_base64() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .arg("--raw")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("00000000  6d 65 65 74"))
+        .stdout(predicate::str::contains("|meet at the old |"));
+
+    steg()
+        .arg("extract")
+        .arg("--input")
+        .arg(&hidden_path)
+        .arg("--key")
+        .arg(&key_path)
+        .args(["--raw", "base64"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bWVldCBhdCB0aGUgb2xkIGJyaWRnZQ=="))
+        .stdout(predicate::str::contains(format!("\"{}\"", SECRET_MESSAGE)).not());
+}
+
+#[test]
+fn info_reports_image_metadata_and_payload_likelihood_without_a_key() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let carrier_path = write_carrier(temp_dir.path(), 64, 48);
+
+    steg()
+        .arg("info")
+        .arg("--input")
+        .arg(&carrier_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Format: Png, Rgb8"))
+        .stdout(predicate::str::contains("Dimensions: 64x48 pixels"))
+        .stdout(predicate::str::contains("8x8 blocks: 48"))
+        .stdout(predicate::str::contains("Capacity: 48 bits (6 bytes)"))
+        .stdout(predicate::str::contains("Verdict: no hidden data detected"));
+
+    let key_path = write_key_file(temp_dir.path(), "secret.key", 0x42);
+    let hidden_path = hide_secret_message(&temp_dir, &key_path);
+    steg()
+        .arg("info")
+        .arg("--input")
+        .arg(&hidden_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Format: Jpeg"))
+        .stdout(predicate::str::contains("8x8 blocks: 4096"))
+        .stdout(predicate::str::contains(
+            "Verdict: likely contains hidden data",
+        ));
+}